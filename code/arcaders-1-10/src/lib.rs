@@ -0,0 +1,11 @@
+//! The `phi` engine on which the game is built. It is a library of its own,
+//! so that its whole API is available to the game, and to other games, even
+//! where this one does not use it yet.
+
+extern crate rand;
+extern crate rustc_serialize;
+extern crate sdl2;
+extern crate sdl2_image;
+extern crate sdl2_ttf;
+
+pub mod phi;
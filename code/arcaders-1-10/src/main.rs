@@ -1,14 +1,25 @@
+extern crate arcade_rs;
 extern crate rand;
+extern crate rustc_serialize;
 extern crate sdl2;
-extern crate sdl2_image;
-extern crate sdl2_ttf;
 
-mod phi;
+// The views refer to the engine as `::phi`, as they did when it was a module
+// of the game.
+use arcade_rs::phi;
+
 mod views;
 
 
 fn main() {
-    ::phi::spawn("ArcadeRS Shooter", |phi| {
-        Box::new(::views::main_menu::MainMenuView::new(phi))
+    let status = ::phi::spawn("ArcadeRS Shooter", |phi| {
+        // Where the saved game and the high scores have always been kept.
+        phi.save_dir = ::phi::save::config_dir("arcade-rs");
+        ::views::game::load_sounds(phi);
+
+        Box::new(::views::loading::LoadingView::new(::views::loading::game_assets(), Box::new(|phi| {
+            ::phi::ViewAction::ChangeView(Box::new(::views::main_menu::MainMenuView::new(phi)))
+        })))
     });
+
+    ::std::process::exit(status);
 }
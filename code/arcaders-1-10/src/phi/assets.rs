@@ -0,0 +1,311 @@
+use ::phi::Phi;
+use ::std::cell::RefCell;
+use ::std::collections::{HashMap, VecDeque};
+use ::std::fs::File;
+use ::std::io::{self, Read};
+use ::std::rc::Rc;
+use ::std::sync::mpsc::{self, Receiver, TryRecvError};
+use ::std::thread;
+
+
+/// The number of assets which `Preload::step` uploads per frame by default.
+pub const UPLOADS_PER_FRAME: usize = 4;
+
+
+/// The assets loaded so far, by path, so that every file is only read once and
+/// everything which uses it shares what was loaded from it. `Phi` keeps its
+/// textures in one; see `Phi::texture`.
+pub struct AssetCache<T> {
+    // The keys are normalized paths; see `normalize_path`.
+    assets: HashMap<String, Rc<RefCell<T>>>,
+}
+
+impl<T> AssetCache<T> {
+    pub fn new() -> AssetCache<T> {
+        AssetCache {
+            assets: HashMap::new(),
+        }
+    }
+
+    /// Returns the asset loaded from `path` if it is in the cache, and
+    /// otherwise calls `load` with the normalized path and caches what it
+    /// returns. Errors are not cached, so that the next call tries again.
+    pub fn get_or_load<F>(&mut self, path: &str, load: F) -> Result<Rc<RefCell<T>>, String>
+    where F: FnOnce(&str) -> Result<T, String> {
+        let key = normalize_path(path);
+
+        if let Some(asset) = self.assets.get(&key) {
+            return Ok(asset.clone());
+        }
+
+        let asset = Rc::new(RefCell::new(load(&key)?));
+        self.assets.insert(key, asset.clone());
+        Ok(asset)
+    }
+
+    /// Caches `asset` as if it had been loaded from `path`, replacing what was
+    /// loaded from it before, if anything.
+    pub fn insert(&mut self, path: &str, asset: T) -> Rc<RefCell<T>> {
+        let asset = Rc::new(RefCell::new(asset));
+        self.assets.insert(normalize_path(path), asset.clone());
+        asset
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.assets.contains_key(&normalize_path(path))
+    }
+
+    /// Removes the asset loaded from `path`. Those who still hold it keep it
+    /// alive, but the next call to `get_or_load` loads it again.
+    pub fn evict(&mut self, path: &str) {
+        self.assets.remove(&normalize_path(path));
+    }
+
+    pub fn clear(&mut self) {
+        self.assets.clear();
+    }
+
+    /// The number of assets in the cache.
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+}
+
+
+/// Returns the path with forward slashes only, and without redundant separators
+/// or `.` segments, so that the different spellings of a path map to the same
+/// texture. For instance, `assets\ship.png` and `./assets//ship.png` both
+/// become `assets/ship.png`.
+fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let absolute = path.starts_with('/');
+
+    let segments: Vec<&str> = path.split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect();
+
+    let normalized = segments.join("/");
+    if absolute { format!("/{}", normalized) } else { normalized }
+}
+
+
+
+/// Lists the images and fonts to load into the caches of `Phi` before they are
+/// needed, so that the views which use them do not freeze the window while
+/// they are read. See `views::loading::LoadingView`.
+#[derive(Clone, Debug, Default)]
+pub struct Preloader {
+    textures: Vec<String>,
+    fonts: Vec<(String, u16)>,
+}
+
+impl Preloader {
+    pub fn new() -> Preloader {
+        Preloader::default()
+    }
+
+    /// Adds the image at `path`, which `Phi::texture` and `Phi::sprite` then
+    /// find in the cache.
+    pub fn queue_texture(&mut self, path: &str) -> &mut Preloader {
+        self.textures.push(path.to_string());
+        self
+    }
+
+    /// Adds the font at `path`, at the given point size.
+    pub fn queue_font(&mut self, path: &str, size: u16) -> &mut Preloader {
+        self.fonts.push((path.to_string(), size));
+        self
+    }
+
+    /// The number of assets queued.
+    pub fn len(&self) -> usize {
+        self.textures.len() + self.fonts.len()
+    }
+
+    /// Starts reading the images on another thread. The assets then have to
+    /// be uploaded, on the main thread, with `Preload::step`.
+    pub fn start(self) -> Preload {
+        let total = self.len();
+        let (sender, files) = mpsc::channel();
+        let paths = self.textures;
+
+        thread::spawn(move || {
+            for path in paths {
+                let mut bytes = Vec::new();
+                let result = File::open(&path)
+                    .and_then(|mut file| file.read_to_end(&mut bytes))
+                    .map(|_| bytes);
+
+                // The preload is gone, so nobody needs the other files.
+                if sender.send((path, result)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Preload {
+            files: Some(files),
+            fonts: self.fonts.into_iter().collect(),
+            total: total,
+            loaded: 0,
+            failures: Vec::new(),
+        }
+    }
+}
+
+
+/// Assets being loaded, as started by `Preloader::start`.
+///
+/// SDL only creates textures on the main thread, so the images read by the
+/// worker thread are decoded and uploaded a few at a time, between frames.
+/// An asset which cannot be loaded does not stop the others: the error is
+/// kept in `failures`.
+pub struct Preload {
+    // `None` once every file was received, or the thread stopped early.
+    files: Option<Receiver<(String, io::Result<Vec<u8>>)>>,
+    fonts: VecDeque<(String, u16)>,
+    total: usize,
+    loaded: usize,
+    failures: Vec<String>,
+}
+
+impl Preload {
+    /// Uploads at most `budget` of the assets which are ready into the caches
+    /// of `phi`. The fonts are loaded once every image is.
+    pub fn step(&mut self, phi: &mut Phi, budget: usize) {
+        for _ in 0..budget {
+            if !self.step_once(phi) {
+                return;
+            }
+        }
+    }
+
+    // Returns whether an asset was loaded, or whether it failed to load.
+    fn step_once(&mut self, phi: &mut Phi) -> bool {
+        let received = match self.files {
+            Some(ref files) => Some(files.try_recv()),
+            None => None,
+        };
+
+        let result = match received {
+            Some(Ok((path, Ok(bytes)))) => phi.cache_texture(&path, &bytes).map(|_| ()),
+            Some(Ok((path, Err(e)))) => Err(format!("{}: {}", path, e)),
+            Some(Err(TryRecvError::Empty)) => return false,
+            Some(Err(TryRecvError::Disconnected)) => {
+                self.files = None;
+                // The thread stopped before sending every image: the views
+                // which need them load them again, and report their errors.
+                self.loaded = self.total - self.fonts.len();
+                return true;
+            },
+            None => match self.fonts.pop_front() {
+                Some((path, size)) => phi.load_font(&path, size),
+                None => return false,
+            },
+        };
+
+        if let Err(e) = result {
+            self.failures.push(e);
+        }
+
+        self.loaded += 1;
+        true
+    }
+
+    /// The fraction of the assets which were loaded, or failed to, in [0.0,
+    /// 1.0].
+    pub fn progress(&self) -> f64 {
+        if self.total == 0 { 1.0 } else { self.loaded as f64 / self.total as f64 }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.loaded >= self.total
+    }
+
+    /// Why the assets which could not be loaded failed to.
+    pub fn failures(&self) -> &[String] {
+        &self.failures
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::std::cell::Cell;
+
+    #[test]
+    fn same_path_is_loaded_once_and_shared() {
+        let mut cache = AssetCache::new();
+        let loads = Cell::new(0);
+        let load = |path: &str| {
+            loads.set(loads.get() + 1);
+            Ok(path.to_string())
+        };
+
+        let first = cache.get_or_load("assets/ship.png", &load).unwrap();
+        let second = cache.get_or_load("assets/ship.png", &load).unwrap();
+
+        assert!(Rc::ptr_eq(&first, &second));
+        assert_eq!(loads.get(), 1);
+        assert_eq!(*first.borrow(), "assets/ship.png");
+    }
+
+    #[test]
+    fn spellings_of_a_path_share_their_asset() {
+        let mut cache = AssetCache::new();
+        let first = cache.get_or_load("./assets//ship.png", |path| Ok(path.to_string())).unwrap();
+        let second = cache.get_or_load("assets\\ship.png", |_| Err("loaded twice".to_string())).unwrap();
+
+        assert!(Rc::ptr_eq(&first, &second));
+        // The loader is given the normalized path.
+        assert_eq!(*first.borrow(), "assets/ship.png");
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let mut cache: AssetCache<u32> = AssetCache::new();
+
+        assert!(cache.get_or_load("missing.png", |_| Err("not found".to_string())).is_err());
+        assert!(!cache.contains("missing.png"));
+        assert_eq!(*cache.get_or_load("missing.png", |_| Ok(3)).unwrap().borrow(), 3);
+    }
+
+    #[test]
+    fn evicted_asset_is_loaded_again() {
+        let mut cache = AssetCache::new();
+        let first = cache.get_or_load("a.png", |_| Ok(1)).unwrap();
+        cache.get_or_load("b.png", |_| Ok(2)).unwrap();
+
+        cache.evict("./a.png");
+        assert!(!cache.contains("a.png"));
+        assert!(cache.contains("b.png"));
+
+        let second = cache.get_or_load("a.png", |_| Ok(10)).unwrap();
+        assert!(!Rc::ptr_eq(&first, &second));
+        // Whoever held the evicted asset still has it.
+        assert_eq!(*first.borrow(), 1);
+
+        cache.clear();
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn inserted_asset_replaces_the_loaded_one() {
+        let mut cache = AssetCache::new();
+        cache.get_or_load("a.png", |_| Ok(1)).unwrap();
+        let inserted = cache.insert("a.png", 2);
+
+        assert!(Rc::ptr_eq(&inserted, &cache.get_or_load("a.png", |_| Ok(3)).unwrap()));
+    }
+
+    #[test]
+    fn paths_are_normalized() {
+        assert_eq!(normalize_path("assets\\ship.png"), "assets/ship.png");
+        assert_eq!(normalize_path("./assets//./ship.png"), "assets/ship.png");
+        assert_eq!(normalize_path("/usr//share/ship.png"), "/usr/share/ship.png");
+        // `..` is kept, since the folder it leaves may be a link.
+        assert_eq!(normalize_path("assets/../ship.png"), "assets/../ship.png");
+    }
+}
@@ -0,0 +1,516 @@
+use ::sdl2::AudioSubsystem;
+use ::sdl2::audio::{AudioCallback, AudioCVT, AudioDevice, AudioFormat, AudioSpecDesired, AudioSpecWAV};
+use ::std::collections::HashMap;
+use ::std::path::Path;
+use ::std::sync::Arc;
+
+
+/// The number of sounds which can be played at the same time. If more sounds
+/// are played, then they are dropped.
+const CHANNELS: usize = 16;
+
+/// The duration of the crossfade between two music tracks, in milliseconds.
+const CROSSFADE_MS: u32 = 1_000;
+
+/// The volume at which sounds are played as they were recorded, as in
+/// SDL_mixer.
+pub const MAX_VOLUME: u8 = 128;
+
+
+/// Identifies the channel on which a sound is being played.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChannelId(pub usize);
+
+
+/// A sound being played on one of the channels.
+struct Voice {
+    /// The samples of the sound, in the format of the audio device.
+    samples: Arc<Vec<f32>>,
+    /// The index of the next sample to be played.
+    pos: usize,
+}
+
+impl Voice {
+    fn remaining(&self) -> usize {
+        self.samples.len() - self.pos
+    }
+}
+
+
+/// A music track, which is played independently of the sound effects.
+struct Track {
+    samples: Arc<Vec<f32>>,
+    pos: usize,
+    /// The number of times the track is played again once it is done. If it
+    /// is negative, then the track loops forever.
+    loops: i32,
+    /// The gain applied to the samples, in [0.0, 1.0], and how much it changes
+    /// after every sample while the track fades in or out.
+    gain: f32,
+    gain_step: f32,
+}
+
+impl Track {
+    /// Adds the next samples of the track to `out`. Returns whether the track
+    /// is done, either because it has no loops left or because it has faded
+    /// out completely.
+    fn mix(&mut self, out: &mut [f32], volume: f32) -> bool {
+        for sample in out.iter_mut() {
+            if self.pos == self.samples.len() {
+                if self.loops == 0 || self.samples.is_empty() {
+                    return true;
+                }
+
+                if self.loops > 0 {
+                    self.loops -= 1;
+                }
+
+                self.pos = 0;
+            }
+
+            *sample += self.samples[self.pos] * self.gain * volume;
+            self.pos += 1;
+
+            self.gain = (self.gain + self.gain_step).max(0.0).min(1.0);
+            if self.gain_step < 0.0 && self.gain == 0.0 {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+
+/// Adds up the sounds which are currently being played. This runs on SDL's
+/// audio thread, so it may only be accessed through `AudioDevice::lock`.
+struct Mixer {
+    channels: Vec<Option<Voice>>,
+    /// The volume of everything, then of the sound effects and of the music
+    /// only, in [0.0, 1.0].
+    volume: f32,
+    sfx_volume: f32,
+    music_volume: f32,
+    /// Whether nothing should be heard. The sounds still advance, so that
+    /// the music is where one would expect it once it is unmuted.
+    muted: bool,
+
+    music: Option<Track>,
+    /// The previous tracks, while they fade out under the current one. There
+    /// may be several if a track was started during a crossfade.
+    outgoing_music: Vec<Track>,
+    music_paused: bool,
+}
+
+impl Mixer {
+    fn new() -> Mixer {
+        Mixer {
+            channels: (0..CHANNELS).map(|_| None).collect(),
+            volume: 1.0,
+            sfx_volume: 1.0,
+            music_volume: 1.0,
+            muted: false,
+            music: None,
+            outgoing_music: Vec::new(),
+            music_paused: false,
+        }
+    }
+
+    /// Plays the track made of `samples`, crossfading it with the music which
+    /// is currently heard, if any, by `step` after every sample.
+    fn start_music(&mut self, samples: Arc<Vec<f32>>, loops: i32, step: f32) {
+        // The tracks which were already fading out keep doing so from where
+        // they were.
+        let crossfade = match self.music.take() {
+            Some(mut previous) => {
+                previous.gain_step = -step;
+                self.outgoing_music.push(previous);
+                true
+            },
+            None => !self.outgoing_music.is_empty(),
+        };
+
+        self.music = Some(Track {
+            samples: samples,
+            pos: 0,
+            loops: loops,
+            gain: if crossfade { 0.0 } else { 1.0 },
+            gain_step: if crossfade { step } else { 0.0 },
+        });
+
+        self.music_paused = false;
+    }
+}
+
+impl AudioCallback for Mixer {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = 0.0;
+        }
+
+        for channel in self.channels.iter_mut() {
+            let done = match *channel {
+                Some(ref mut voice) => {
+                    let count = ::std::cmp::min(out.len(), voice.remaining());
+
+                    for (sample, value) in out.iter_mut().zip(&voice.samples[voice.pos..voice.pos + count]) {
+                        *sample += *value * self.sfx_volume;
+                    }
+
+                    voice.pos += count;
+                    voice.remaining() == 0
+                },
+                None => false,
+            };
+
+            // Free the channel for the next sound.
+            if done {
+                *channel = None;
+            }
+        }
+
+        if !self.music_paused {
+            let music_volume = self.music_volume;
+
+            let done = self.music.as_mut().map(|track| track.mix(out, music_volume)).unwrap_or(false);
+            if done {
+                self.music = None;
+            }
+
+            // The order of the tracks does not matter once they are mixed.
+            let mut i = 0;
+            while i < self.outgoing_music.len() {
+                if self.outgoing_music[i].mix(out, music_volume) {
+                    self.outgoing_music.swap_remove(i);
+                } else {
+                    i += 1;
+                }
+            }
+        }
+
+        let volume = if self.muted { 0.0 } else { self.volume };
+
+        for sample in out.iter_mut() {
+            *sample = (*sample * volume).max(-1.0).min(1.0);
+        }
+    }
+}
+
+
+/// Loads sound effects and plays them on demand, along with the music.
+pub struct Audio {
+    // `None` if the audio device could not be opened, in which case the game
+    // runs silently.
+    device: Option<AudioDevice<Mixer>>,
+
+    // The format of the samples expected by the device.
+    freq: i32,
+    channels: u8,
+
+    sounds: HashMap<String, Arc<Vec<f32>>>,
+    // The music tracks which were played, by path, so that playing one again
+    // does not decode it again.
+    music: HashMap<String, Arc<Vec<f32>>>,
+}
+
+impl Audio {
+    pub fn new(subsystem: Option<AudioSubsystem>) -> Audio {
+        let desired = AudioSpecDesired {
+            freq: Some(44_100),
+            channels: Some(2),
+            samples: None,
+        };
+
+        let mut format = (44_100, 2);
+
+        let device = subsystem.and_then(|subsystem| {
+            subsystem.open_playback(None, desired, |spec| {
+                format = (spec.freq, spec.channels);
+                Mixer::new()
+            }).ok()
+        });
+
+        if let Some(ref device) = device {
+            device.resume();
+        }
+
+        Audio {
+            device: device,
+            freq: format.0,
+            channels: format.1,
+            sounds: HashMap::new(),
+            music: HashMap::new(),
+        }
+    }
+
+    /// Loads the sound at `path` so that it can be played as `name`. Only WAV
+    /// files are currently supported; other formats, such as OGG, are
+    /// reported as an error.
+    pub fn load(&mut self, name: &str, path: &str) -> Result<(), String> {
+        let samples = self.load_samples(path)?;
+        self.sounds.insert(name.to_string(), Arc::new(samples));
+        Ok(())
+    }
+
+    /// Plays a sound effect on a free channel, and returns that channel. Any
+    /// number of sounds can be played during the same frame. `sound` is either
+    /// the name given to `load`, or the path of a file, which is loaded the
+    /// first time and then kept in memory for as long as the context.
+    ///
+    /// Returns an error if there is no such sound, or if every channel is busy,
+    /// in which case nothing is played, and no other sound is cut short.
+    pub fn play(&mut self, sound: &str) -> Result<ChannelId, String> {
+        let samples = self.cached(sound)?;
+        self.start(samples)
+    }
+
+    /// Plays the music track at `path`, then plays it again `loops` times, or
+    /// forever if `loops` is negative. If another track is playing, then it is
+    /// crossfaded with the new one, and so are those which were still fading
+    /// out. The music keeps playing when the view changes, until it is stopped
+    /// or faded out.
+    ///
+    /// The track is decoded the first time only, and then kept in memory.
+    pub fn play_music(&mut self, path: &str, loops: i32) -> Result<(), String> {
+        let samples = match self.music.get(path).cloned() {
+            Some(samples) => samples,
+            None => {
+                let samples = Arc::new(self.load_samples(path)?);
+                self.music.insert(path.to_string(), samples.clone());
+                samples
+            },
+        };
+
+        let step = self.fade_step(CROSSFADE_MS);
+
+        if let Some(ref mut device) = self.device {
+            device.lock().start_music(samples, loops, step);
+        }
+
+        Ok(())
+    }
+
+    /// Gradually lowers the volume of the music over `ms` milliseconds, then
+    /// stops it.
+    pub fn fade_out_music(&mut self, ms: u32) {
+        let step = self.fade_step(ms);
+
+        if let Some(ref mut device) = self.device {
+            if let Some(ref mut track) = device.lock().music {
+                track.gain_step = -step;
+            }
+        }
+    }
+
+    /// Stops the music immediately.
+    pub fn stop_music(&mut self) {
+        if let Some(ref mut device) = self.device {
+            let mut mixer = device.lock();
+            mixer.music = None;
+            mixer.outgoing_music.clear();
+        }
+    }
+
+    /// Silences the music, which can then be resumed from the same position.
+    /// The sound effects keep playing.
+    pub fn pause_music(&mut self) {
+        if let Some(ref mut device) = self.device {
+            device.lock().music_paused = true;
+        }
+    }
+
+    pub fn resume_music(&mut self) {
+        if let Some(ref mut device) = self.device {
+            device.lock().music_paused = false;
+        }
+    }
+
+    /// Sets the volume of every sound, from 0.0 (muted) to 1.0 (the default).
+    pub fn set_volume(&mut self, volume: f32) {
+        if let Some(ref mut device) = self.device {
+            device.lock().volume = volume.max(0.0).min(1.0);
+        }
+    }
+
+
+    /// Sets the volume of the sound effects, from 0 (silent) to `MAX_VOLUME`.
+    pub fn set_sfx_volume(&mut self, volume: u8) {
+        if let Some(ref mut device) = self.device {
+            device.lock().sfx_volume = volume.min(MAX_VOLUME) as f32 / MAX_VOLUME as f32;
+        }
+    }
+
+    /// Sets the volume of the music, from 0 (silent) to `MAX_VOLUME`.
+    pub fn set_music_volume(&mut self, volume: u8) {
+        if let Some(ref mut device) = self.device {
+            device.lock().music_volume = volume.min(MAX_VOLUME) as f32 / MAX_VOLUME as f32;
+        }
+    }
+
+    /// Silences everything, or restores the volumes which were set before.
+    pub fn set_muted(&mut self, muted: bool) {
+        if let Some(ref mut device) = self.device {
+            device.lock().muted = muted;
+        }
+    }
+
+    pub fn toggle_mute(&mut self) {
+        let muted = self.is_muted();
+        self.set_muted(!muted);
+    }
+
+    /// Whether the sound is muted. Always false if no device could be opened.
+    pub fn is_muted(&mut self) -> bool {
+        match self.device {
+            Some(ref mut device) => device.lock().muted,
+            None => false,
+        }
+    }
+
+    /// Returns the samples of the sound loaded as `sound`, or of the file at
+    /// this path, loading them the first time.
+    fn cached(&mut self, sound: &str) -> Result<Arc<Vec<f32>>, String> {
+        if let Some(samples) = self.sounds.get(sound) {
+            return Ok(samples.clone());
+        }
+
+        let samples = Arc::new(self.load_samples(sound)
+            .map_err(|e| format!("Unknown sound {:?} ({})", sound, e))?);
+        self.sounds.insert(sound.to_string(), samples.clone());
+        Ok(samples)
+    }
+
+    /// Plays `samples` on a free channel, if there is one.
+    fn start(&mut self, samples: Arc<Vec<f32>>) -> Result<ChannelId, String> {
+        let mut mixer = match self.device {
+            Some(ref mut device) => device.lock(),
+            None => return Err("No audio device".to_string()),
+        };
+
+        let index = mixer.channels.iter().position(|channel| channel.is_none())
+            .ok_or("Every channel is busy".to_string())?;
+
+        mixer.channels[index] = Some(Voice {
+            samples: samples,
+            pos: 0,
+        });
+
+        Ok(ChannelId(index))
+    }
+
+    /// The change in gain, after every sample, which fades a track over `ms`
+    /// milliseconds.
+    fn fade_step(&self, ms: u32) -> f32 {
+        let samples = ::std::cmp::max(ms, 1) as f32 / 1_000.0 * self.freq as f32 * self.channels as f32;
+        1.0 / samples
+    }
+
+    /// Reads a WAV file and converts it to the format of the audio device.
+    fn load_samples(&self, path: &str) -> Result<Vec<f32>, String> {
+        let is_wav = Path::new(path).extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase() == "wav")
+            .unwrap_or(false);
+
+        if !is_wav {
+            return Err(format!("{}: unsupported audio format, expected a WAV file", path));
+        }
+
+        let wav = AudioSpecWAV::load_wav(path).map_err(|e| format!("{}: {}", path, e.0))?;
+
+        let cvt = AudioCVT::new(
+                wav.format, wav.channels, wav.freq,
+                AudioFormat::f32_sys(), self.channels, self.freq)
+            .map_err(|e| format!("{}: {}", path, e.0))?;
+
+        let bytes = cvt.convert(wav.buffer().to_vec());
+
+        Ok(bytes.chunks(4)
+            .filter(|chunk| chunk.len() == 4)
+            .map(|chunk| f32::from_bits(u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])))
+            .collect())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(value: f32) -> Arc<Vec<f32>> {
+        Arc::new(vec![value; 100])
+    }
+
+    fn mix(mixer: &mut Mixer, count: usize) -> Vec<f32> {
+        let mut out = vec![0.0; count];
+        mixer.callback(&mut out);
+        out
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-4, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn first_track_starts_at_full_volume() {
+        let mut mixer = Mixer::new();
+        mixer.start_music(track(0.5), -1, 0.1);
+
+        assert_eq!(mix(&mut mixer, 3), vec![0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn new_track_crossfades_with_the_current_one() {
+        let mut mixer = Mixer::new();
+        mixer.start_music(track(0.5), -1, 0.1);
+        mix(&mut mixer, 5);
+
+        mixer.start_music(track(0.25), -1, 0.1);
+        let out = mix(&mut mixer, 20);
+
+        for (i, &sample) in out.iter().enumerate().take(10) {
+            let gain = i as f32 * 0.1;
+            assert_close(sample, 0.5 * (1.0 - gain) + 0.25 * gain);
+        }
+
+        assert!(mixer.outgoing_music.is_empty());
+        assert_close(out[19], 0.25);
+    }
+
+    #[test]
+    fn track_started_during_a_crossfade_fades_out_the_whole_mix() {
+        let mut mixer = Mixer::new();
+        mixer.start_music(track(0.5), -1, 0.1);
+        mixer.start_music(track(0.25), -1, 0.1);
+        let before = mix(&mut mixer, 5);
+
+        // Both tracks are halfway through the crossfade, and keep being heard
+        // as loud as they were.
+        mixer.start_music(track(0.125), -1, 0.1);
+        assert_eq!(mixer.outgoing_music.len(), 2);
+        for outgoing in &mixer.outgoing_music {
+            assert_close(outgoing.gain, 0.5);
+            assert!(outgoing.gain_step < 0.0);
+        }
+
+        let after = mix(&mut mixer, 20);
+        assert_close(before[4], 0.5 * 0.6 + 0.25 * 0.4);
+        for (i, &sample) in after.iter().enumerate().take(5) {
+            let gain = i as f32 * 0.1;
+            assert_close(sample, (0.5 + 0.25) * (0.5 - gain) + 0.125 * gain);
+        }
+
+        assert!(mixer.outgoing_music.is_empty());
+        assert_close(after[19], 0.125);
+    }
+
+    #[test]
+    fn tracks_stop_after_their_loops() {
+        let mut mixer = Mixer::new();
+        mixer.start_music(Arc::new(vec![0.5, 0.25]), 1, 0.1);
+
+        assert_eq!(mix(&mut mixer, 6), vec![0.5, 0.25, 0.5, 0.25, 0.0, 0.0]);
+        assert!(mixer.music.is_none());
+    }
+}
@@ -0,0 +1,158 @@
+use ::phi::data::Rectangle;
+use ::phi::math::Vec2f;
+
+
+/// The region of the world which is shown on the screen, for levels which are
+/// larger than the window. Things drawn through the camera, for instance with
+/// `Renderable::render_world` or `CopySprite::copy_sprite_world`, are moved by
+/// the opposite of its position; everything else, such as the interface, is
+/// drawn in screen coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    /// The position of the top-left corner of the screen, in the world.
+    pub x: f64,
+    pub y: f64,
+
+    /// The size of the screen, in pixels. The game loop keeps it equal to
+    /// `Phi::output_size` when the window is resized.
+    pub w: f64,
+    pub h: f64,
+
+    /// How many pixels of the screen a unit of the world spans. Above 1.0,
+    /// the camera zooms in and shows a smaller region of the world.
+    pub zoom: f64,
+
+    /// The extent of the level, outside of which the camera does not go.
+    pub bounds: Option<Rectangle>,
+}
+
+impl Camera {
+    /// Creates a camera showing the region of the world from `(0, 0)` to `(w,
+    /// h)`, which is not limited to any level.
+    pub fn new(w: f64, h: f64) -> Camera {
+        Camera {
+            x: 0.0,
+            y: 0.0,
+            w: w,
+            h: h,
+            zoom: 1.0,
+            bounds: None,
+        }
+    }
+
+    /// The point of the world shown at the top-left corner of the screen.
+    pub fn position(&self) -> Vec2f {
+        Vec2f::new(self.x, self.y)
+    }
+
+    /// Moves the top-left corner of the screen to `position`, staying within
+    /// the bounds of the level.
+    pub fn set_position(&mut self, position: Vec2f) {
+        self.x = position.x;
+        self.y = position.y;
+        self.clamp();
+    }
+
+    /// The region of the world which is shown on the screen.
+    pub fn viewport(&self) -> Rectangle {
+        Rectangle { x: self.x, y: self.y, w: self.w / self.zoom, h: self.h / self.zoom }
+    }
+
+    /// The point of the world shown at the center of the screen.
+    pub fn center(&self) -> (f64, f64) {
+        let viewport = self.viewport();
+        (viewport.x + viewport.w / 2.0, viewport.y + viewport.h / 2.0)
+    }
+
+    /// Changes the zoom while keeping the same point at the center of the
+    /// screen. Zooms which are not positive are ignored.
+    pub fn set_zoom(&mut self, zoom: f64) {
+        if !(zoom > 0.0) {
+            return;
+        }
+
+        let (cx, cy) = self.center();
+        self.zoom = zoom;
+        self.x = cx - self.w / zoom / 2.0;
+        self.y = cy - self.h / zoom / 2.0;
+        self.clamp();
+    }
+
+    /// Moves the camera so that `target` is at the center of the screen, unless
+    /// this would show what lies beyond the bounds of the level. If the level
+    /// is smaller than the screen, then it is centered instead.
+    pub fn center_on(&mut self, target: Rectangle) {
+        self.follow(target, 1.0);
+    }
+
+    /// Moves the camera by a fraction `lerp` of the way towards centering
+    /// `target`, so that calling it on every update smoothly tracks a moving
+    /// target instead of jerking with it. A `lerp` of 1.0 is the same as
+    /// `center_on`, and of 0.0 does not move the camera. The camera stays
+    /// within the bounds of the level.
+    pub fn follow(&mut self, target: Rectangle, lerp: f64) {
+        let target = target.normalized();
+        let lerp = lerp.max(0.0).min(1.0);
+        let viewport = self.viewport();
+
+        let goal_x = target.x + target.w / 2.0 - viewport.w / 2.0;
+        let goal_y = target.y + target.h / 2.0 - viewport.h / 2.0;
+        self.x += (goal_x - self.x) * lerp;
+        self.y += (goal_y - self.y) * lerp;
+        self.clamp();
+    }
+
+    /// Moves the camera back inside of the level's bounds, if it left them.
+    pub fn clamp(&mut self) {
+        if let Some(bounds) = self.bounds {
+            let bounds = bounds.normalized();
+            let viewport = self.viewport();
+
+            self.x =
+                if bounds.w <= viewport.w { bounds.x + (bounds.w - viewport.w) / 2.0 }
+                else { self.x.max(bounds.x).min(bounds.x + bounds.w - viewport.w) };
+
+            self.y =
+                if bounds.h <= viewport.h { bounds.y + (bounds.h - viewport.h) / 2.0 }
+                else { self.y.max(bounds.y).min(bounds.y + bounds.h - viewport.h) };
+        }
+    }
+
+    /// Whether some of a region of the world is shown on the screen, so that
+    /// what lies entirely outside of it need not be drawn.
+    pub fn is_visible(&self, rect: Rectangle) -> bool {
+        self.viewport().overlaps(rect.normalized())
+    }
+
+    /// Returns where a point of the world is found on the screen.
+    pub fn world_to_screen(&self, point: Vec2f) -> Vec2f {
+        (point - self.position()) * self.zoom
+    }
+
+    /// Returns which point of the world is shown at a point of the screen, for
+    /// instance under the mouse cursor.
+    pub fn screen_to_world(&self, point: Vec2f) -> Vec2f {
+        point * (1.0 / self.zoom) + self.position()
+    }
+
+    /// Returns where a region of the world is found on the screen.
+    pub fn to_screen(&self, rect: Rectangle) -> Rectangle {
+        Rectangle {
+            x: (rect.x - self.x) * self.zoom,
+            y: (rect.y - self.y) * self.zoom,
+            w: rect.w * self.zoom,
+            h: rect.h * self.zoom,
+        }
+    }
+
+    /// Returns which region of the world is shown at a region of the screen,
+    /// for instance under the mouse cursor.
+    pub fn to_world(&self, rect: Rectangle) -> Rectangle {
+        Rectangle {
+            x: rect.x / self.zoom + self.x,
+            y: rect.y / self.zoom + self.y,
+            w: rect.w / self.zoom,
+            h: rect.h / self.zoom,
+        }
+    }
+}
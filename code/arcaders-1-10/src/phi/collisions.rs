@@ -0,0 +1,447 @@
+use ::phi::data::{Circle, Rectangle};
+
+
+/// Whether two axis-aligned boxes intersect. Boxes which only touch along an
+/// edge do not.
+///
+/// Collisions are computed with logical `Rectangle`s, whose coordinates are
+/// `f64`s, so that slow entities do not get stuck on a pixel grid. Use
+/// `Rectangle::from_sdl` to test SDL's integer rectangles.
+pub fn aabb(a: Rectangle, b: Rectangle) -> bool {
+    a.normalized().overlaps(b.normalized())
+}
+
+/// Whether two discs intersect. Discs which only touch do not.
+pub fn circles(a: &Circle, b: &Circle) -> bool {
+    a.overlaps(b)
+}
+
+
+/// The shape of something which may collide with other things.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    Rect(Rectangle),
+    Circle(Circle),
+}
+
+/// Something which may be tested for collisions against other things,
+/// whatever their shape, for instance a round asteroid against a rectangular
+/// bullet.
+pub trait Collidable {
+    fn shape(&self) -> Shape;
+
+    /// The smallest rectangle containing the shape, for instance to index it
+    /// in a `QuadTree` or a `Grid` which only deal with rectangles.
+    fn bounding_box(&self) -> Rectangle {
+        match self.shape() {
+            Shape::Rect(rect) => rect.normalized(),
+            Shape::Circle(circle) => Rectangle {
+                x: circle.x - circle.radius,
+                y: circle.y - circle.radius,
+                w: circle.radius * 2.0,
+                h: circle.radius * 2.0,
+            },
+        }
+    }
+
+    /// Whether both shapes share some area. Shapes which only touch do not.
+    fn collides_with(&self, other: &dyn Collidable) -> bool {
+        match (self.shape(), other.shape()) {
+            (Shape::Rect(a), Shape::Rect(b)) => aabb(a, b),
+            (Shape::Circle(a), Shape::Circle(b)) => circles(&a, &b),
+            (Shape::Rect(rect), Shape::Circle(circle)) |
+            (Shape::Circle(circle), Shape::Rect(rect)) => circle.overlaps_rect(&rect),
+        }
+    }
+}
+
+impl Collidable for Rectangle {
+    fn shape(&self) -> Shape {
+        Shape::Rect(*self)
+    }
+}
+
+impl Collidable for Circle {
+    fn shape(&self) -> Shape {
+        Shape::Circle(*self)
+    }
+}
+
+
+/// Which pixels of an image are solid, for collisions which must be exact,
+/// for instance between the ship and the asteroids. Testing masks is much
+/// slower than testing shapes, so it is usually only done for the pairs whose
+/// bounding boxes overlap. See `Sprite::collision_mask`.
+#[derive(Clone, Debug)]
+pub struct Mask {
+    width: usize,
+    height: usize,
+    /// One bit per pixel, set if it is solid, row by row. Every row starts on
+    /// a new word, and the bits past the end of a row are unset.
+    words_per_row: usize,
+    bits: Vec<u64>,
+}
+
+impl Mask {
+    /// Creates a mask where every pixel of `pixels`, in the `ARGB8888` format,
+    /// whose alpha is at least `threshold_alpha` is solid.
+    pub fn from_argb(pixels: &[u8], width: usize, height: usize, pitch: usize, threshold_alpha: u8) -> Mask {
+        let words_per_row = (width + 63) / 64;
+        let mut bits = vec![0; words_per_row * height];
+
+        // The pixels are 32-bit integers in the native byte order, with the
+        // alpha in their highest byte.
+        let alpha_byte = if cfg!(target_endian = "little") { 3 } else { 0 };
+
+        for y in 0..height {
+            for x in 0..width {
+                if pixels[y * pitch + x * 4 + alpha_byte] >= threshold_alpha {
+                    bits[y * words_per_row + x / 64] |= 1 << (x % 64);
+                }
+            }
+        }
+
+        Mask {
+            width: width,
+            height: height,
+            words_per_row: words_per_row,
+            bits: bits,
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Whether the pixel at `(x, y)` is solid. Pixels outside of the mask are
+    /// not.
+    pub fn is_solid(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height &&
+            self.bits[y as usize * self.words_per_row + x as usize / 64] & (1 << (x as usize % 64)) != 0
+    }
+
+    /// Whether both masks have a solid pixel in common, when the top-left
+    /// corner of `other` is `offset` pixels away from the top-left corner of
+    /// this one.
+    pub fn overlaps(&self, other: &Mask, offset: (i32, i32)) -> bool {
+        let (dx, dy) = offset;
+        let top = ::std::cmp::max(0, dy);
+        let bottom = ::std::cmp::min(self.height as i32, dy + other.height as i32);
+
+        for y in top..bottom {
+            let row = &self.bits[y as usize * self.words_per_row..][..self.words_per_row];
+            let other_y = (y - dy) as usize;
+            let other_row = &other.bits[other_y * other.words_per_row..][..other.words_per_row];
+
+            for (word, &bits) in row.iter().enumerate() {
+                if bits != 0 && bits & Mask::word_at(other_row, word as i64 * 64 - dx as i64) != 0 {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// The 64 bits of `row` starting at bit `start`, which may be negative or
+    /// past the end of the row, in which case the missing bits are unset.
+    fn word_at(row: &[u64], start: i64) -> u64 {
+        let get = |index: i64| {
+            if index < 0 || index >= row.len() as i64 { 0 } else { row[index as usize] }
+        };
+
+        let index = start.div_euclid(64);
+        let shift = start.rem_euclid(64);
+
+        if shift == 0 {
+            get(index)
+        } else {
+            (get(index) >> shift) | (get(index + 1) << (64 - shift))
+        }
+    }
+}
+
+
+/// Splits a world of fixed size in square cells, so that looking for the
+/// entities close to a given box only goes through the cells that it covers,
+/// rather than through every entity.
+///
+/// The grid is meant to be cleared and filled again every frame, as things
+/// move. Entities partly or completely outside of the world are kept in the
+/// cells along its border.
+pub struct Grid<T> {
+    cell_size: f64,
+    columns: usize,
+    rows: usize,
+
+    /// The indices, in `entries`, of the entities overlapping every cell, in
+    /// row-major order.
+    cells: Vec<Vec<usize>>,
+    entries: Vec<(Rectangle, T)>,
+}
+
+impl<T> Grid<T> {
+    /// Creates an empty grid covering `(0, 0)` to `(width, height)` with cells
+    /// of `cell_size` pixels. Panics if `cell_size` is not positive.
+    pub fn new(width: f64, height: f64, cell_size: f64) -> Grid<T> {
+        assert!(cell_size > 0.0, "The cells of a grid must have a positive size");
+
+        let columns = ::std::cmp::max(1, (width / cell_size).ceil() as usize);
+        let rows = ::std::cmp::max(1, (height / cell_size).ceil() as usize);
+
+        Grid {
+            cell_size: cell_size,
+            columns: columns,
+            rows: rows,
+            cells: (0..columns * rows).map(|_| Vec::new()).collect(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Removes every entity, but keeps the memory for the next frame.
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.clear();
+        }
+
+        self.entries.clear();
+    }
+
+    /// Adds an entity occupying `rect`.
+    pub fn insert(&mut self, rect: Rectangle, value: T) {
+        let rect = rect.normalized();
+        let index = self.entries.len();
+        let (xmin, ymin, xmax, ymax) = self.cell_range(rect);
+
+        for y in ymin..ymax + 1 {
+            for x in xmin..xmax + 1 {
+                self.cells[y * self.columns + x].push(index);
+            }
+        }
+
+        self.entries.push((rect, value));
+    }
+
+    /// Returns every entity which overlaps `rect`, each of them once, in the
+    /// order in which they were inserted.
+    pub fn query(&self, rect: Rectangle) -> Vec<&T> {
+        let rect = rect.normalized();
+        let (xmin, ymin, xmax, ymax) = self.cell_range(rect);
+        let mut found = Vec::new();
+
+        for y in ymin..ymax + 1 {
+            for x in xmin..xmax + 1 {
+                found.extend_from_slice(&self.cells[y * self.columns + x]);
+            }
+        }
+
+        // An entity covering several cells is found once for each of them.
+        found.sort();
+        found.dedup();
+
+        found.into_iter()
+            .map(|index| &self.entries[index])
+            .filter(|&&(ref entry, _)| entry.overlaps(rect))
+            .map(|&(_, ref value)| value)
+            .collect()
+    }
+
+    /// Returns every pair of entities which overlap each other, each of them
+    /// once.
+    pub fn pairs(&self) -> Vec<(&T, &T)> {
+        let mut found = Vec::new();
+
+        for cell in self.cells.iter() {
+            for (i, &a) in cell.iter().enumerate() {
+                for &b in cell[i + 1..].iter() {
+                    found.push((a, b));
+                }
+            }
+        }
+
+        found.sort();
+        found.dedup();
+
+        found.into_iter()
+            .filter(|&(a, b)| self.entries[a].0.overlaps(self.entries[b].0))
+            .map(|(a, b)| (&self.entries[a].1, &self.entries[b].1))
+            .collect()
+    }
+
+
+    /// The first and last column and row covered by `rect`, clamped to the
+    /// grid.
+    fn cell_range(&self, rect: Rectangle) -> (usize, usize, usize, usize) {
+        let clamp = |value: f64, count: usize| {
+            (value / self.cell_size).floor().max(0.0).min(count as f64 - 1.0) as usize
+        };
+
+        (clamp(rect.x, self.columns), clamp(rect.y, self.rows),
+         clamp(rect.x + rect.w, self.columns), clamp(rect.y + rect.h, self.rows))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An image of `w` by `h` pixels in the `ARGB8888` format, where the
+    /// pixels for which `solid` returns true are opaque and the others are
+    /// transparent.
+    fn argb<F: Fn(usize, usize) -> bool>(w: usize, h: usize, solid: F) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(w * h * 4);
+
+        for y in 0..h {
+            for x in 0..w {
+                let pixel: u32 = if solid(x, y) { 0xff336699 } else { 0x00336699 };
+                pixels.extend_from_slice(&pixel.to_ne_bytes());
+            }
+        }
+
+        pixels
+    }
+
+    /// A disc inscribed in a square of `size` pixels.
+    fn disc(size: usize) -> Mask {
+        let r = size as f64 / 2.0;
+        let pixels = argb(size, size, |x, y| {
+            let (dx, dy) = (x as f64 + 0.5 - r, y as f64 + 0.5 - r);
+            dx * dx + dy * dy <= r * r
+        });
+
+        Mask::from_argb(&pixels, size, size, size * 4, 128)
+    }
+
+    #[test]
+    fn mask_keeps_the_pixels_above_the_threshold() {
+        let mut pixels = argb(2, 1, |_, _| false);
+        let alpha_byte = if cfg!(target_endian = "little") { 3 } else { 0 };
+        pixels[alpha_byte] = 127;
+        pixels[4 + alpha_byte] = 128;
+
+        let mask = Mask::from_argb(&pixels, 2, 1, 8, 128);
+        assert!(!mask.is_solid(0, 0));
+        assert!(mask.is_solid(1, 0));
+        assert!(!mask.is_solid(2, 0));
+        assert!(!mask.is_solid(-1, 0));
+    }
+
+    #[test]
+    fn mask_of_a_mirrored_image_is_mirrored() {
+        // An L in the top-left corner, over more than one word per row.
+        let (w, h) = (70, 3);
+        let solid = |x: usize, y: usize| x == 0 || (y == 0 && x < 10);
+
+        let mask = Mask::from_argb(&argb(w, h, solid), w, h, w * 4, 128);
+        let flipped_h = Mask::from_argb(&argb(w, h, |x, y| solid(w - 1 - x, y)), w, h, w * 4, 128);
+        let flipped_v = Mask::from_argb(&argb(w, h, |x, y| solid(x, h - 1 - y)), w, h, w * 4, 128);
+
+        for y in 0..h as i32 {
+            for x in 0..w as i32 {
+                assert_eq!(mask.is_solid(x, y), flipped_h.is_solid(w as i32 - 1 - x, y));
+                assert_eq!(mask.is_solid(x, y), flipped_v.is_solid(x, h as i32 - 1 - y));
+            }
+        }
+
+        assert!(flipped_h.is_solid(69, 1) && !flipped_h.is_solid(0, 1));
+        assert!(flipped_v.is_solid(5, 2) && !flipped_v.is_solid(5, 0));
+    }
+
+    #[test]
+    fn mask_ignores_the_padding_of_the_rows() {
+        let pixels = argb(4, 2, |x, y| x == y);
+        // Only keep the first three pixels of every row.
+        let mask = Mask::from_argb(&pixels, 3, 2, 16, 128);
+
+        assert_eq!(mask.size(), (3, 2));
+        assert!(mask.is_solid(0, 0) && mask.is_solid(1, 1));
+        assert!(!mask.is_solid(3, 0));
+    }
+
+    #[test]
+    fn masks_overlap_only_where_both_are_solid() {
+        let a = disc(100);
+        let b = disc(100);
+
+        // The bounding boxes overlap at the corner, but the discs do not.
+        assert!(!a.overlaps(&b, (80, 80)));
+        assert!(a.overlaps(&b, (50, 50)));
+        // Side by side, across word boundaries, they touch at 100.
+        assert!(a.overlaps(&b, (99, 0)));
+        assert!(!a.overlaps(&b, (100, 0)));
+        assert!(a.overlaps(&b, (-99, 0)));
+        assert!(!a.overlaps(&b, (0, 100)));
+    }
+
+    #[test]
+    fn shapes_collide_whatever_their_kind() {
+        let rect = Rectangle { x: 0.0, y: 0.0, w: 10.0, h: 10.0 };
+        let near = Circle { x: 14.0, y: 14.0, radius: 5.0 };
+        let far = Circle { x: 20.0, y: 10.0, radius: 5.0 };
+
+        // The bounding box of `near` overlaps the corner, but not the disc.
+        assert!(aabb(rect, near.bounding_box()));
+        assert!(!rect.collides_with(&near));
+        assert!(!near.collides_with(&rect));
+        assert!(!far.collides_with(&rect));
+        assert!(near.collides_with(&far));
+    }
+
+    /// Compares testing the bounding boxes of 200 asteroids against each
+    /// other, with testing their masks as well when the boxes overlap. Run
+    /// with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_aabb_against_masks() {
+        use ::std::time::Instant;
+
+        const ASTEROIDS: usize = 200;
+        const ROUNDS: u32 = 20;
+
+        // A fixed pseudo-random layout, so that every run does the same work.
+        let mut seed = 0x2545f491u32;
+        let mut next = |max: u32| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed % max
+        };
+
+        let masks: Vec<Mask> = (0..4).map(|size| disc(32 + size * 16)).collect();
+        let asteroids: Vec<(Rectangle, &Mask)> = (0..ASTEROIDS).map(|_| {
+            let mask = &masks[next(4) as usize];
+            let size = mask.size().0 as f64;
+            (Rectangle { x: next(800) as f64, y: next(600) as f64, w: size, h: size }, mask)
+        }).collect();
+
+        let start = Instant::now();
+        let mut boxes = 0;
+        for _ in 0..ROUNDS {
+            boxes = 0;
+            for (i, &(a, _)) in asteroids.iter().enumerate() {
+                for &(b, _) in &asteroids[i + 1..] {
+                    if aabb(a, b) { boxes += 1; }
+                }
+            }
+        }
+        let aabb_time = start.elapsed() / ROUNDS;
+
+        let start = Instant::now();
+        let mut pixels = 0;
+        for _ in 0..ROUNDS {
+            pixels = 0;
+            for (i, &(a, mask_a)) in asteroids.iter().enumerate() {
+                for &(b, mask_b) in &asteroids[i + 1..] {
+                    let offset = ((b.x - a.x) as i32, (b.y - a.y) as i32);
+                    if aabb(a, b) && mask_a.overlaps(mask_b, offset) { pixels += 1; }
+                }
+            }
+        }
+        let mask_time = start.elapsed() / ROUNDS;
+
+        println!("{} asteroids: {} hits with boxes in {:?}, {} with masks in {:?}",
+                 ASTEROIDS, boxes, aabb_time, pixels, mask_time);
+        assert!(pixels <= boxes);
+    }
+}
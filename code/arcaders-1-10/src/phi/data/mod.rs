@@ -0,0 +1,654 @@
+pub mod scores;
+
+use ::phi::math::Vec2f;
+use ::sdl2::rect::Rect as SdlRect;
+
+/// The countdowns and tweens which views keep in their state live next to the
+/// timer queue and the `Lerp` implementations, but are also found here, along
+/// with the other plain data types.
+pub use ::phi::timer::{Cooldown, Timer};
+pub use ::phi::tween::{Ease, Lerp, Tween};
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rectangle {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+impl Rectangle {
+    /// Returns the rectangle covering the same pixels as an SDL `Rect`.
+    pub fn from_sdl(rect: SdlRect) -> Rectangle {
+        Rectangle {
+            x: rect.x() as f64,
+            y: rect.y() as f64,
+            w: rect.width() as f64,
+            h: rect.height() as f64,
+        }
+    }
+
+    /// Generates an SDL-compatible Rect equivalent to `self`. A negative width
+    /// or height is understood as extending to the left or to the top of the
+    /// rectangle's origin. The corners are rounded to the nearest pixel, so
+    /// that rectangles which touch still do once converted.
+    ///
+    /// Returns `None` if it could not be created, for example if the rectangle
+    /// is empty or if a coordinate of a corner overflows an `i32`.
+    pub fn to_sdl(self) -> Option<SdlRect> {
+        let rect = self.normalized();
+        let (left, top) = (rect.x.round(), rect.y.round());
+        let (right, bottom) = ((rect.x + rect.w).round(), (rect.y + rect.h).round());
+
+        // SdlRect::new : `(i32, i32, u32, u32) -> Result<Option<SdlRect>>`
+        SdlRect::new(left as i32, top as i32, (right - left) as u32, (bottom - top) as u32)
+            .ok().and_then(|rect| rect)
+    }
+
+    /// Returns the same region as `self`, but with a non-negative width and
+    /// height.
+    pub fn normalized(self) -> Rectangle {
+        Rectangle {
+            x: if self.w < 0.0 { self.x + self.w } else { self.x },
+            y: if self.h < 0.0 { self.y + self.h } else { self.y },
+            w: self.w.abs(),
+            h: self.h.abs(),
+        }
+    }
+
+
+    pub fn contains(&self, rect: Rectangle) -> bool {
+        let xmin = rect.x;
+        let xmax = xmin + rect.w;
+        let ymin = rect.y;
+        let ymax = ymin + rect.h;
+
+        xmin >= self.x && xmin <= self.x + self.w &&
+        xmax >= self.x && xmax <= self.x + self.w &&
+        ymin >= self.y && ymin <= self.y + self.h &&
+        ymax >= self.y && ymax <= self.y + self.h
+    }
+
+    /// Whether the point `(x, y)` is inside of the rectangle. Accepts both
+    /// logical coordinates and integer ones, such as the mouse position.
+    pub fn contains_point<T: Into<f64>>(&self, (x, y): (T, T)) -> bool {
+        let (x, y) = (x.into(), y.into());
+
+        x >= self.x && x <= self.x + self.w &&
+        y >= self.y && y <= self.y + self.h
+    }
+
+    /// Whether the rectangles intersect. Rectangles which only touch each other
+    /// along an edge do not overlap.
+    pub fn overlaps(&self, other: Rectangle) -> bool {
+        self.x < other.x + other.w &&
+        self.x + self.w > other.x &&
+        self.y < other.y + other.h &&
+        self.y + self.h > other.y
+    }
+
+    /// The region shared by both rectangles, or `None` if they do not overlap.
+    pub fn intersection(&self, other: Rectangle) -> Option<Rectangle> {
+        let (a, b) = (self.normalized(), other.normalized());
+        if !a.overlaps(b) {
+            return None;
+        }
+
+        let (x, y) = (a.x.max(b.x), a.y.max(b.y));
+        Some(Rectangle {
+            x: x,
+            y: y,
+            w: (a.x + a.w).min(b.x + b.w) - x,
+            h: (a.y + a.h).min(b.y + b.h) - y,
+        })
+    }
+
+    /// The top-left corner of the rectangle.
+    pub fn origin(&self) -> Vec2f {
+        Vec2f::new(self.x, self.y)
+    }
+
+    pub fn center(&self) -> Vec2f {
+        Vec2f::new(self.x + self.w / 2.0, self.y + self.h / 2.0)
+    }
+
+
+    /// Returns the same rectangle, rotated by `angle` radians around its
+    /// center.
+    pub fn to_oriented(self, angle: f64) -> OrientedRect {
+        let rect = self.normalized();
+
+        OrientedRect {
+            cx: rect.x + rect.w / 2.0,
+            cy: rect.y + rect.h / 2.0,
+            w: rect.w,
+            h: rect.h,
+            angle: angle,
+        }
+    }
+
+
+    /// Return a (perhaps moved) rectangle which is contained by a `parent`
+    /// rectangle. If it can indeed be moved to fit, return `Some(result)`;
+    /// otherwise, return `None`.
+    pub fn moved_inside(self, parent: Rectangle) -> Option<Rectangle> {
+        // It must be smaller than the parent rectangle to fit in it.
+        if self.w > parent.w || self.h > parent.h {
+            return None;
+        }
+
+        Some(Rectangle {
+            w: self.w,
+            h: self.h,
+            x: if self.x < parent.x { parent.x }
+               else if self.x + self.w >= parent.x + parent.w { parent.x + parent.w - self.w }
+               else { self.x },
+            y: if self.y < parent.y { parent.y }
+               else if self.y + self.h >= parent.y + parent.h { parent.y + parent.h - self.h }
+               else { self.y },
+        })
+    }
+}
+
+
+/// A rectangle which may be rotated, for tighter collisions with objects that
+/// spin, such as the asteroids.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrientedRect {
+    /// The center of the rectangle.
+    pub cx: f64,
+    pub cy: f64,
+    pub w: f64,
+    pub h: f64,
+    /// The rotation of the rectangle around its center, in radians. Because
+    /// the y axis points down, positive angles turn clockwise on the screen.
+    pub angle: f64,
+}
+
+impl OrientedRect {
+    /// The unit vectors along the width and the height of the rectangle.
+    /// Unlike its edges, these are never zero, even if the rectangle is flat.
+    fn axes(&self) -> [Vec2f; 2] {
+        let u = Vec2f::from_angle(self.angle);
+        [u, Vec2f::new(-u.y, u.x)]
+    }
+
+    /// The position of the four corners of the rectangle.
+    pub fn corners(&self) -> [(f64, f64); 4] {
+        let [u, v] = self.axes();
+        let (hu, hv) = (u * (self.w.abs() / 2.0), v * (self.h.abs() / 2.0));
+        let center = Vec2f::new(self.cx, self.cy);
+
+        let corner = |su: f64, sv: f64| (center + hu * su + hv * sv).into();
+
+        [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)]
+    }
+
+    /// The interval covered by the rectangle when it is projected on `axis`.
+    fn project(&self, axis: Vec2f) -> (f64, f64) {
+        self.corners().iter()
+            .map(|&corner| Vec2f::from(corner).dot(axis))
+            .fold((::std::f64::INFINITY, ::std::f64::NEG_INFINITY),
+                  |(min, max), p| (min.min(p), max.max(p)))
+    }
+
+    /// Whether both rectangles intersect, using the separating axis theorem:
+    /// two convex shapes are disjoint if and only if there is an axis, here
+    /// one of their sides' directions, on which their projections are apart.
+    ///
+    /// Unlike `Rectangle::overlaps`, rectangles which touch along an edge are
+    /// considered to overlap, so that boxes with a width or height of zero,
+    /// such as a laser beam, still collide with what they cross.
+    pub fn overlaps(&self, other: &OrientedRect) -> bool {
+        let axes = self.axes();
+        let other_axes = other.axes();
+
+        axes.iter().chain(other_axes.iter()).all(|&axis| {
+            let (min1, max1) = self.project(axis);
+            let (min2, max2) = other.project(axis);
+            max1 >= min2 && max2 >= min1
+        })
+    }
+}
+
+
+/// A disc, for collisions with round objects such as asteroids and bullets.
+/// A circle with a negative radius is empty: it contains and overlaps nothing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Circle {
+    /// The center of the circle.
+    pub x: f64,
+    pub y: f64,
+    pub radius: f64,
+}
+
+impl Circle {
+    /// Returns the largest circle which fits inside of `rect`.
+    pub fn from_rect(rect: &Rectangle) -> Circle {
+        let rect = rect.normalized();
+
+        Circle {
+            x: rect.x + rect.w / 2.0,
+            y: rect.y + rect.h / 2.0,
+            radius: rect.w.min(rect.h) / 2.0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.radius < 0.0
+    }
+
+    /// Whether the point `(x, y)` is inside of the circle, or on its border.
+    pub fn contains_point<T: Into<f64>>(&self, (x, y): (T, T)) -> bool {
+        let (dx, dy) = (x.into() - self.x, y.into() - self.y);
+        !self.is_empty() && dx * dx + dy * dy <= self.radius * self.radius
+    }
+
+    /// Whether both circles share some area. Like rectangles, circles which
+    /// only touch each other do not overlap.
+    pub fn overlaps(&self, other: &Circle) -> bool {
+        let (dx, dy) = (other.x - self.x, other.y - self.y);
+        let radii = self.radius + other.radius;
+
+        !self.is_empty() && !other.is_empty() && dx * dx + dy * dy < radii * radii
+    }
+
+    /// Whether the circle and `rect` share some area. See `circle_rect_overlap`.
+    pub fn overlaps_rect(&self, rect: &Rectangle) -> bool {
+        circle_rect_overlap(self, rect)
+    }
+}
+
+
+/// Whether a circle and a rectangle share some area. As with the other shapes,
+/// touching is not overlapping.
+pub fn circle_rect_overlap(circle: &Circle, rect: &Rectangle) -> bool {
+    if circle.is_empty() {
+        return false;
+    }
+
+    // The point of the rectangle which is closest to the center of the circle
+    let rect = rect.normalized();
+    let closest_x = circle.x.max(rect.x).min(rect.x + rect.w);
+    let closest_y = circle.y.max(rect.y).min(rect.y + rect.h);
+
+    let (dx, dy) = (circle.x - closest_x, circle.y - closest_y);
+    dx * dx + dy * dy < circle.radius * circle.radius
+}
+
+
+/// Indexes boxes by their position, so that finding those which overlap a
+/// given region does not require testing all of them. For example, bullets
+/// can look for the asteroids which they hit.
+///
+/// Every node holds at most `capacity` entries before it is split into four
+/// quadrants, unless it is already `max_depth` levels deep. Entries which
+/// straddle the boundary between quadrants, or which lie outside of the
+/// tree's bounds, stay in the node which contains them, so that every entry
+/// is stored, and returned, exactly once.
+pub struct QuadTree<I> {
+    root: QuadNode<I>,
+    max_depth: usize,
+    capacity: usize,
+}
+
+struct QuadNode<I> {
+    bounds: Rectangle,
+    depth: usize,
+    entries: Vec<(I, Rectangle)>,
+    children: Option<Box<[QuadNode<I>; 4]>>,
+}
+
+impl<I: Copy> QuadTree<I> {
+    pub fn new(bounds: Rectangle, max_depth: usize, capacity: usize) -> QuadTree<I> {
+        QuadTree {
+            root: QuadNode::new(bounds.normalized(), 0),
+            max_depth: max_depth,
+            capacity: ::std::cmp::max(capacity, 1),
+        }
+    }
+
+    /// Removes every entry, so that the tree can be filled again on the next
+    /// frame.
+    pub fn clear(&mut self) {
+        self.root = QuadNode::new(self.root.bounds, 0);
+    }
+
+    /// Adds the entity `id`, occupying `rect`.
+    pub fn insert(&mut self, id: I, rect: Rectangle) {
+        self.root.insert(id, rect.normalized(), self.max_depth, self.capacity);
+    }
+
+    /// Returns the entities which overlap `rect`.
+    pub fn query(&self, rect: Rectangle) -> Vec<I> {
+        let mut found = Vec::new();
+        self.root.query(rect.normalized(), &mut found);
+        found
+    }
+}
+
+impl<I: Copy> QuadNode<I> {
+    fn new(bounds: Rectangle, depth: usize) -> QuadNode<I> {
+        QuadNode {
+            bounds: bounds,
+            depth: depth,
+            entries: Vec::new(),
+            children: None,
+        }
+    }
+
+    /// The child which completely contains `rect`, if any.
+    fn child_for(&mut self, rect: Rectangle) -> Option<&mut QuadNode<I>> {
+        self.children.as_mut()
+            .and_then(|children| children.iter_mut().find(|child| child.bounds.contains(rect)))
+    }
+
+    fn insert(&mut self, id: I, rect: Rectangle, max_depth: usize, capacity: usize) {
+        if let Some(child) = self.child_for(rect) {
+            return child.insert(id, rect, max_depth, capacity);
+        }
+
+        self.entries.push((id, rect));
+
+        if self.children.is_none() && self.entries.len() > capacity && self.depth < max_depth {
+            self.split(max_depth, capacity);
+        }
+    }
+
+    /// Creates the four quadrants of the node, and moves down the entries
+    /// which fit in one of them.
+    fn split(&mut self, max_depth: usize, capacity: usize) {
+        let Rectangle { x, y, w, h } = self.bounds;
+        let (hw, hh) = (w / 2.0, h / 2.0);
+        let depth = self.depth + 1;
+
+        self.children = Some(Box::new([
+            QuadNode::new(Rectangle { x: x,      y: y,      w: hw, h: hh }, depth),
+            QuadNode::new(Rectangle { x: x + hw, y: y,      w: hw, h: hh }, depth),
+            QuadNode::new(Rectangle { x: x,      y: y + hh, w: hw, h: hh }, depth),
+            QuadNode::new(Rectangle { x: x + hw, y: y + hh, w: hw, h: hh }, depth),
+        ]));
+
+        let entries = ::std::mem::replace(&mut self.entries, Vec::new());
+
+        for (id, rect) in entries {
+            match self.child_for(rect) {
+                Some(child) => child.insert(id, rect, max_depth, capacity),
+                None => self.entries.push((id, rect)),
+            }
+        }
+    }
+
+    fn query(&self, rect: Rectangle, found: &mut Vec<I>) {
+        found.extend(self.entries.iter()
+            .filter(|&&(_, entry)| entry.overlaps(rect))
+            .map(|&(id, _)| id));
+
+        if let Some(ref children) = self.children {
+            for child in children.iter() {
+                // The entries of a child all lie inside of its bounds.
+                if child.bounds.overlaps(rect) {
+                    child.query(rect, found);
+                }
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circle(x: f64, y: f64, radius: f64) -> Circle {
+        Circle { x: x, y: y, radius: radius }
+    }
+
+    fn rect(x: f64, y: f64, w: f64, h: f64) -> Rectangle {
+        Rectangle { x: x, y: y, w: w, h: h }
+    }
+
+    #[test]
+    fn tangent_circles_do_not_overlap() {
+        let a = circle(0.0, 0.0, 1.0);
+
+        assert!(!a.overlaps(&circle(2.0, 0.0, 1.0)));
+        assert!(!circle(2.0, 0.0, 1.0).overlaps(&a));
+        // 3-4-5: the centers are exactly 5 apart.
+        assert!(!circle(0.0, 0.0, 2.0).overlaps(&circle(3.0, 4.0, 3.0)));
+
+        assert!(a.overlaps(&circle(1.999, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn tangent_circle_and_rect_do_not_overlap() {
+        let disc = circle(0.0, 0.0, 1.0);
+
+        // Touching an edge
+        assert!(!circle_rect_overlap(&disc, &rect(1.0, -1.0, 2.0, 2.0)));
+        assert!(!disc.overlaps_rect(&rect(-1.0, -3.0, 2.0, 2.0)));
+        // Touching a corner, at a distance of 5 from the center
+        assert!(!circle_rect_overlap(&circle(0.0, 0.0, 5.0), &rect(3.0, 4.0, 1.0, 1.0)));
+
+        assert!(circle_rect_overlap(&disc, &rect(0.999, -1.0, 2.0, 2.0)));
+        // The center is inside of the rectangle.
+        assert!(circle_rect_overlap(&disc, &rect(-4.0, -4.0, 8.0, 8.0)));
+    }
+
+    #[test]
+    fn border_points_are_contained() {
+        let disc = circle(1.0, 1.0, 2.0);
+
+        assert!(disc.contains_point((3.0, 1.0)));
+        assert!(disc.contains_point((1, -1)));
+        assert!(!disc.contains_point((3.001, 1.0)));
+    }
+
+    #[test]
+    fn negative_radius_is_empty() {
+        let empty = circle(0.0, 0.0, -1.0);
+        let large = circle(0.0, 0.0, 10.0);
+
+        assert!(!empty.contains_point((0.0, 0.0)));
+        assert!(!empty.overlaps(&large));
+        assert!(!large.overlaps(&empty));
+        assert!(!empty.overlaps(&empty));
+        assert!(!circle_rect_overlap(&empty, &rect(-5.0, -5.0, 10.0, 10.0)));
+
+        // The radius is not simply taken as its absolute value either.
+        assert!(!circle(0.0, 0.0, -3.0).overlaps(&circle(3.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn zero_radius_contains_its_center_only() {
+        let point = circle(2.0, 3.0, 0.0);
+
+        assert!(point.contains_point((2.0, 3.0)));
+        assert!(!point.contains_point((2.0, 3.001)));
+        assert!(!point.overlaps(&circle(2.0, 4.0, 1.0)));
+    }
+
+    #[test]
+    fn from_rect_inscribes_the_largest_circle() {
+        assert_eq!(Circle::from_rect(&rect(0.0, 0.0, 4.0, 2.0)), circle(2.0, 1.0, 1.0));
+        assert_eq!(Circle::from_rect(&rect(4.0, 2.0, -4.0, -2.0)), circle(2.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn zero_size_rect_has_no_sdl_equivalent() {
+        assert_eq!(rect(10.0, 10.0, 0.0, 0.0).to_sdl(), None);
+        assert_eq!(rect(10.0, 10.0, 5.0, 0.0).to_sdl(), None);
+        assert_eq!(rect(10.0, 10.0, 0.0, -5.0).to_sdl(), None);
+        // Less than half a pixel wide, so it rounds to nothing.
+        assert_eq!(rect(10.0, 10.0, 0.4, 5.0).to_sdl(), None);
+    }
+
+    #[test]
+    fn negative_size_rect_converts_without_panicking() {
+        assert_eq!(rect(10.0, 20.0, -4.0, -6.0).to_sdl(), SdlRect::new(6, 14, 4, 6).unwrap());
+        assert_eq!(rect(-2.0, -2.0, 4.0, 4.0).to_sdl(), SdlRect::new(-2, -2, 4, 4).unwrap());
+    }
+
+    #[test]
+    fn rect_larger_than_parent_cannot_be_moved_inside() {
+        let parent = rect(0.0, 0.0, 100.0, 50.0);
+
+        assert_eq!(rect(0.0, 0.0, 101.0, 10.0).moved_inside(parent), None);
+        assert_eq!(rect(0.0, 0.0, 10.0, 51.0).moved_inside(parent), None);
+        assert_eq!(rect(-20.0, -20.0, 200.0, 200.0).moved_inside(parent), None);
+    }
+
+    #[test]
+    fn rect_is_clamped_into_parent() {
+        let parent = rect(0.0, 0.0, 100.0, 50.0);
+
+        assert_eq!(rect(-5.0, 45.0, 10.0, 10.0).moved_inside(parent), Some(rect(0.0, 40.0, 10.0, 10.0)));
+        assert_eq!(rect(95.0, -1.0, 10.0, 10.0).moved_inside(parent), Some(rect(90.0, 0.0, 10.0, 10.0)));
+        assert_eq!(rect(20.0, 20.0, 10.0, 10.0).moved_inside(parent), Some(rect(20.0, 20.0, 10.0, 10.0)));
+        // Exactly as large as the parent
+        assert_eq!(rect(30.0, 30.0, 100.0, 50.0).moved_inside(parent), Some(parent));
+        // A zero-size rect fits anywhere.
+        assert_eq!(rect(200.0, 200.0, 0.0, 0.0).moved_inside(parent), Some(rect(100.0, 50.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn touching_rects_do_not_overlap() {
+        let a = rect(0.0, 0.0, 10.0, 10.0);
+
+        assert!(!a.overlaps(rect(10.0, 0.0, 10.0, 10.0)));
+        assert!(!a.overlaps(rect(0.0, -10.0, 10.0, 10.0)));
+        assert!(a.overlaps(rect(9.5, 9.5, 10.0, 10.0)));
+    }
+
+    fn sorted(mut ids: Vec<usize>) -> Vec<usize> {
+        ids.sort();
+        ids
+    }
+
+    /// A tree over 0..100 in both directions which splits as soon as a node
+    /// holds more than one entry.
+    fn small_tree() -> QuadTree<usize> {
+        QuadTree::new(rect(0.0, 0.0, 100.0, 100.0), 4, 1)
+    }
+
+    #[test]
+    fn entries_straddling_boundaries_are_returned_once() {
+        let mut tree = small_tree();
+
+        // Across the vertical boundary of the root, the horizontal one, both,
+        // and one of the boundaries of the top-left quadrant.
+        tree.insert(0, rect(45.0, 10.0, 10.0, 10.0));
+        tree.insert(1, rect(10.0, 45.0, 10.0, 10.0));
+        tree.insert(2, rect(45.0, 45.0, 10.0, 10.0));
+        tree.insert(3, rect(20.0, 5.0, 10.0, 10.0));
+        // Inside of a single quadrant, so that the tree is split further.
+        tree.insert(4, rect(5.0, 5.0, 5.0, 5.0));
+        tree.insert(5, rect(60.0, 60.0, 5.0, 5.0));
+        tree.insert(6, rect(80.0, 80.0, 5.0, 5.0));
+
+        assert_eq!(sorted(tree.query(rect(0.0, 0.0, 100.0, 100.0))), vec![0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(sorted(tree.query(rect(40.0, 0.0, 20.0, 60.0))), vec![0, 2]);
+        assert_eq!(sorted(tree.query(rect(0.0, 0.0, 50.0, 50.0))), vec![0, 1, 2, 3, 4]);
+        assert_eq!(tree.query(rect(90.0, 0.0, 10.0, 10.0)), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn entries_outside_of_the_bounds_are_kept() {
+        let mut tree = small_tree();
+        tree.insert(0, rect(-20.0, 10.0, 10.0, 10.0));
+        tree.insert(1, rect(95.0, 95.0, 10.0, 10.0));
+        tree.insert(2, rect(10.0, 10.0, 5.0, 5.0));
+
+        assert_eq!(tree.query(rect(-30.0, 0.0, 25.0, 50.0)), vec![0]);
+        assert_eq!(tree.query(rect(100.0, 100.0, 50.0, 50.0)), vec![1]);
+    }
+
+    #[test]
+    fn touching_entries_are_not_returned() {
+        let mut tree = small_tree();
+        tree.insert(0, rect(10.0, 10.0, 10.0, 10.0));
+
+        assert!(tree.query(rect(20.0, 10.0, 10.0, 10.0)).is_empty());
+        assert_eq!(tree.query(rect(19.0, 10.0, 10.0, 10.0)), vec![0]);
+    }
+
+    #[test]
+    fn clear_forgets_every_entry() {
+        let mut tree = small_tree();
+        for i in 0..10 {
+            tree.insert(i, rect(i as f64 * 10.0, 0.0, 5.0, 5.0));
+        }
+
+        tree.clear();
+        assert!(tree.query(rect(0.0, 0.0, 100.0, 100.0)).is_empty());
+    }
+
+    /// Where the `i`th of many boxes lies, spread over 800x600 without calling
+    /// a random number generator, so that the tests always see the same ones.
+    fn scattered(i: usize, side: f64) -> Rectangle {
+        rect(((i * 7919) % 800) as f64, ((i * 104729) % 600) as f64, side, side)
+    }
+
+    #[test]
+    fn query_matches_testing_every_entry() {
+        let mut tree = QuadTree::new(rect(0.0, 0.0, 800.0, 600.0), 6, 4);
+        let boxes: Vec<Rectangle> = (0..300).map(|i| scattered(i, 40.0)).collect();
+        for (i, &b) in boxes.iter().enumerate() {
+            tree.insert(i, b);
+        }
+
+        for j in 0..50 {
+            let region = scattered(j * 13 + 1, 60.0);
+            let expected: Vec<usize> = (0..boxes.len()).filter(|&i| boxes[i].overlaps(region)).collect();
+
+            assert_eq!(sorted(tree.query(region)), expected);
+        }
+    }
+
+    /// Compares finding which of 500 asteroids a bullet hits with the tree,
+    /// rebuilt on every frame like in `views::game`, and by testing them all.
+    /// Run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_quadtree_with_500_asteroids() {
+        use ::std::time::Instant;
+
+        const FRAMES: usize = 1_000;
+        const ASTEROIDS: usize = 500;
+        const BULLETS: usize = 64;
+
+        let asteroids: Vec<Rectangle> = (0..ASTEROIDS).map(|i| scattered(i, 96.0)).collect();
+        let bullets: Vec<Rectangle> = (0..BULLETS)
+            .map(|i| Rectangle { h: 4.0, ..scattered(i * 31 + 7, 8.0) })
+            .collect();
+
+        let start = Instant::now();
+        let mut tree = QuadTree::new(rect(0.0, 0.0, 800.0, 600.0), 6, 8);
+        let mut tree_hits = 0;
+        for _ in 0..FRAMES {
+            tree.clear();
+            for (i, &asteroid) in asteroids.iter().enumerate() {
+                tree.insert(i, asteroid);
+            }
+
+            for &bullet in &bullets {
+                tree_hits += tree.query(bullet).len();
+            }
+        }
+        let tree_time = start.elapsed();
+
+        let start = Instant::now();
+        let mut brute_hits = 0;
+        for _ in 0..FRAMES {
+            for &bullet in &bullets {
+                brute_hits += asteroids.iter().filter(|asteroid| asteroid.overlaps(bullet)).count();
+            }
+        }
+        let brute_time = start.elapsed();
+
+        println!("{} frames, {} asteroids, {} bullets: QuadTree in {:?}, every pair in {:?}",
+                 FRAMES, ASTEROIDS, BULLETS, tree_time, brute_time);
+        assert_eq!(tree_hits, brute_hits);
+    }
+}
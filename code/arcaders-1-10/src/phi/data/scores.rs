@@ -0,0 +1,131 @@
+use ::phi::save::{self, ScoreEntry};
+use ::std::io;
+use ::std::path::Path;
+
+
+/// The number of scores kept on the board.
+pub const MAX_SCORES: usize = 10;
+
+
+/// The best scores ever achieved, from the highest to the lowest. They are
+/// saved with `phi::save`.
+#[derive(Clone, Debug)]
+pub struct ScoreBoard {
+    scores: Vec<ScoreEntry>,
+}
+
+impl ScoreBoard {
+    pub fn new() -> ScoreBoard {
+        ScoreBoard {
+            scores: Vec::new(),
+        }
+    }
+
+    /// Reads the board saved at `path`. If the file is missing or cannot be
+    /// read, for instance because it was edited by hand, then the board is
+    /// empty, so that the game can still be played.
+    pub fn load(path: &Path) -> ScoreBoard {
+        ScoreBoard {
+            scores: save::load_scores(path).unwrap_or(Vec::new()),
+        }
+    }
+
+    /// Writes the board to `path`, replacing the previous one.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        save::save_scores(path, &self.scores)
+    }
+
+    /// Adds a score achieved now to the board and returns its rank, starting
+    /// from 0 for the best one. A score which ties with others is ranked below
+    /// them. If the rank is `MAX_SCORES` or more, then the score was not good
+    /// enough to be kept.
+    pub fn insert(&mut self, name: &str, score: u64) -> usize {
+        let date = ::std::time::SystemTime::now()
+            .duration_since(::std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+
+        let rank = self.scores.iter()
+            .position(|entry| entry.score < score)
+            .unwrap_or(self.scores.len());
+
+        self.scores.insert(rank, ScoreEntry { name: name.to_string(), score: score, date: date });
+        self.scores.truncate(MAX_SCORES);
+
+        rank
+    }
+
+    /// Whether `score` would be kept if it were inserted, so that the player
+    /// is not asked for their name in vain.
+    pub fn qualifies(&self, score: u64) -> bool {
+        self.scores.len() < MAX_SCORES || self.scores.iter().any(|entry| entry.score < score)
+    }
+
+    /// The scores, from the best to the worst.
+    pub fn scores(&self) -> &[ScoreEntry] {
+        &self.scores
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(scores: &[u64]) -> ScoreBoard {
+        let mut board = ScoreBoard::new();
+        for (i, &score) in scores.iter().enumerate() {
+            board.insert(&format!("player {}", i), score);
+        }
+        board
+    }
+
+    fn scores(board: &ScoreBoard) -> Vec<u64> {
+        board.scores().iter().map(|entry| entry.score).collect()
+    }
+
+    #[test]
+    fn scores_are_ranked_from_the_best() {
+        let mut board = board(&[300, 100]);
+
+        assert_eq!(board.insert("best", 500), 0);
+        assert_eq!(board.insert("middle", 200), 2);
+        assert_eq!(board.insert("worst", 50), 4);
+
+        assert_eq!(scores(&board), vec![500, 300, 200, 100, 50]);
+        assert_eq!(board.scores()[2].name, "middle");
+    }
+
+    #[test]
+    fn ties_rank_below_existing_scores() {
+        let mut board = board(&[300, 200, 200, 100]);
+
+        assert_eq!(board.insert("newcomer", 200), 3);
+        assert_eq!(board.scores()[3].name, "newcomer");
+        assert_eq!(board.scores()[1].name, "player 1");
+        assert_eq!(board.scores()[2].name, "player 2");
+    }
+
+    #[test]
+    fn only_the_best_scores_are_kept() {
+        let mut board = board(&[100, 90, 80, 70, 60, 50, 40, 30, 20, 10]);
+        assert!(!board.qualifies(10));
+        assert!(!board.qualifies(5));
+
+        assert_eq!(board.insert("too low", 5), MAX_SCORES);
+        assert_eq!(board.scores().len(), MAX_SCORES);
+        assert!(board.scores().iter().all(|entry| entry.name != "too low"));
+
+        // A better score pushes the worst one out.
+        assert!(board.qualifies(11));
+        assert_eq!(board.insert("just enough", 11), 9);
+        assert_eq!(scores(&board), vec![100, 90, 80, 70, 60, 50, 40, 30, 20, 11]);
+    }
+
+    #[test]
+    fn any_score_qualifies_while_the_board_is_not_full() {
+        let board = board(&[100, 90]);
+
+        assert!(board.qualifies(0));
+    }
+}
@@ -0,0 +1,64 @@
+use ::phi::data::Rectangle;
+use ::sdl2::pixels::Color;
+use ::sdl2::rect::Point;
+use ::sdl2::render::Renderer;
+
+
+/// What the view submitted to the debug overlay for the current frame, through
+/// `Phi::debug_line`, `Phi::debug_rect` and `Phi::debug_segment`. `Phi` draws
+/// it on top of the frame once the view has rendered it, so that the view's
+/// `renderer.clear()` does not wipe it, and then starts the next frame with an
+/// empty one.
+pub struct DebugOverlay {
+    rects: Vec<(Rectangle, Color)>,
+    segments: Vec<((f64, f64), (f64, f64), Color)>,
+    lines: Vec<String>,
+}
+
+impl DebugOverlay {
+    pub fn new() -> DebugOverlay {
+        DebugOverlay {
+            rects: Vec::new(),
+            segments: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn rect(&mut self, rect: Rectangle, color: Color) {
+        self.rects.push((rect, color));
+    }
+
+    pub fn segment(&mut self, a: (f64, f64), b: (f64, f64), color: Color) {
+        self.segments.push((a, b, color));
+    }
+
+    pub fn line(&mut self, line: &str) {
+        self.lines.push(line.to_string());
+    }
+
+    /// The lines of text, in the order in which they were submitted.
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Draws the outlines and the segments, in the coordinates which the view
+    /// renders to.
+    pub fn render_shapes(&self, renderer: &mut Renderer) {
+        let previous = renderer.draw_color();
+
+        for &(rect, color) in &self.rects {
+            if let Some(rect) = rect.normalized().to_sdl() {
+                renderer.set_draw_color(color);
+                renderer.draw_rect(rect);
+            }
+        }
+
+        for &((ax, ay), (bx, by), color) in &self.segments {
+            renderer.set_draw_color(color);
+            renderer.draw_lines(&[Point::new(ax.round() as i32, ay.round() as i32),
+                                  Point::new(bx.round() as i32, by.round() as i32)]);
+        }
+
+        renderer.set_draw_color(previous);
+    }
+}
@@ -0,0 +1,325 @@
+use ::std::any::{Any, TypeId};
+use ::std::collections::{BTreeMap, BTreeSet, HashMap};
+
+
+/// Identifies an entity of a `World`. Identifiers are never reused, so that an
+/// identifier kept after its entity was despawned does not point to another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EntityId(pub u32);
+
+
+/// The components of a single type, by entity. They are kept sorted by entity,
+/// so that queries visit entities in the order in which they were spawned.
+pub struct ComponentStorage<T> {
+    components: BTreeMap<EntityId, T>,
+}
+
+impl<T> ComponentStorage<T> {
+    fn new() -> ComponentStorage<T> {
+        ComponentStorage { components: BTreeMap::new() }
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&T> {
+        self.components.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut T> {
+        self.components.get_mut(&id)
+    }
+
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.components.contains_key(&id)
+    }
+
+    pub fn iter<'a>(&'a self) -> ::std::collections::btree_map::Iter<'a, EntityId, T> {
+        self.components.iter()
+    }
+
+    pub fn iter_mut<'a>(&'a mut self) -> ::std::collections::btree_map::IterMut<'a, EntityId, T> {
+        self.components.iter_mut()
+    }
+}
+
+
+/// What `World` needs to know about a storage whose type it forgot, to remove
+/// the components of an entity which is despawned.
+trait AnyStorage {
+    fn remove(&mut self, id: EntityId);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyStorage for ComponentStorage<T> {
+    fn remove(&mut self, id: EntityId) {
+        self.components.remove(&id);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+
+/// The entities of a view, such as the bullets, the enemies and the powerups,
+/// and their components, which can be any `'static` type: an entity has at most
+/// one component of each type.
+///
+/// The view which owns the world lends it to the functions which update and
+/// render its entities.
+pub struct World {
+    next_id: u32,
+    entities: BTreeSet<EntityId>,
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+}
+
+impl World {
+    pub fn new() -> World {
+        World {
+            next_id: 0,
+            entities: BTreeSet::new(),
+            storages: HashMap::new(),
+        }
+    }
+
+    /// Creates an entity without any component.
+    pub fn spawn(&mut self) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+        self.entities.insert(id);
+        id
+    }
+
+    /// Removes an entity along with all of its components. Does nothing if it
+    /// was already despawned.
+    pub fn despawn(&mut self, id: EntityId) {
+        if self.entities.remove(&id) {
+            for storage in self.storages.values_mut() {
+                storage.remove(id);
+            }
+        }
+    }
+
+    pub fn is_alive(&self, id: EntityId) -> bool {
+        self.entities.contains(&id)
+    }
+
+    /// The entities which are alive, in the order in which they were spawned.
+    pub fn entities<'a>(&'a self) -> ::std::collections::btree_set::Iter<'a, EntityId> {
+        self.entities.iter()
+    }
+
+    /// Gives `component` to the entity, replacing the component of the same
+    /// type which it had, if any, and returning it. Nothing happens if the
+    /// entity was despawned, and `component` is returned.
+    pub fn insert<T: 'static>(&mut self, id: EntityId, component: T) -> Option<T> {
+        if !self.is_alive(id) {
+            return Some(component);
+        }
+
+        self.storage_mut::<T>().components.insert(id, component)
+    }
+
+    /// Takes the component of type `T` away from the entity, and returns it.
+    pub fn remove<T: 'static>(&mut self, id: EntityId) -> Option<T> {
+        self.storage_mut::<T>().components.remove(&id)
+    }
+
+    pub fn get<T: 'static>(&self, id: EntityId) -> Option<&T> {
+        self.storage::<T>().and_then(|storage| storage.get(id))
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, id: EntityId) -> Option<&mut T> {
+        self.storage_mut::<T>().get_mut(id)
+    }
+
+    pub fn has<T: 'static>(&self, id: EntityId) -> bool {
+        self.get::<T>(id).is_some()
+    }
+
+    /// The components of type `T`, or `None` if no entity ever had one.
+    pub fn storage<T: 'static>(&self) -> Option<&ComponentStorage<T>> {
+        self.storages.get(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any().downcast_ref())
+    }
+
+    /// The components of type `T`, for instance to move every entity which has
+    /// a velocity with `iter_mut`.
+    pub fn storage_mut<T: 'static>(&mut self) -> &mut ComponentStorage<T> {
+        self.storages.entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(ComponentStorage::<T>::new()))
+            .as_any_mut().downcast_mut()
+            .unwrap()
+    }
+
+    /// Returns every entity having a component of each of the types of the
+    /// tuple `Q`, such as `(Position, Velocity)`, along with those components,
+    /// in the order in which the entities were spawned.
+    pub fn query<'w, Q: Query<'w>>(&'w self) -> Vec<(EntityId, Q::Item)> {
+        Q::fetch(self)
+    }
+}
+
+
+/// A tuple of component types which can be queried from a `World`. It is
+/// implemented for tuples of one to four types.
+pub trait Query<'w> {
+    type Item;
+    fn fetch(world: &'w World) -> Vec<(EntityId, Self::Item)>;
+}
+
+impl<'w, A: 'static> Query<'w> for (A,) {
+    type Item = (&'w A,);
+
+    fn fetch(world: &'w World) -> Vec<(EntityId, (&'w A,))> {
+        match world.storage::<A>() {
+            Some(a) => a.iter().map(|(&id, a)| (id, (a,))).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+// The first type is iterated over, and the others are looked up for each of
+// its entities.
+macro_rules! impl_query {
+    ($first:ident, $($rest:ident),+) => {
+        impl<'w, $first: 'static, $($rest: 'static),+> Query<'w> for ($first, $($rest),+) {
+            type Item = (&'w $first, $(&'w $rest),+);
+
+            #[allow(non_snake_case)]
+            fn fetch(world: &'w World) -> Vec<(EntityId, Self::Item)> {
+                let $first = match world.storage::<$first>() {
+                    Some(storage) => storage,
+                    None => return Vec::new(),
+                };
+                $(
+                    let $rest = match world.storage::<$rest>() {
+                        Some(storage) => storage,
+                        None => return Vec::new(),
+                    };
+                )+
+
+                $first.iter().filter_map(|(&id, first)| {
+                    Some((id, (first, $(match $rest.get(id) {
+                        Some(component) => component,
+                        None => return None,
+                    }),+)))
+                }).collect()
+            }
+        }
+    }
+}
+
+impl_query!(A, B);
+impl_query!(A, B, C);
+impl_query!(A, B, C, D);
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32, i32);
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32, i32);
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+
+    #[test]
+    fn inserted_components_can_be_read_back() {
+        let mut world = World::new();
+        let a = world.spawn();
+        let b = world.spawn();
+
+        assert_eq!(world.insert(a, Position(1, 2)), None);
+        assert_eq!(world.insert(b, Velocity(3, 4)), None);
+
+        assert_eq!(world.get::<Position>(a), Some(&Position(1, 2)));
+        assert_eq!(world.get::<Velocity>(b), Some(&Velocity(3, 4)));
+        assert_eq!(world.get::<Velocity>(a), None);
+        assert!(world.has::<Position>(a) && !world.has::<Position>(b));
+        // No entity ever had one.
+        assert_eq!(world.get::<Health>(a), None);
+    }
+
+    #[test]
+    fn inserting_again_replaces_the_component() {
+        let mut world = World::new();
+        let a = world.spawn();
+        world.insert(a, Health(3));
+
+        assert_eq!(world.insert(a, Health(2)), Some(Health(3)));
+        world.get_mut::<Health>(a).unwrap().0 -= 1;
+
+        assert_eq!(world.get::<Health>(a), Some(&Health(1)));
+        assert_eq!(world.remove::<Health>(a), Some(Health(1)));
+        assert!(!world.has::<Health>(a));
+    }
+
+    #[test]
+    fn despawn_removes_every_component() {
+        let mut world = World::new();
+        let a = world.spawn();
+        let b = world.spawn();
+        world.insert(a, Position(0, 0));
+        world.insert(a, Velocity(1, 1));
+        world.insert(a, Health(5));
+        world.insert(b, Position(9, 9));
+
+        world.despawn(a);
+
+        assert!(!world.is_alive(a));
+        assert!(!world.storage::<Position>().unwrap().contains(a));
+        assert!(!world.storage::<Velocity>().unwrap().contains(a));
+        assert!(!world.storage::<Health>().unwrap().contains(a));
+        assert_eq!(world.get::<Position>(b), Some(&Position(9, 9)));
+
+        // Despawning it again does nothing, and it cannot get components back.
+        world.despawn(a);
+        assert_eq!(world.insert(a, Health(1)), Some(Health(1)));
+        assert!(!world.has::<Health>(a));
+    }
+
+    #[test]
+    fn ids_are_never_reused() {
+        let mut world = World::new();
+        let a = world.spawn();
+        world.despawn(a);
+        let b = world.spawn();
+        let c = world.spawn();
+
+        assert!(a != b && a != c && b != c);
+        assert!(!world.is_alive(a));
+        assert_eq!(world.entities().cloned().collect::<Vec<_>>(), vec![b, c]);
+    }
+
+    #[test]
+    fn query_skips_entities_lacking_a_component() {
+        let mut world = World::new();
+        let ids: Vec<EntityId> = (0..5).map(|_| world.spawn()).collect();
+
+        // Inserted out of order, and only some entities move.
+        for &i in &[3, 0, 4, 1, 2] {
+            world.insert(ids[i], Position(i as i32, 0));
+        }
+        world.insert(ids[4], Velocity(4, 4));
+        world.insert(ids[1], Velocity(1, 1));
+        world.insert(ids[3], Velocity(3, 3));
+        world.despawn(ids[3]);
+
+        let moving = world.query::<(Position, Velocity)>();
+
+        assert_eq!(moving, vec![
+            (ids[1], (&Position(1, 0), &Velocity(1, 1))),
+            (ids[4], (&Position(4, 0), &Velocity(4, 4))),
+        ]);
+        assert_eq!(world.query::<(Position,)>().len(), 4);
+        assert!(world.query::<(Position, Health)>().is_empty());
+    }
+}
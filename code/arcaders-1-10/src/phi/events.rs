@@ -1,91 +1,572 @@
 macro_rules! struct_events {
     (
-        keyboard: { $( $k_alias:ident : $k_sdl:ident ),* },
+        keyboard: { $( $k_alias:ident [ $k_released:ident, $k_down:ident ] : $k_sdl:ident ),* },
+        controller: { $( $c_alias:ident : $c_sdl:ident ),* },
+        mouse: { $( $m_alias:ident : $m_sdl:ident ),* },
         else: { $( $e_alias:ident : $e_sdl:pat ),* }
     )
     => {
         use ::sdl2::EventPump;
 
 
+        /// The state of a game controller which is plugged in.
+        pub struct Gamepad {
+            // Controllers must be kept open for SDL to report their events.
+            #[allow(dead_code)]
+            controller: ::sdl2::controller::GameController,
+            // The same device, opened as a joystick, which is the only way to
+            // get the id by which the events refer to the controller.
+            joystick: ::sdl2::joystick::Joystick,
+
+            /// The position of the left stick, where each axis is in
+            /// [-1.0, 1.0] and is zero inside of the dead zone.
+            pub left_stick: (f64, f64),
+
+            // The position of every axis which has moved, normalized like
+            // `left_stick`.
+            axes: ::std::collections::HashMap<::sdl2::controller::Axis, f32>,
+            // Every button which is currently held down. See `button`.
+            buttons: ::std::collections::HashSet<::sdl2::controller::Button>,
+
+            // true  => held down
+            // false => not pressed
+            $( pub $c_alias: bool ),*
+        }
+
+        impl Gamepad {
+            /// Opens the controller with the given device index, if it is one.
+            fn open(controllers: &::sdl2::GameControllerSubsystem,
+                    joysticks: &::sdl2::JoystickSubsystem, index: u32) -> Option<Gamepad> {
+                if !controllers.is_game_controller(index) {
+                    return None;
+                }
+
+                match (controllers.open(index), joysticks.open(index)) {
+                    (Ok(controller), Ok(joystick)) => Some(Gamepad {
+                        controller: controller,
+                        joystick: joystick,
+                        left_stick: (0.0, 0.0),
+                        axes: ::std::collections::HashMap::new(),
+                        buttons: ::std::collections::HashSet::new(),
+                        $( $c_alias: false ),*
+                    }),
+                    _ => None,
+                }
+            }
+
+            /// The id by which SDL identifies this controller for as long as
+            /// it is plugged in.
+            pub fn id(&self) -> i32 {
+                self.joystick.instance_id()
+            }
+
+            /// The position of `axis`, in [-1.0, 1.0] and zero inside of the
+            /// dead zone.
+            pub fn axis(&self, axis: ::sdl2::controller::Axis) -> f32 {
+                self.axes.get(&axis).cloned().unwrap_or(0.0)
+            }
+
+            /// Same as the `pad_*` fields, but for any button, including
+            /// those which do not have a field of their own.
+            pub fn button(&self, button: ::sdl2::controller::Button) -> bool {
+                self.buttons.contains(&button)
+            }
+
+            /// Maps the raw position of an axis to [-1.0, 1.0], taking the
+            /// dead zone into account.
+            fn normalize_axis(value: i16, dead_zone: f64) -> f64 {
+                let value = (value as f64 / 32767.0).max(-1.0).min(1.0);
+
+                if value.abs() < dead_zone {
+                    0.0
+                } else {
+                    value.signum() * (value.abs() - dead_zone) / (1.0 - dead_zone)
+                }
+            }
+        }
+
+
         pub struct ImmediateEvents {
-            resize: Option<(u32, u32)>,
+            /// The new size of the window's drawable area, in pixels, if it
+            /// was resized during this frame.
+            pub resized: Option<(u32, u32)>,
+
+            /// The position of the mouse cursor inside of the window, in the
+            /// renderer's coordinates.
+            pub mouse_pos: (i32, i32),
+            /// How much the mouse wheel was scrolled during this frame.
+            /// Positive values go away from the user.
+            pub mouse_wheel: i32,
+
+            /// The text typed during this frame, while text input is enabled.
+            /// See `Events::start_text_input`. It is already composed by the
+            /// system, so it may contain accented letters and such, and the
+            /// view must handle backspace itself. `text_input` returns it as an
+            /// `Option`.
+            pub text: String,
+
+            // The transitions of every key, including those which do not have
+            // a field of their own, with the same meaning as the fields below.
+            // See `key`.
+            keys: ::std::collections::HashMap<::sdl2::keyboard::Keycode, bool>,
+            // Every key which was released during this frame. See
+            // `key_released`.
+            released: ::std::collections::HashSet<::sdl2::keyboard::Keycode>,
+
+            /// The ids of the controllers which were plugged in during this
+            /// frame.
+            pub controllers_added: Vec<i32>,
+            /// The ids of the controllers which were unplugged during this
+            /// frame.
+            pub controllers_removed: Vec<i32>,
+
+            // The transitions of the buttons of every controller, and the
+            // position of their axes, by controller id. See `controller_*`.
+            buttons: ::std::collections::HashMap<(i32, ::sdl2::controller::Button), bool>,
+            axes: ::std::collections::HashMap<(i32, ::sdl2::controller::Axis), f32>,
+            // The id of the player's controller, that is, `Events::gamepad`.
+            player_controller: Option<i32>,
+
+            // Some(true)  => went from up to down during this frame, even if
+            //                it was released again before the end of it;
+            //                keeping a key held down does not repeat it
+            // Some(false) => released during this frame, and not pressed
+            // None        => unchanged; see `Events::held` for whether it is held
             $( pub $k_alias : Option<bool> , )*
+            $( pub $c_alias : Option<bool> , )*
+            $( pub $m_alias : Option<bool> , )*
+
+            // true  => released during this frame, even if it was also
+            //          pressed during it
+            // false => not released
+            $( pub $k_released : bool , )*
+
+            // true  => held down at the end of this frame, from the frame
+            //          where it was pressed until the one where it is released;
+            //          the same as the fields of `Events::held`
+            // false => not pressed
+            $( pub $k_down : bool , )*
+
             $( pub $e_alias : bool ),*
         }
 
         impl ImmediateEvents {
             pub fn new() -> ImmediateEvents {
                 ImmediateEvents {
-                    resize: None,
+                    resized: None,
+                    mouse_pos: (0, 0),
+                    mouse_wheel: 0,
+                    text: String::new(),
+                    keys: ::std::collections::HashMap::new(),
+                    released: ::std::collections::HashSet::new(),
+                    controllers_added: Vec::new(),
+                    controllers_removed: Vec::new(),
+                    buttons: ::std::collections::HashMap::new(),
+                    axes: ::std::collections::HashMap::new(),
+                    player_controller: None,
                     $( $k_alias: None , )*
+                    $( $c_alias: None , )*
+                    $( $m_alias: None , )*
+                    $( $k_released: false , )*
+                    $( $k_down: false , )*
                     $( $e_alias: false ),*
                 }
             }
+
+            /// Same as the `key_*` fields, but for any key, including those
+            /// which do not have a field of their own.
+            pub fn key(&self, keycode: ::sdl2::keyboard::Keycode) -> Option<bool> {
+                self.keys.get(&keycode).cloned()
+            }
+
+            /// Same as the `key_*_released` fields, but for any key.
+            pub fn key_released(&self, keycode: ::sdl2::keyboard::Keycode) -> bool {
+                self.released.contains(&keycode)
+            }
+
+            /// Same as the `pad_*` fields, but for any button of the player's
+            /// controller.
+            pub fn controller_button(&self, button: ::sdl2::controller::Button) -> Option<bool> {
+                self.player_controller
+                    .and_then(|id| self.controller_button_of(id, button))
+            }
+
+            /// Same as `controller_button`, but for the controller with the
+            /// given id.
+            pub fn controller_button_of(&self, id: i32, button: ::sdl2::controller::Button) -> Option<bool> {
+                self.buttons.get(&(id, button)).cloned()
+            }
+
+            /// The position of an axis of the player's controller, in
+            /// [-1.0, 1.0] and zero inside of the dead zone. Zero if there is
+            /// no controller.
+            pub fn controller_axis(&self, axis: ::sdl2::controller::Axis) -> f32 {
+                self.player_controller
+                    .map(|id| self.controller_axis_of(id, axis))
+                    .unwrap_or(0.0)
+            }
+
+            /// Same as `controller_axis`, but for the controller with the
+            /// given id.
+            pub fn controller_axis_of(&self, id: i32, axis: ::sdl2::controller::Axis) -> f32 {
+                self.axes.get(&(id, axis)).cloned().unwrap_or(0.0)
+            }
+        }
+
+
+        /// Whether the keys and mouse buttons are currently held down.
+        pub struct HeldEvents {
+            // Every key which is currently held down. See `key`.
+            keys: ::std::collections::HashSet<::sdl2::keyboard::Keycode>,
+
+            // true  => held down, from the frame where it was pressed until
+            //          the one where it is released
+            // false => not pressed
+            $( pub $k_alias: bool , )*
+            $( pub $m_alias: bool ),*
+        }
+
+        impl HeldEvents {
+            fn new() -> HeldEvents {
+                HeldEvents {
+                    keys: ::std::collections::HashSet::new(),
+
+                    // By default, initialize every key with _not pressed_
+                    $( $k_alias: false , )*
+                    $( $m_alias: false ),*
+                }
+            }
+
+            /// Same as the `key_*` fields, but for any key, including those
+            /// which do not have a field of their own.
+            pub fn key(&self, keycode: ::sdl2::keyboard::Keycode) -> bool {
+                self.keys.contains(&keycode)
+            }
+
+            /// The transitions of a new frame, where nothing happened yet, and
+            /// the keys which are held down are still down.
+            fn next_frame(&self) -> ImmediateEvents {
+                let mut now = ImmediateEvents::new();
+                $( now.$k_down = self.$k_alias; )*
+                now
+            }
+
+            /// Records that `keycode` went down, both here and in the
+            /// transitions of the current frame, `now`. This is separate from
+            /// `Events::pump` so that it can be tested without SDL.
+            fn press_key(&mut self, now: &mut ImmediateEvents, keycode: ::sdl2::keyboard::Keycode) {
+                use ::sdl2::keyboard::Keycode::*;
+
+                if self.keys.insert(keycode) {
+                    now.keys.insert(keycode, true);
+                }
+
+                match keycode {
+                    // $( ... ),* containing $k_sdl and $k_alias means:
+                    //   "for every element ($k_alias : $k_sdl) pair,
+                    //    check whether the keycode is $k_sdl. If
+                    //    it is, then set the $k_alias fields to true."
+                    $(
+                        $k_sdl => {
+                            // Prevent multiple presses when keeping a key down
+                            // Was previously not pressed?
+                            if !self.$k_alias {
+                                // Key pressed
+                                now.$k_alias = Some(true);
+                            }
+
+                            self.$k_alias = true;
+                            now.$k_down = true;
+                        }
+                    ),* // and add a comma after every option
+                    _ => {}
+                }
+            }
+
+            /// Records that `keycode` went up. See `press_key`.
+            fn release_key(&mut self, now: &mut ImmediateEvents, keycode: ::sdl2::keyboard::Keycode) {
+                use ::sdl2::keyboard::Keycode::*;
+
+                self.keys.remove(&keycode);
+                now.released.insert(keycode);
+
+                // A key which is tapped quickly may go down and up during the
+                // same frame, in which case the press is kept, so that it is
+                // not missed by the views waiting for it.
+                if now.keys.get(&keycode) != Some(&true) {
+                    now.keys.insert(keycode, false);
+                }
+
+                match keycode {
+                    $(
+                        $k_sdl => {
+                            // Key released
+                            if now.$k_alias != Some(true) {
+                                now.$k_alias = Some(false);
+                            }
+
+                            now.$k_released = true;
+                            now.$k_down = false;
+                            self.$k_alias = false;
+                        }
+                    ),*
+                    _ => {}
+                }
+            }
         }
 
 
         pub struct Events {
             pump: EventPump,
             pub now: ImmediateEvents,
+            pub held: HeldEvents,
 
-            // true  => pressed
-            // false => not pressed
-            $( pub $k_alias: bool ),*
+            // Every controller which is plugged in, in the order in which
+            // they were connected. The first one is the player's.
+            gamepads: Vec<Gamepad>,
+
+            /// The fraction of an axis' range, starting from its center, in
+            /// which its movements are ignored.
+            pub dead_zone: f64,
+
+            subsystem: ::sdl2::GameControllerSubsystem,
+            joysticks: ::sdl2::JoystickSubsystem,
+            text_input: ::sdl2::keyboard::TextInputUtil,
+
+            /// The position of the mouse cursor inside of the window, in the
+            /// renderer's coordinates. SDL maps it to the logical size of the
+            /// renderer if one is set, so that this holds after a resize, and
+            /// to the internal resolution of `Phi` if one is set.
+            pub mouse_pos: (i32, i32),
+
+            // Where the internal resolution is shown in the window: the
+            // position of its top-left corner and its scale.
+            internal_viewport: Option<(i32, i32, i32)>,
         }
 
         impl Events {
-            pub fn new(pump: EventPump) -> Events {
+            pub fn new(pump: EventPump, subsystem: ::sdl2::GameControllerSubsystem,
+                       joysticks: ::sdl2::JoystickSubsystem,
+                       text_input: ::sdl2::keyboard::TextInputUtil) -> Events {
+                // SDL enables text input by default, which may show an
+                // on-screen keyboard; only the views which need it enable it.
+                text_input.stop();
+
                 Events {
                     pump: pump,
                     now: ImmediateEvents::new(),
+                    held: HeldEvents::new(),
+                    gamepads: Vec::new(),
+                    dead_zone: 0.25,
 
-                    // By default, initialize every key with _not pressed_
-                    $( $k_alias: false ),*
+                    subsystem: subsystem,
+                    joysticks: joysticks,
+                    text_input: text_input,
+
+                    mouse_pos: (0, 0),
+                    internal_viewport: None,
                 }
             }
 
+            /// Converts a position in the window to the renderer's coordinates.
+            fn map_mouse(viewport: Option<(i32, i32, i32)>, (x, y): (i32, i32)) -> (i32, i32) {
+                match viewport {
+                    Some((left, top, scale)) => {
+                        let scale = scale as f64;
+                        (((x - left) as f64 / scale).floor() as i32,
+                         ((y - top) as f64 / scale).floor() as i32)
+                    },
+                    None => (x, y),
+                }
+            }
+
+            /// Starts reporting the text typed by the player in `now.text`.
+            /// The keys keep being reported as usual.
+            pub fn start_text_input(&mut self) {
+                self.text_input.start();
+            }
+
+            /// Stops reporting the text typed by the player.
+            pub fn stop_text_input(&mut self) {
+                self.text_input.stop();
+            }
+
+            pub fn is_text_input_active(&self) -> bool {
+                self.text_input.is_active()
+            }
+
+            /// The controller used by the player, if one is plugged in. This is
+            /// the one which was connected first. The per-frame transitions of
+            /// its buttons are in `now`.
+            pub fn gamepad(&self) -> Option<&Gamepad> {
+                self.gamepads.first()
+            }
+
+            /// Every controller which is plugged in, starting with the
+            /// player's.
+            pub fn gamepads(&self) -> &[Gamepad] {
+                &self.gamepads
+            }
+
             pub fn pump(&mut self, renderer: &mut ::sdl2::render::Renderer) {
-                self.now = ImmediateEvents::new();
+                self.now = self.held.next_frame();
 
                 for event in self.pump.poll_iter() {
                     use ::sdl2::event::Event::*;
-                    use ::sdl2::event::WindowEventId::Resized;
-                    use ::sdl2::keyboard::Keycode::*;
+                    use ::sdl2::event::WindowEventId::SizeChanged;
 
                     match event {
-                        Window { win_event_id: Resized, .. } => {
-                            self.now.resize = Some(renderer.output_size().unwrap());
+                        // Unlike `Resized`, this is also sent when the game
+                        // resizes the window itself, or makes it fullscreen.
+                        Window { win_event_id: SizeChanged, .. } => {
+                            // The renderer may be drawing to a texture, so we
+                            // ask the window directly.
+                            self.now.resized = renderer.window().map(|window| window.drawable_size());
+                        },
+
+                        // SDL repeats the key presses while a key is held
+                        // down, which we ignore.
+                        KeyDown { repeat: true, .. } => {},
+
+                        KeyDown { keycode: Some(keycode), .. } => {
+                            self.held.press_key(&mut self.now, keycode);
                         },
 
-                        KeyDown { keycode, .. } => match keycode {
-                            // $( ... ),* containing $k_sdl and $k_alias means:
-                            //   "for every element ($k_alias : $k_sdl) pair,
-                            //    check whether the keycode is Some($k_sdl). If
-                            //    it is, then set the $k_alias fields to true."
-                            $(
-                                Some($k_sdl) => {
-                                    // Prevent multiple presses when keeping a key down
-                                    // Was previously not pressed?
-                                    if !self.$k_alias {
-                                        // Key pressed
-                                        self.now.$k_alias = Some(true);
+                        KeyUp { keycode: Some(keycode), .. } => {
+                            self.held.release_key(&mut self.now, keycode);
+                        },
+
+                        MouseMotion { x, y, .. } => {
+                            self.mouse_pos = Events::map_mouse(self.internal_viewport, (x, y));
+                        },
+
+                        MouseButtonDown { mouse_btn, x, y, .. } => {
+                            self.mouse_pos = Events::map_mouse(self.internal_viewport, (x, y));
+
+                            match mouse_btn {
+                                $(
+                                    ::sdl2::mouse::Mouse::$m_sdl => {
+                                        self.now.$m_alias = Some(true);
+                                        self.held.$m_alias = true;
                                     }
+                                ),*
+                                _ => {}
+                            }
+                        },
 
-                                    self.$k_alias = true;
+                        MouseButtonUp { mouse_btn, x, y, .. } => {
+                            self.mouse_pos = Events::map_mouse(self.internal_viewport, (x, y));
+
+                            match mouse_btn {
+                                $(
+                                    ::sdl2::mouse::Mouse::$m_sdl => {
+                                        // Like a key, a quick click is
+                                        // reported as such.
+                                        if self.now.$m_alias != Some(true) {
+                                            self.now.$m_alias = Some(false);
+                                        }
+
+                                        self.held.$m_alias = false;
+                                    }
+                                ),*
+                                _ => {}
+                            }
+                        },
+
+                        MouseWheel { y, .. } => {
+                            self.now.mouse_wheel += y;
+                        },
+
+                        TextInput { text, .. } => {
+                            self.now.text.push_str(&text);
+                        },
+
+                        ControllerDeviceAdded { which, .. } => {
+                            // SDL also sends this event for the controllers
+                            // which are plugged in when the game starts, so
+                            // that they are opened automatically. If one cannot
+                            // be opened, we simply ignore it.
+                            if let Some(pad) = Gamepad::open(&self.subsystem, &self.joysticks, which as u32) {
+                                if !self.gamepads.iter().any(|known| known.id() == pad.id()) {
+                                    self.now.controllers_added.push(pad.id());
+                                    self.gamepads.push(pad);
                                 }
-                            ),* // and add a comma after every option
-                            _ => {}
+                            }
                         },
 
-                        KeyUp { keycode, .. } => match keycode {
-                            $(
-                                Some($k_sdl) => {
-                                    // Key released
-                                    self.now.$k_alias = Some(false);
-                                    self.$k_alias = false;
+                        ControllerDeviceRemoved { which, .. } => {
+                            if let Some(index) = self.gamepads.iter().position(|pad| pad.id() == which) {
+                                let pad = self.gamepads.remove(index);
+
+                                // Release everything that the player's
+                                // controller held, so that the views do not
+                                // wait for it. The next controller, if any,
+                                // becomes the player's.
+                                if index == 0 {
+                                    $(
+                                        if pad.$c_alias {
+                                            self.now.$c_alias = Some(false);
+                                        }
+                                    )*
                                 }
-                            ),*
-                            _ => {}
+
+                                self.now.controllers_removed.push(which);
+                            }
+                        },
+
+                        ControllerButtonDown { which, button, .. } => {
+                            if let Some(index) = self.gamepads.iter().position(|pad| pad.id() == which) {
+                                self.now.buttons.insert((which, button), true);
+                                let pad = &mut self.gamepads[index];
+                                pad.buttons.insert(button);
+
+                                match button {
+                                    $(
+                                        ::sdl2::controller::Button::$c_sdl => {
+                                            if index == 0 && !pad.$c_alias {
+                                                self.now.$c_alias = Some(true);
+                                            }
+
+                                            pad.$c_alias = true;
+                                        }
+                                    ),*
+                                    _ => {}
+                                }
+                            }
+                        },
+
+                        ControllerButtonUp { which, button, .. } => {
+                            if let Some(index) = self.gamepads.iter().position(|pad| pad.id() == which) {
+                                self.now.buttons.insert((which, button), false);
+                                let pad = &mut self.gamepads[index];
+                                pad.buttons.remove(&button);
+
+                                match button {
+                                    $(
+                                        ::sdl2::controller::Button::$c_sdl => {
+                                            if index == 0 && self.now.$c_alias != Some(true) {
+                                                self.now.$c_alias = Some(false);
+                                            }
+
+                                            pad.$c_alias = false;
+                                        }
+                                    ),*
+                                    _ => {}
+                                }
+                            }
+                        },
+
+                        ControllerAxisMotion { which, axis, value, .. } => {
+                            if let Some(pad) = self.gamepads.iter_mut().find(|pad| pad.id() == which) {
+                                let value = Gamepad::normalize_axis(value, self.dead_zone);
+                                pad.axes.insert(axis, value as f32);
+
+                                match axis {
+                                    ::sdl2::controller::Axis::LeftX => pad.left_stick.0 = value,
+                                    ::sdl2::controller::Axis::LeftY => pad.left_stick.1 = value,
+                                    _ => {}
+                                }
+                            }
                         },
 
                         $(
@@ -98,6 +579,15 @@ macro_rules! struct_events {
                         _ => {}
                     }
                 }
+
+                self.now.mouse_pos = self.mouse_pos;
+
+                self.now.player_controller = self.gamepads.first().map(|pad| pad.id());
+                for pad in &self.gamepads {
+                    for (&axis, &value) in &pad.axes {
+                        self.now.axes.insert((pad.id(), axis), value);
+                    }
+                }
             }
         }
     }
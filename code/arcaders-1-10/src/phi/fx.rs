@@ -0,0 +1,91 @@
+use ::phi::camera::Camera;
+
+
+/// Shakes the screen for a short while, for instance when something explodes.
+/// The view owning it calls `update` once per update, and draws the world
+/// through `apply`, so that everything is moved by the same random offset.
+pub struct ScreenShake {
+    /// The largest distance, in pixels, by which the screen was moved when the
+    /// current shake started.
+    magnitude: f64,
+    duration: f64,
+    elapsed: f64,
+    offset: (f64, f64),
+}
+
+impl ScreenShake {
+    pub fn new() -> ScreenShake {
+        ScreenShake {
+            magnitude: 0.0,
+            duration: 0.0,
+            elapsed: 0.0,
+            offset: (0.0, 0.0),
+        }
+    }
+
+    /// Shakes the screen by up to `magnitude` pixels, decaying to nothing over
+    /// `duration` seconds. If the screen is already shaking harder, then the
+    /// current shake goes on unchanged, so that several explosions at once do
+    /// not add up.
+    pub fn shake(&mut self, magnitude: f64, duration: f64) {
+        if magnitude <= 0.0 || duration <= 0.0 || magnitude < self.current_magnitude() {
+            return;
+        }
+
+        self.magnitude = magnitude;
+        self.duration = duration;
+        self.elapsed = 0.0;
+    }
+
+    /// Stops shaking the screen right away.
+    pub fn stop(&mut self) {
+        self.magnitude = 0.0;
+        self.offset = (0.0, 0.0);
+    }
+
+    /// Whether the screen is still shaking.
+    pub fn is_active(&self) -> bool {
+        self.current_magnitude() > 0.0
+    }
+
+    /// The largest distance by which the screen is currently moved. It decays
+    /// quadratically, so that the shake ends smoothly rather than abruptly.
+    pub fn current_magnitude(&self) -> f64 {
+        if self.elapsed >= self.duration {
+            return 0.0;
+        }
+
+        let left = 1.0 - self.elapsed / self.duration;
+        self.magnitude * left * left
+    }
+
+    /// Advances the shake by `dt` seconds, and picks a new offset.
+    pub fn update(&mut self, dt: f64) {
+        self.elapsed += dt;
+
+        let magnitude = self.current_magnitude();
+        if magnitude == 0.0 {
+            self.offset = (0.0, 0.0);
+            return;
+        }
+
+        let angle = ::rand::random::<f64>() * 2.0 * ::std::f64::consts::PI;
+        let distance = ::rand::random::<f64>() * magnitude;
+        self.offset = (angle.cos() * distance, angle.sin() * distance);
+    }
+
+    /// The distance by which the screen is currently moved, in pixels.
+    pub fn offset(&self) -> (f64, f64) {
+        self.offset
+    }
+
+    /// Returns `camera` moved by the current offset, to render the world
+    /// through it. The camera itself is left untouched.
+    pub fn apply(&self, camera: &Camera) -> Camera {
+        Camera {
+            x: camera.x + self.offset.0,
+            y: camera.y + self.offset.1,
+            ..*camera
+        }
+    }
+}
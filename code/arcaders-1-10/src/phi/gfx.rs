@@ -1,15 +1,58 @@
+use ::phi::Phi;
+use ::phi::camera::Camera;
+use ::phi::collisions::Mask;
 use ::phi::data::Rectangle;
+use ::phi::tween::Lerp;
+use ::rustc_serialize::json::Json;
 use ::std::cell::RefCell;
+use ::std::collections::HashMap;
+use ::std::fs::File;
+use ::std::io::Read;
 use ::std::path::Path;
 use ::std::rc::Rc;
-use ::sdl2::render::{Renderer, Texture};
-use ::sdl2_image::LoadTexture;
+use ::sdl2::pixels::{Color, PixelFormatEnum};
+use ::sdl2::rect::Point;
+use ::sdl2::render::{BlendMode, Renderer, Texture};
 
 
 /// Common interface for rendering a graphical component to some given region
 /// of the window.
 pub trait Renderable {
     fn render(&self, renderer: &mut Renderer, dest: Rectangle);
+
+    /// Same as `render`, but mirrors the image horizontally if `flip_h` is
+    /// true, and vertically if `flip_v` is true.
+    ///
+    /// Flipping is done in the image's own coordinates. If the image is also
+    /// rotated, it is flipped first and then rotated around the center of
+    /// `dest`, as SDL does, so that a flipped ship still turns in the
+    /// direction it faces.
+    fn render_flipped(&self, renderer: &mut Renderer, dest: Rectangle, flip_h: bool, flip_v: bool) {
+        self.render_ex(renderer, dest, 0.0, None, flip_h, flip_v);
+    }
+
+    /// Same as `render_flipped`, but also turns the image by `angle` degrees,
+    /// clockwise, like SDL does. It turns around `center`, relative to the
+    /// top-left corner of `dest`, or around the center of `dest` if `center`
+    /// is `None`.
+    fn render_ex(&self, renderer: &mut Renderer, dest: Rectangle, angle: f64,
+                 center: Option<(f64, f64)>, flip_h: bool, flip_v: bool);
+
+    /// Same as `render`, but `dest` is in the coordinates of the world, and the
+    /// image is drawn where `camera` shows it. Nothing is drawn if it is not
+    /// on the screen.
+    fn render_world(&self, renderer: &mut Renderer, camera: &Camera, dest: Rectangle) {
+        if camera.is_visible(dest) {
+            self.render(renderer, camera.to_screen(dest));
+        }
+    }
+}
+
+/// The point around which `render_ex` turns an image drawn in `dest`, relative
+/// to the top-left corner of `dest`: `center` if it is given, and the middle of
+/// `dest` otherwise.
+pub fn rotation_center(dest: Rectangle, center: Option<(f64, f64)>) -> (f64, f64) {
+    center.unwrap_or((dest.w / 2.0, dest.h / 2.0))
 }
 
 
@@ -18,6 +61,15 @@ pub trait Renderable {
 pub struct Sprite {
     tex: Rc<RefCell<Texture>>,
     src: Rectangle,
+    /// The rotation of the sprite around the center of its destination, in
+    /// degrees, clockwise.
+    angle: f64,
+    flip_h: bool,
+    flip_v: bool,
+    /// The tint and opacity applied to the texture whenever this sprite, and
+    /// only this one, is copied.
+    color_mod: (u8, u8, u8),
+    alpha: u8,
 }
 
 impl Sprite {
@@ -32,14 +84,243 @@ impl Sprite {
                 h: tex_query.height as f64,
                 x: 0.0,
                 y: 0.0,
-            }
+            },
+            angle: 0.0,
+            flip_h: false,
+            flip_v: false,
+            color_mod: (255, 255, 255),
+            alpha: 255,
+        }
+    }
+
+    /// Creates a sprite of size zero, which renders nothing.
+    pub fn empty(renderer: &Renderer) -> Option<Sprite> {
+        renderer.create_texture_streaming(PixelFormatEnum::RGBA8888, (1, 1)).ok()
+            .map(|texture| Sprite {
+                tex: Rc::new(RefCell::new(texture)),
+                src: Rectangle { x: 0.0, y: 0.0, w: 0.0, h: 0.0 },
+                angle: 0.0,
+                flip_h: false,
+                flip_v: false,
+                color_mod: (255, 255, 255),
+                alpha: 255,
+            })
+    }
+
+    /// Creates a blank, transparent sprite of `w` by `h` pixels that can be
+    /// rendered to with `Phi::with_render_target`.
+    pub fn new_render_target(renderer: &Renderer, w: u32, h: u32) -> Result<Sprite, String> {
+        let mut texture = renderer.create_texture_target(PixelFormatEnum::RGBA8888, (w, h))
+            .map_err(|e| e.0)?;
+        texture.set_blend_mode(BlendMode::Blend);
+        Ok(Sprite::new(texture))
+    }
+
+    /// Creates a new sprite showing the whole of a texture which may also be
+    /// used elsewhere, for example one returned by `Phi::texture`.
+    pub fn from_shared(tex: Rc<RefCell<Texture>>) -> Sprite {
+        let tex_query = tex.borrow().query();
+
+        Sprite {
+            tex: tex,
+            src: Rectangle {
+                w: tex_query.width as f64,
+                h: tex_query.height as f64,
+                x: 0.0,
+                y: 0.0,
+            },
+            angle: 0.0,
+            flip_h: false,
+            flip_v: false,
+            color_mod: (255, 255, 255),
+            alpha: 255,
         }
     }
 
     /// Creates a new sprite from an image file located at the given path.
     /// Returns `Some` if the file could be read, and `None` otherwise.
-    pub fn load(renderer: &Renderer, path: &str) -> Option<Sprite> {
-        renderer.load_texture(Path::new(path)).ok().map(Sprite::new)
+    ///
+    /// The texture is shared with every other sprite loaded from the same
+    /// file, using the cache of `phi`.
+    pub fn load(phi: &mut Phi, path: &str) -> Option<Sprite> {
+        phi.sprite(path).ok()
+    }
+
+    /// Same as `load`, but reports why the file could not be read.
+    pub fn from_path(phi: &mut Phi, path: &str) -> Result<Sprite, String> {
+        phi.sprite(path)
+    }
+
+
+    /// Loads every frame described by a TexturePacker-style JSON file, in
+    /// either its "hash" or "array" format, as named sprites. The frames all
+    /// share the texture loaded from `meta.image`, whose path is relative to
+    /// the JSON file, and which is kept in the cache of `phi`.
+    ///
+    /// Returns an error if the descriptor cannot be read or parsed, or if one
+    /// of the frames does not fit inside of the texture.
+    pub fn load_atlas(phi: &mut Phi, json_path: &str) -> Result<HashMap<String, Sprite>, String> {
+        let mut source = String::new();
+        File::open(json_path)
+            .and_then(|mut file| file.read_to_string(&mut source))
+            .map_err(|e| format!("{}: {}", json_path, e))?;
+
+        let json = Json::from_str(&source).map_err(|e| format!("{}: {}", json_path, e))?;
+
+        let image = json.find_path(&["meta", "image"]).and_then(|image| image.as_string())
+            .ok_or(format!("{}: missing meta.image", json_path))?;
+        let image_path = Path::new(json_path).parent().unwrap_or(Path::new("")).join(image);
+
+        // Through the cache, so that the image is only decoded once even if a
+        // view also loads it with `Phi::sprite`.
+        let sheet = phi.sprite(&image_path.to_string_lossy())?;
+
+        // Collect the (name, frame) pairs of both formats.
+        let frames: Vec<(String, &Json)> = match json.find("frames") {
+            Some(&Json::Object(ref frames)) =>
+                frames.iter().map(|(name, frame)| (name.clone(), frame)).collect(),
+
+            Some(&Json::Array(ref frames)) =>
+                frames.iter().map(|frame| {
+                    frame.find("filename").and_then(|name| name.as_string())
+                        .map(|name| (name.to_string(), frame))
+                        .ok_or(format!("{}: frame without a filename", json_path))
+                }).collect::<Result<_, String>>()?,
+
+            _ => return Err(format!("{}: missing frames", json_path)),
+        };
+
+        let mut sprites = HashMap::new();
+
+        for (name, frame) in frames {
+            let field = |key: &str| {
+                frame.find_path(&["frame", key]).and_then(|value| value.as_f64())
+                    .ok_or(format!("{}: frame {} has no {}", json_path, name, key))
+            };
+
+            let rect = Rectangle {
+                x: field("x")?,
+                y: field("y")?,
+                w: field("w")?,
+                h: field("h")?,
+            };
+
+            let sprite = sheet.region(rect).ok_or(format!(
+                "{}: frame {} at ({}, {}) of size {}x{} lies outside of the {}x{} texture",
+                json_path, name, rect.x, rect.y, rect.w, rect.h, sheet.src.w, sheet.src.h))?;
+
+            sprites.insert(name, sprite);
+        }
+
+        Ok(sprites)
+    }
+
+
+    /// Sets the opacity of the sprite, from 0 (invisible) to 255 (opaque), for
+    /// instance to fade an explosion out.
+    ///
+    /// Sprites loaded from the same file, or cut from the same spritesheet,
+    /// share their texture, so the opacity is only applied to the texture
+    /// while this sprite is copied, and the other sprites are unaffected.
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.alpha = alpha;
+    }
+
+    pub fn alpha(&self) -> u8 {
+        self.alpha
+    }
+
+    /// Multiplies the color of every pixel of the sprite by `color / 255`.
+    /// For instance, `Color::RGB(255, 0, 0)` keeps only its red component, and
+    /// `Color::RGB(255, 255, 255)` restores it. The alpha of `color` is
+    /// ignored: use `set_alpha` instead.
+    ///
+    /// Like `set_alpha`, this only affects this sprite.
+    pub fn set_color_mod(&mut self, color: Color) {
+        self.color_mod = match color {
+            Color::RGB(r, g, b) | Color::RGBA(r, g, b, _) => (r, g, b),
+        };
+    }
+
+    pub fn color_mod(&self) -> Color {
+        let (r, g, b) = self.color_mod;
+        Color::RGB(r, g, b)
+    }
+
+    /// Returns a copy of the sprite, sharing its texture, tinted by `color`.
+    /// See `set_color_mod`.
+    pub fn tinted(&self, color: Color) -> Sprite {
+        let mut sprite = self.clone();
+        sprite.set_color_mod(color);
+        sprite
+    }
+
+    /// Returns a copy of the sprite with a texture of its own, which can then
+    /// be changed, for instance drawn on, without affecting the others. Only
+    /// the region of the sprite is copied, without its tint. Returns `None` if
+    /// the platform does not support rendering to a texture.
+    pub fn clone_texture(&self, phi: &mut Phi) -> Option<Sprite> {
+        let (w, h) = (self.src.w as u32, self.src.h as u32);
+
+        let copy = phi.render_to_texture(w, h, |phi| {
+            self.copy_raw(&mut phi.renderer, false, false);
+        });
+
+        copy.ok().map(|mut copy| {
+            copy.set_blend_mode(BlendMode::Blend);
+            Sprite::new(copy)
+        })
+    }
+
+    // Copies the region of the sprite over the whole of the current render
+    // target, exactly as it is in the texture: the pixels of the target are
+    // replaced rather than blended with, and the tint, opacity and angle of
+    // every sprite are ignored. Only the given flips are applied.
+    fn copy_raw(&self, renderer: &mut Renderer, flip_h: bool, flip_v: bool) {
+        let src = match self.src.to_sdl() {
+            Some(src) => src,
+            None => return,
+        };
+
+        let mut tex = self.tex.borrow_mut();
+        let blend_mode = tex.blend_mode();
+        let alpha = tex.alpha_mod();
+        let (r, g, b) = tex.color_mod();
+        tex.set_blend_mode(BlendMode::None);
+        tex.set_alpha_mod(255);
+        tex.set_color_mod(255, 255, 255);
+
+        renderer.copy_ex(&tex, Some(src), None, 0.0, None, (flip_h, flip_v));
+
+        tex.set_blend_mode(blend_mode);
+        tex.set_alpha_mod(alpha);
+        tex.set_color_mod(r, g, b);
+    }
+
+
+    /// Sets the rotation of the sprite, in degrees, clockwise. It turns around
+    /// the center of the rectangle in which it is rendered.
+    ///
+    /// Unlike the tint, the orientation belongs to this sprite only.
+    pub fn set_angle(&mut self, angle: f64) {
+        self.angle = angle;
+    }
+
+    pub fn angle(&self) -> f64 {
+        self.angle
+    }
+
+    /// Mirrors the sprite horizontally if `flip_h` is true, and vertically if
+    /// `flip_v` is true, whenever it is rendered.
+    pub fn set_flip(&mut self, flip_h: bool, flip_v: bool) {
+        self.flip_h = flip_h;
+        self.flip_v = flip_v;
+    }
+
+
+    /// The texture of the sprite, which may be shared with other sprites.
+    pub fn texture(&self) -> &Rc<RefCell<Texture>> {
+        &self.tex
     }
 
 
@@ -49,6 +330,28 @@ impl Sprite {
     }
 
 
+    /// Computes which pixels of the sprite are solid, that is, those whose
+    /// alpha is at least `threshold_alpha`, for exact collisions. This reads
+    /// the texture back from the graphics card, which is slow, so it should be
+    /// done once, when the sprite is loaded. The sprite's flips are taken into
+    /// account, but not its angle, nor its tint and opacity.
+    pub fn collision_mask(&self, phi: &mut Phi, threshold_alpha: u8) -> Result<Mask, String> {
+        let (w, h) = (self.src.w as u32, self.src.h as u32);
+        let target = Sprite::new_render_target(&phi.renderer, w, h)?;
+        let mut pixels = Err("The sprite was not rendered".to_string());
+
+        phi.with_render_target(&target, |phi| {
+            phi.renderer.set_draw_color(Color::RGBA(0, 0, 0, 0));
+            phi.renderer.clear();
+            self.copy_raw(&mut phi.renderer, self.flip_h, self.flip_v);
+            pixels = phi.renderer.read_pixels(None, PixelFormatEnum::ARGB8888).map_err(|e| e.0);
+        })?;
+
+        let pixels = pixels?;
+        Ok(Mask::from_argb(&pixels, w as usize, h as usize, w as usize * 4, threshold_alpha))
+    }
+
+
     /// Returns a new `Sprite` representing a sub-region of the current one.
     /// The provided `rect` is relative to the currently held region.
     /// Returns `Some` if the `rect` is valid, i.e. included in the current
@@ -65,6 +368,7 @@ impl Sprite {
             Some(Sprite {
                 tex: self.tex.clone(),
                 src: new_src,
+                ..*self
             })
         } else {
             None
@@ -74,43 +378,214 @@ impl Sprite {
 
 impl Renderable for Sprite {
     fn render(&self, renderer: &mut Renderer, dest: Rectangle) {
-        renderer.copy(&mut self.tex.borrow_mut(), self.src.to_sdl(), dest.to_sdl())
+        // SDL would understand an empty rectangle as the whole texture, or the
+        // whole screen, so empty sprites are skipped.
+        self.render_flipped(renderer, dest, false, false)
+    }
+
+    /// Turns and mirrors the sprite on top of its own angle and flips, so that
+    /// flipping a sprite which is already flipped restores it.
+    fn render_ex(&self, renderer: &mut Renderer, dest: Rectangle, angle: f64,
+                 center: Option<(f64, f64)>, flip_h: bool, flip_v: bool) {
+        let flip = (self.flip_h != flip_h, self.flip_v != flip_v);
+        let angle = self.angle + angle;
+
+        if let (Some(src), Some(sdl_dest)) = (self.src.to_sdl(), dest.to_sdl()) {
+            let mut tex = self.tex.borrow_mut();
+
+            // The texture may be shared with other sprites, so its own
+            // modulation is restored once this sprite has been copied.
+            let tinted = self.color_mod != (255, 255, 255) || self.alpha != 255;
+            let saved = (tex.color_mod(), tex.alpha_mod(), tex.blend_mode());
+
+            if tinted {
+                let (r, g, b) = self.color_mod;
+                tex.set_color_mod(r, g, b);
+                tex.set_alpha_mod(self.alpha);
+
+                // Some drivers silently ignore the alpha without blending.
+                if self.alpha != 255 {
+                    tex.set_blend_mode(BlendMode::Blend);
+                }
+            }
+
+            if angle == 0.0 && flip == (false, false) {
+                renderer.copy(&tex, Some(src), Some(sdl_dest))
+            } else {
+                let (cx, cy) = rotation_center(dest, center);
+                renderer.copy_ex(&tex, Some(src), Some(sdl_dest),
+                                 angle, Some(Point::new(cx.round() as i32, cy.round() as i32)), flip)
+            }
+
+            if tinted {
+                let ((r, g, b), alpha, blend_mode) = saved;
+                tex.set_color_mod(r, g, b);
+                tex.set_alpha_mod(alpha);
+                tex.set_blend_mode(blend_mode);
+            }
+        }
+    }
+}
+
+
+
+/// A sprite cut into a grid of frames of the same size, starting from its
+/// top-left corner. Partial cells on the right and bottom edges are ignored.
+#[derive(Clone)]
+pub struct SpriteSheet {
+    pub sprite: Sprite,
+    pub frame_w: u32,
+    pub frame_h: u32,
+}
+
+impl SpriteSheet {
+    pub fn new(sprite: Sprite, frame_w: u32, frame_h: u32) -> SpriteSheet {
+        SpriteSheet {
+            sprite: sprite,
+            frame_w: frame_w,
+            frame_h: frame_h,
+        }
+    }
+
+    /// The number of columns and rows of frames in the sheet.
+    pub fn grid(&self) -> (usize, usize) {
+        if self.frame_w == 0 || self.frame_h == 0 {
+            return (0, 0);
+        }
+
+        let (w, h) = self.sprite.size();
+        ((w / self.frame_w as f64) as usize, (h / self.frame_h as f64) as usize)
+    }
+
+    /// The number of frames in the sheet.
+    pub fn len(&self) -> usize {
+        let (columns, rows) = self.grid();
+        columns * rows
+    }
+
+    /// Returns the frame at `index`, counting in row-major order, or `None`
+    /// if the sheet has no such frame.
+    pub fn frame(&self, index: usize) -> Option<Sprite> {
+        let (columns, _) = self.grid();
+
+        if index >= self.len() {
+            return None;
+        }
+
+        self.sprite.region(Rectangle {
+            w: self.frame_w as f64,
+            h: self.frame_h as f64,
+            x: (self.frame_w as usize * (index % columns)) as f64,
+            y: (self.frame_h as usize * (index / columns)) as f64,
+        })
+    }
+
+    /// Returns every frame of the sheet, in row-major order.
+    pub fn frames(&self) -> Vec<Sprite> {
+        (0..self.len()).filter_map(|index| self.frame(index)).collect()
     }
 }
 
 
+/// Describes how the frames of an animation are laid out in a spritesheet.
+#[derive(Clone)]
+pub struct AnimatedSpriteDescr<'a> {
+    /// The path of the spritesheet.
+    pub image_path: &'a str,
+    /// The number of frames in the animation. The last row may be incomplete.
+    pub total_frames: usize,
+    /// The number of columns and rows of the grid.
+    pub frames_wide: usize,
+    pub frames_high: usize,
+    /// The size of a single frame, in pixels.
+    pub frame_w: f64,
+    pub frame_h: f64,
+    /// The number of pixels around the grid, and between two cells.
+    pub margin: f64,
+    pub spacing: f64,
+}
+
 
 #[derive(Clone)]
 pub struct AnimatedSprite {
     /// The frames that will be rendered, in order.
     sprites: Rc<Vec<Sprite>>,
 
-    /// The time it takes to get from one frame to the next, in seconds.
-    frame_delay: f64,
+    /// The number of frames shown every second. If it is negative, then the
+    /// animation is played backwards; if it is zero, then it is frozen.
+    fps: f64,
 
-    /// The total time that the sprite has been alive, from which the current
-    /// frame is derived.
-    current_time: f64,
+    /// The position of the animation, in frames. Its integer part is the index
+    /// of the current frame. This way, changing the speed of the animation
+    /// does not change the frame which is shown.
+    position: f64,
+
+    /// Whether the animation starts over once it reaches its end. Otherwise,
+    /// it stays on its last frame.
+    looping: bool,
 }
 
 impl AnimatedSprite {
     /// Creates a new animated sprite initialized at time 0.
     pub fn new(sprites: Vec<Sprite>, frame_delay: f64) -> AnimatedSprite {
-        AnimatedSprite {
-            sprites: Rc::new(sprites),
-            frame_delay: frame_delay,
-            current_time: 0.0,
-        }
+        let mut sprite = AnimatedSprite::with_fps(sprites, 0.0);
+        sprite.set_frame_delay(frame_delay);
+        sprite
     }
 
     /// Creates a new animated sprite which goes to the next frame `fps` times
     /// every second.
     pub fn with_fps(sprites: Vec<Sprite>, fps: f64) -> AnimatedSprite {
-        if fps == 0.0 {
-            panic!("Passed 0 to AnimatedSprite::with_fps");
+        AnimatedSprite {
+            sprites: Rc::new(sprites),
+            fps: fps,
+            position: 0.0,
+            looping: true,
+        }
+    }
+
+
+    /// Creates an animation showing the frames of `sheet` at the given
+    /// indices, in that order, so that frames may be reused or skipped.
+    /// Returns `None` if one of the indices lies outside of the sheet.
+    pub fn from_sheet(sheet: &SpriteSheet, frames: &[usize], fps: f64) -> Option<AnimatedSprite> {
+        let sprites: Option<Vec<Sprite>> = frames.iter().map(|&index| sheet.frame(index)).collect();
+        sprites.map(|sprites| AnimatedSprite::with_fps(sprites, fps))
+    }
+
+
+    /// Slices the spritesheet described by `descr` into frames, returned in
+    /// row-major order. Panics if the sheet cannot be loaded, or if it is too
+    /// small for the requested grid.
+    pub fn load_frames(phi: &mut Phi, descr: AnimatedSpriteDescr) -> Vec<Sprite> {
+        let spritesheet = Sprite::load(phi, descr.image_path)
+            .expect(&format!("Could not load spritesheet {}", descr.image_path));
+
+        let (sheet_w, sheet_h) = spritesheet.size();
+        let expected_w = descr.margin * 2.0 + descr.frames_wide as f64 * (descr.frame_w + descr.spacing) - descr.spacing;
+        let expected_h = descr.margin * 2.0 + descr.frames_high as f64 * (descr.frame_h + descr.spacing) - descr.spacing;
+
+        if expected_w > sheet_w || expected_h > sheet_h {
+            panic!("Spritesheet {} is {}x{}, but a grid of {}x{} frames requires at least {}x{}",
+                   descr.image_path, sheet_w, sheet_h,
+                   descr.frames_wide, descr.frames_high, expected_w, expected_h);
+        }
+
+        if descr.total_frames > descr.frames_wide * descr.frames_high {
+            panic!("Spritesheet {} has room for {} frames, but {} were requested",
+                   descr.image_path, descr.frames_wide * descr.frames_high, descr.total_frames);
         }
 
-        AnimatedSprite::new(sprites, 1.0 / fps)
+        (0..descr.total_frames).map(|i| {
+            let (xth, yth) = (i % descr.frames_wide, i / descr.frames_wide);
+
+            spritesheet.region(Rectangle {
+                w: descr.frame_w,
+                h: descr.frame_h,
+                x: descr.margin + (descr.frame_w + descr.spacing) * xth as f64,
+                y: descr.margin + (descr.frame_h + descr.spacing) * yth as f64,
+            }).unwrap()
+        }).collect()
     }
 
 
@@ -120,53 +595,732 @@ impl AnimatedSprite {
     }
 
     /// Set the time it takes to get from one frame to the next, in seconds.
-    /// If the value is negative, then we "rewind" the animation.
+    /// If the value is negative, then we "rewind" the animation. A delay of
+    /// zero freezes it.
     pub fn set_frame_delay(&mut self, frame_delay: f64) {
-        self.frame_delay = frame_delay;
+        self.fps =
+            if frame_delay == 0.0 { 0.0 }
+            else { 1.0 / frame_delay };
     }
 
     /// Set the number of frames the animation goes through every second.
-    /// If the value is negative, then we "rewind" the animation.
+    /// If the value is negative, then we "rewind" the animation; if it is
+    /// zero, then the animation is frozen. The current frame is kept.
     pub fn set_fps(&mut self, fps: f64) {
-        if fps == 0.0 {
-            panic!("Passed 0 to AnimatedSprite::set_fps");
-        }
+        self.fps = fps;
+    }
+
+    pub fn fps(&self) -> f64 {
+        self.fps
+    }
 
-        self.set_frame_delay(1.0 / fps);
+    /// Whether the animation starts over once it is done, which is the
+    /// default. Otherwise, it stops on its last frame (or its first one, when
+    /// it is played backwards).
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
     }
 
-    /// Adds a certain amount of time, in seconds, to the `current_time` of the
-    /// animated sprite, so that it knows when it must go to the next frame.
+    /// Whether an animation which does not loop has reached its end. Looping
+    /// animations are never finished.
+    pub fn is_finished(&self) -> bool {
+        !self.looping && (self.position >= self.frames() as f64 || self.position < 0.0)
+    }
+
+    /// Adds a certain amount of time, in seconds, to the animated sprite, so
+    /// that it knows when it must go to the next frame.
     pub fn add_time(&mut self, dt: f64) {
-        self.current_time += dt;
+        let frames = self.frames() as f64;
+        self.position += dt * self.fps;
 
-        // If we decide to go "back in time", this allows us to select the
-        // last frame whenever we reach a negative one.
-        if (self.current_time < 0.0) {
-            self.current_time = (self.frames() - 1) as f64 * self.frame_delay;
+        if self.looping {
+            // If we decide to go "back in time", this allows us to select the
+            // last frame whenever we reach a negative one.
+            self.position = ((self.position % frames) + frames) % frames;
+        } else {
+            // Stay just past the end, so that `is_finished` can tell.
+            self.position = self.position.max(-1.0).min(frames);
         }
     }
+
+    /// The index of the frame which is currently shown.
+    pub fn current_frame(&self) -> usize {
+        let last = self.frames() as f64 - 1.0;
+        self.position.max(0.0).min(last) as usize
+    }
 }
 
 impl Renderable for AnimatedSprite {
     /// Renders the current frame of the sprite.
     fn render(&self, renderer: &mut Renderer, dest: Rectangle) {
-        let current_frame =
-            (self.current_time / self.frame_delay) as usize % self.frames();
-
-        let sprite = &self.sprites[current_frame];
+        let sprite = &self.sprites[self.current_frame()];
         sprite.render(renderer, dest);
     }
+
+    /// Renders the current frame of the sprite, turned and flipped.
+    fn render_ex(&self, renderer: &mut Renderer, dest: Rectangle, angle: f64,
+                 center: Option<(f64, f64)>, flip_h: bool, flip_v: bool) {
+        let sprite = &self.sprites[self.current_frame()];
+        sprite.render_ex(renderer, dest, angle, center, flip_h, flip_v);
+    }
+}
+
+
+
+/// A TrueType font loaded at a given size. To avoid rendering the same text
+/// every frame, use `Phi::ttf_str_sprite`, which caches the results.
+pub struct Font {
+    font: ::sdl2_ttf::Font,
+}
+
+impl Font {
+    /// Loads the font at `path` with the given point size.
+    pub fn load(path: &str, size: u16) -> Result<Font, String> {
+        ::sdl2_ttf::Font::from_file(Path::new(path), size as i32)
+            .map(|font| Font {
+                font: font,
+            })
+            .map_err(|e| e.0)
+    }
+
+    /// Renders `text` as a sprite. An empty string gives an empty sprite.
+    /// Returns `None` if the text could not be rendered.
+    pub fn render_text(&self, renderer: &Renderer, text: &str, color: Color) -> Option<Sprite> {
+        // SDL_ttf refuses to render text without any width.
+        if text.is_empty() {
+            return Sprite::empty(renderer);
+        }
+
+        self.font.render(text, ::sdl2_ttf::blended(color)).ok()
+            .and_then(|surface| renderer.create_texture_from_surface(&surface).ok())
+            .map(Sprite::new)
+    }
+}
+
+
+
+/// Keeps the fonts loaded by (path, size), and the text that they rendered by
+/// (text, font, size, color), so that a string which is shown on every frame
+/// is only rendered once. Only the most recently used strings are kept, so
+/// that text which changes all the time, such as a timer, does not fill the
+/// memory.
+pub struct FontCache {
+    fonts: HashMap<(String, u16), Font>,
+    // Every rendered text, along with the last time that it was used.
+    text: HashMap<(String, String, u16, Color), (Sprite, u64)>,
+    clock: u64,
+    capacity: usize,
+}
+
+impl FontCache {
+    /// Creates a cache which keeps at most `capacity` rendered strings.
+    pub fn new(capacity: usize) -> FontCache {
+        FontCache {
+            fonts: HashMap::new(),
+            text: HashMap::new(),
+            clock: 0,
+            capacity: ::std::cmp::max(capacity, 1),
+        }
+    }
+
+    /// Returns the font at `path` with the given point size, loading it the
+    /// first time.
+    pub fn font(&mut self, path: &str, size: u16) -> Result<&Font, String> {
+        let key = (path.to_string(), size);
+
+        if !self.fonts.contains_key(&key) {
+            let font = Font::load(path, size).map_err(|e| format!("{}: {}", path, e))?;
+            self.fonts.insert(key.clone(), font);
+        }
+
+        Ok(&self.fonts[&key])
+    }
+
+    /// Returns `text` rendered with the given font, size and color.
+    pub fn text_sprite(&mut self, renderer: &Renderer, text: &str, font_path: &str, size: u16, color: Color) -> Result<Sprite, String> {
+        let key = (text.to_string(), font_path.to_string(), size, color);
+        self.clock += 1;
+
+        if let Some(entry) = self.text.get_mut(&key) {
+            entry.1 = self.clock;
+            return Ok(entry.0.clone());
+        }
+
+        let sprite = self.font(font_path, size)?.render_text(renderer, text, color)
+            .ok_or(format!("Could not render text: {}", text))?;
+
+        // Forget the least recently used text to make some room.
+        if self.text.len() >= self.capacity {
+            let oldest = self.text.iter()
+                .min_by_key(|&(_, &(_, last_used))| last_used)
+                .map(|(key, _)| key.clone());
+
+            if let Some(oldest) = oldest {
+                self.text.remove(&oldest);
+            }
+        }
+
+        self.text.insert(key, (sprite.clone(), self.clock));
+        Ok(sprite)
+    }
+}
+
+
+
+/// An image which scrolls horizontally forever, stretched to the height of the
+/// screen and repeated as many times as needed to cover its width.
+#[derive(Clone)]
+pub struct ParallaxLayer {
+    pub sprite: Sprite,
+    /// The number of pixels of the image scrolled to the left every second.
+    /// Negative velocities scroll to the right.
+    pub velocity: f64,
+    /// The depth of the layer. Layers with a lower `z` are further away, and
+    /// are rendered first.
+    pub z: i32,
+
+    // The horizontal offset of the image, in the image's own pixels, so that it
+    // does not depend on the size of the screen. Always in [0, width).
+    pos: f64,
+}
+
+impl ParallaxLayer {
+    pub fn new(sprite: Sprite, velocity: f64, z: i32) -> ParallaxLayer {
+        ParallaxLayer {
+            sprite: sprite,
+            velocity: velocity,
+            z: z,
+            pos: 0.0,
+        }
+    }
+
+    /// Scrolls the layer according to the time elapsed, in seconds.
+    pub fn update(&mut self, elapsed: f64) {
+        let (w, _) = self.sprite.size();
+        if w <= 0.0 {
+            return;
+        }
+
+        // Wrap in both directions, so that there is no gap when scrolling to
+        // the right, where the offset becomes negative.
+        self.pos = ((self.pos + self.velocity * elapsed) % w + w) % w;
+    }
+
+    pub fn render(&self, renderer: &mut Renderer) {
+        let (w, h) = self.sprite.size();
+
+        // Draw in the logical coordinates, if there are any.
+        let (win_w, win_h) = match renderer.logical_size() {
+            (0, 0) => renderer.output_size().unwrap(),
+            size => size,
+        };
+
+        if w <= 0.0 || h <= 0.0 {
+            return;
+        }
+
+        // We determine the scale ratio of the window to the sprite.
+        let scale = win_h as f64 / h;
+
+        // We render as many copies of the layer as necessary to fill the
+        // screen, even if the image is narrower than it.
+        let mut physical_left = -self.pos * scale;
+
+        while physical_left < win_w as f64 {
+            renderer.copy_sprite(&self.sprite, Rectangle {
+                x: physical_left,
+                y: 0.0,
+                w: w * scale,
+                h: win_h as f64,
+            });
+
+            physical_left += w * scale;
+        }
+    }
+}
+
+
+/// Layers scrolling at different speeds, giving an impression of depth. It
+/// can be cloned and passed from one view to the next, so that it keeps
+/// scrolling from where it was.
+#[derive(Clone)]
+pub struct Background {
+    /// Sorted by depth, the furthest first.
+    layers: Vec<ParallaxLayer>,
+}
+
+impl Background {
+    pub fn new(mut layers: Vec<ParallaxLayer>) -> Background {
+        // A stable sort keeps the layers of the same depth in order.
+        layers.sort_by_key(|layer| layer.z);
+
+        Background {
+            layers: layers,
+        }
+    }
+
+    pub fn layers(&self) -> &[ParallaxLayer] {
+        &self.layers
+    }
+
+    /// Scrolls every layer according to the time elapsed, in seconds.
+    pub fn update(&mut self, elapsed: f64) {
+        for layer in self.layers.iter_mut() {
+            layer.update(elapsed);
+        }
+    }
+
+    /// Renders every layer, from back to front.
+    pub fn render(&self, renderer: &mut Renderer) {
+        for layer in self.layers.iter() {
+            layer.render(renderer);
+        }
+    }
+
+    /// Renders the layers which are further than `z`, so that objects at this
+    /// depth can be drawn on top of them.
+    pub fn render_behind(&self, renderer: &mut Renderer, z: i32) {
+        for layer in self.layers.iter().filter(|layer| layer.z < z) {
+            layer.render(renderer);
+        }
+    }
+
+    /// Renders the remaining layers, whose depth is `z` or closer.
+    pub fn render_in_front(&self, renderer: &mut Renderer, z: i32) {
+        for layer in self.layers.iter().filter(|layer| layer.z >= z) {
+            layer.render(renderer);
+        }
+    }
+}
+
+
+
+/// The largest number of particles which a `ParticleSystem` keeps alive by
+/// default.
+pub const MAX_PARTICLES: usize = 10_000;
+
+/// Describes a burst of particles, for instance the debris of an explosion.
+/// Every particle picks its speed, direction and lifetime at random in the
+/// given ranges, then fades from the start to the end color and size.
+#[derive(Clone, Copy, Debug)]
+pub struct EmitterConfig {
+    /// Where the particles start, in the coordinates of the world.
+    pub position: (f64, f64),
+    pub count: usize,
+    /// The smallest and largest speed, in pixels per second.
+    pub speed: (f64, f64),
+    /// The direction in which the particles go, in radians, clockwise from the
+    /// x axis, and the total angle, around it, in which they spread. A spread
+    /// of `2π` sends them in every direction.
+    pub angle: f64,
+    pub spread: f64,
+    /// The shortest and longest lifetime, in seconds.
+    pub lifetime: (f64, f64),
+    pub start_color: Color,
+    pub end_color: Color,
+    /// The side of the squares, in pixels.
+    pub start_size: f64,
+    pub end_size: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    x: f64,
+    y: f64,
+    dx: f64,
+    dy: f64,
+    age: f64,
+    lifetime: f64,
+    start_color: Color,
+    end_color: Color,
+    start_size: f64,
+    end_size: f64,
+    /// When the particle was emitted, relative to the others, to know which
+    /// ones are the oldest.
+    serial: u64,
+}
+
+impl Particle {
+    fn rect(&self) -> Rectangle {
+        let size = self.start_size.lerp(self.end_size, self.age / self.lifetime);
+        Rectangle { x: self.x - size / 2.0, y: self.y - size / 2.0, w: size, h: size }
+    }
+
+    fn color(&self) -> Color {
+        self.start_color.lerp(self.end_color, self.age / self.lifetime)
+    }
+}
+
+/// Where an `Emitter` spawns its particles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmitterShape {
+    /// Every particle starts at the same point.
+    Point(f64, f64),
+    /// The particles start anywhere on the segment between both points, for
+    /// instance along the back of a ship.
+    Line((f64, f64), (f64, f64)),
+}
+
+/// Spawns particles into a `ParticleSystem` continuously, at a given rate,
+/// rather than in a single burst. The position of `config` is ignored, since
+/// every particle picks its own in `shape`, and so is its count.
+#[derive(Clone, Copy, Debug)]
+pub struct Emitter {
+    pub shape: EmitterShape,
+    /// The number of particles spawned per second.
+    pub spawn_rate: f64,
+    pub config: EmitterConfig,
+    // The fraction of a particle which was due, but not spawned yet, so that
+    // a low rate still spawns particles at high frame rates.
+    pending: f64,
+}
+
+impl Emitter {
+    pub fn new(shape: EmitterShape, spawn_rate: f64, config: EmitterConfig) -> Emitter {
+        Emitter {
+            shape: shape,
+            spawn_rate: spawn_rate,
+            config: config,
+            pending: 0.0,
+        }
+    }
+
+    /// Spawns the particles due after `elapsed` seconds into `system`.
+    pub fn update(&mut self, elapsed: f64, system: &mut ParticleSystem) {
+        self.pending += self.spawn_rate.max(0.0) * elapsed;
+
+        while self.pending >= 1.0 {
+            self.pending -= 1.0;
+
+            let position = match self.shape {
+                EmitterShape::Point(x, y) => (x, y),
+                EmitterShape::Line(from, to) => from.lerp(to, ::rand::random::<f64>()),
+            };
+
+            system.emit(EmitterConfig { position: position, count: 1, ..self.config });
+        }
+    }
+}
+
+
+/// Many small squares which move in straight lines and fade away, such as
+/// sparks, smoke or the exhaust of an engine. A view usually holds a single
+/// system, and emits bursts into it, or lets an `Emitter` feed it.
+pub struct ParticleSystem {
+    /// In no particular order, since dead particles are swapped with the last.
+    particles: Vec<Particle>,
+    capacity: usize,
+    next_serial: u64,
+    /// Drawn, tinted, in place of the squares, if set.
+    texture: Option<Sprite>,
+    /// The acceleration of every particle, in pixels per second squared.
+    gravity: (f64, f64),
+}
+
+impl ParticleSystem {
+    pub fn new() -> ParticleSystem {
+        ParticleSystem::with_capacity(MAX_PARTICLES)
+    }
+
+    /// Creates a system keeping at most `capacity` particles alive.
+    pub fn with_capacity(capacity: usize) -> ParticleSystem {
+        ParticleSystem {
+            particles: Vec::new(),
+            capacity: capacity,
+            next_serial: 0,
+            texture: None,
+            gravity: (0.0, 0.0),
+        }
+    }
+
+    /// Draws every particle as `sprite`, stretched to its size and tinted by
+    /// its color, rather than as a filled square. `None` goes back to squares.
+    pub fn set_texture(&mut self, sprite: Option<Sprite>) {
+        self.texture = sprite;
+    }
+
+    /// Makes every particle accelerate by `(gx, gy)` pixels per second squared,
+    /// for instance downwards so that debris falls. There is no gravity by
+    /// default.
+    pub fn set_gravity(&mut self, gx: f64, gy: f64) {
+        self.gravity = (gx, gy);
+    }
+
+    /// The number of particles which are alive.
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    /// Emits a burst of particles. If there are more than the capacity of the
+    /// system, then the oldest ones are removed to make room.
+    pub fn emit(&mut self, config: EmitterConfig) {
+        let count = config.count.min(self.capacity);
+        let excess = (self.particles.len() + count).saturating_sub(self.capacity);
+
+        if excess >= self.particles.len() {
+            self.particles.clear();
+        } else if excess > 0 {
+            // Moves the `excess` oldest particles to the front.
+            self.particles.select_nth_unstable_by_key(excess, |particle| particle.serial);
+            self.particles.drain(..excess);
+        }
+
+        let between = |(min, max): (f64, f64)| min + (max - min) * ::rand::random::<f64>();
+
+        for _ in 0..count {
+            let speed = between(config.speed);
+            let angle = config.angle + config.spread * (::rand::random::<f64>() - 0.5);
+
+            self.particles.push(Particle {
+                x: config.position.0,
+                y: config.position.1,
+                dx: speed * angle.cos(),
+                dy: speed * angle.sin(),
+                age: 0.0,
+                lifetime: between(config.lifetime),
+                start_color: config.start_color,
+                end_color: config.end_color,
+                start_size: config.start_size,
+                end_size: config.end_size,
+                serial: self.next_serial,
+            });
+
+            self.next_serial += 1;
+        }
+    }
+
+    /// Moves the particles by the time elapsed, in seconds, and removes those
+    /// which lived their whole lifetime.
+    pub fn update(&mut self, elapsed: f64) {
+        let (gx, gy) = self.gravity;
+        let mut i = 0;
+
+        while i < self.particles.len() {
+            let particle = &mut self.particles[i];
+            particle.age += elapsed;
+
+            if particle.age >= particle.lifetime {
+                self.particles.swap_remove(i);
+            } else {
+                particle.dx += gx * elapsed;
+                particle.dy += gy * elapsed;
+                particle.x += particle.dx * elapsed;
+                particle.y += particle.dy * elapsed;
+                i += 1;
+            }
+        }
+    }
+
+    /// Renders the particles, whose positions are in the coordinates of the
+    /// screen.
+    pub fn render(&self, renderer: &mut Renderer) {
+        self.render_with(renderer, |rect| Some(rect));
+    }
+
+    /// Renders the particles where `camera` shows them, skipping those which
+    /// are not on the screen.
+    pub fn render_world(&self, renderer: &mut Renderer, camera: &Camera) {
+        self.render_with(renderer, |rect| {
+            if camera.is_visible(rect) { Some(camera.to_screen(rect)) } else { None }
+        });
+    }
+
+    fn render_with<F: Fn(Rectangle) -> Option<Rectangle>>(&self, renderer: &mut Renderer, to_screen: F) {
+        // The particles fade out, which the renderer only shows when blending.
+        let blend_mode = renderer.blend_mode();
+        renderer.set_blend_mode(BlendMode::Blend);
+
+        // A single copy of the texture is tinted anew for every particle.
+        let mut texture = self.texture.clone();
+
+        for particle in &self.particles {
+            let dest = match to_screen(particle.rect()) {
+                Some(dest) => dest,
+                None => continue,
+            };
+
+            if let Some(ref mut sprite) = texture {
+                let color = particle.color();
+                sprite.set_color_mod(color);
+                sprite.set_alpha(match color { Color::RGB(..) => 255, Color::RGBA(_, _, _, a) => a });
+                sprite.render(renderer, dest);
+            }
+            // Empty rectangles, such as particles which shrank to nothing, have
+            // no SDL equivalent.
+            else if let Some(rect) = dest.to_sdl() {
+                renderer.set_draw_color(particle.color());
+                renderer.fill_rect(rect);
+            }
+        }
+
+        renderer.set_blend_mode(blend_mode);
+    }
+}
+
+
+
+/// Sprites to draw at the end of the frame, from the furthest to the closest,
+/// whatever the order in which they were submitted. See `Phi::draw`.
+pub struct RenderQueue {
+    commands: Vec<DrawCommand>,
+}
+
+struct DrawCommand {
+    z: i32,
+    sprite: Sprite,
+    dest: Rectangle,
+}
+
+impl RenderQueue {
+    pub fn new() -> RenderQueue {
+        RenderQueue { commands: Vec::new() }
+    }
+
+    /// Adds `sprite`, to be drawn at `dest`, in the coordinates of the screen,
+    /// at depth `z`. Sprites of higher `z` are drawn on top.
+    pub fn push(&mut self, sprite: &Sprite, dest: Rectangle, z: i32) {
+        self.commands.push(DrawCommand {
+            z: z,
+            sprite: sprite.clone(),
+            dest: dest,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Draws every sprite submitted, by increasing `z`, and empties the queue.
+    /// The sort is stable, so that sprites at the same depth are drawn in the
+    /// order in which they were submitted.
+    pub fn flush(&mut self, renderer: &mut Renderer) {
+        self.commands.sort_by_key(|command| command.z);
+
+        for command in self.commands.drain(..) {
+            command.sprite.render(renderer, command.dest);
+        }
+    }
 }
 
 
 
 pub trait CopySprite<T> {
     fn copy_sprite(&mut self, sprite: &T, dest: Rectangle);
+    fn copy_sprite_flipped(&mut self, sprite: &T, dest: Rectangle, flip_h: bool, flip_v: bool);
+    fn copy_sprite_ex(&mut self, sprite: &T, dest: Rectangle, angle: f64,
+                      center: Option<(f64, f64)>, flip_h: bool, flip_v: bool);
+    fn copy_sprite_world(&mut self, sprite: &T, camera: &Camera, dest: Rectangle);
 }
 
 impl<'window, T: Renderable> CopySprite<T> for Renderer<'window> {
     fn copy_sprite(&mut self, renderable: &T, dest: Rectangle) {
        renderable.render(self, dest);
-   }
+    }
+
+    fn copy_sprite_flipped(&mut self, renderable: &T, dest: Rectangle, flip_h: bool, flip_v: bool) {
+        renderable.render_flipped(self, dest, flip_h, flip_v);
+    }
+
+    fn copy_sprite_ex(&mut self, renderable: &T, dest: Rectangle, angle: f64,
+                      center: Option<(f64, f64)>, flip_h: bool, flip_v: bool) {
+        renderable.render_ex(self, dest, angle, center, flip_h, flip_v);
+    }
+
+    fn copy_sprite_world(&mut self, renderable: &T, camera: &Camera, dest: Rectangle) {
+        renderable.render_world(self, camera, dest);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation_center_defaults_to_the_middle_of_dest() {
+        let dest = Rectangle { x: 100.0, y: 50.0, w: 43.0, h: 39.0 };
+
+        // Relative to the top-left corner of `dest`, not to the screen.
+        assert_eq!(rotation_center(dest, None), (21.5, 19.5));
+        assert_eq!(rotation_center(Rectangle { x: 0.0, y: 0.0, w: 0.0, h: 0.0 }, None), (0.0, 0.0));
+    }
+
+    #[test]
+    fn rotation_center_keeps_an_explicit_center() {
+        let dest = Rectangle { x: 100.0, y: 50.0, w: 43.0, h: 39.0 };
+
+        assert_eq!(rotation_center(dest, Some((0.0, 19.5))), (0.0, 19.5));
+        // Even outside of `dest`, to turn around another point.
+        assert_eq!(rotation_center(dest, Some((-10.0, 80.0))), (-10.0, 80.0));
+    }
+
+    /// A software renderer drawing to a `w` by `h` surface, without a window,
+    /// filled with `background`.
+    fn software_renderer(w: u32, h: u32, background: Color) -> Renderer<'static> {
+        let surface = ::sdl2::surface::Surface::new(w, h, PixelFormatEnum::ARGB8888).unwrap();
+        let mut renderer = Renderer::from_surface(surface).unwrap();
+        renderer.set_draw_color(background);
+        renderer.clear();
+        renderer
+    }
+
+    /// An opaque sprite of `w` by `h` pixels of the given color, in the
+    /// `ARGB8888` format.
+    fn solid_sprite(renderer: &Renderer, w: u32, h: u32, argb: u32) -> Sprite {
+        let mut texture = renderer.create_texture_streaming(PixelFormatEnum::ARGB8888, (w, h)).unwrap();
+        let pixels: Vec<u8> = (0..w * h).flat_map(|_| argb.to_ne_bytes().to_vec()).collect();
+        texture.update(None, &pixels, w as usize * 4).unwrap();
+        Sprite::new(texture)
+    }
+
+    /// The red, green and blue components of the pixel at `(x, y)`.
+    fn read_rgb(renderer: &Renderer, x: i32, y: i32) -> (u8, u8, u8) {
+        let rect = ::sdl2::rect::Rect::new(x, y, 1, 1).unwrap().unwrap();
+        let pixels = renderer.read_pixels(Some(rect), PixelFormatEnum::ARGB8888).unwrap();
+        let pixel = u32::from_ne_bytes([pixels[0], pixels[1], pixels[2], pixels[3]]);
+        ((pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8)
+    }
+
+    fn assert_close(actual: (u8, u8, u8), expected: (u8, u8, u8)) {
+        let close = |a: u8, b: u8| (a as i32 - b as i32).abs() <= 2;
+        assert!(close(actual.0, expected.0) && close(actual.1, expected.1) && close(actual.2, expected.2),
+                "{:?} is not {:?}", actual, expected);
+    }
+
+    #[test]
+    fn half_alpha_sprite_is_blended_over_the_background() {
+        let mut renderer = software_renderer(8, 8, Color::RGB(0, 0, 200));
+        let mut sprite = solid_sprite(&renderer, 4, 4, 0xffffffff);
+        sprite.set_alpha(128);
+
+        renderer.copy_sprite(&sprite, Rectangle { x: 0.0, y: 0.0, w: 4.0, h: 4.0 });
+
+        // Half of the white sprite, and half of the blue background.
+        assert_close(read_rgb(&renderer, 1, 1), (128, 128, 228));
+        // Outside of the sprite, the background is left alone.
+        assert_close(read_rgb(&renderer, 6, 6), (0, 0, 200));
+        // The texture, which may be shared, is opaque again.
+        assert_eq!(sprite.texture().borrow().alpha_mod(), 255);
+    }
+
+    #[test]
+    fn tint_only_applies_to_its_sprite() {
+        let mut renderer = software_renderer(8, 4, Color::RGB(0, 0, 0));
+        let sprite = solid_sprite(&renderer, 4, 4, 0xffffffff);
+        let red = sprite.tinted(Color::RGB(255, 0, 0));
+
+        renderer.copy_sprite(&red, Rectangle { x: 0.0, y: 0.0, w: 4.0, h: 4.0 });
+        renderer.copy_sprite(&sprite, Rectangle { x: 4.0, y: 0.0, w: 4.0, h: 4.0 });
+
+        assert_close(read_rgb(&renderer, 1, 1), (255, 0, 0));
+        assert_close(read_rgb(&renderer, 5, 1), (255, 255, 255));
+    }
 }
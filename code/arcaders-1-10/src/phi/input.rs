@@ -0,0 +1,429 @@
+use ::phi::Events;
+use ::sdl2::controller::Button;
+use ::sdl2::keyboard::Keycode;
+use ::std::collections::HashMap;
+use ::std::fs::File;
+use ::std::hash::Hash;
+use ::std::io::Read;
+
+
+/// The logical actions which the player can perform, independently of the
+/// keys they are bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Fire,
+    Back,
+}
+
+impl Action {
+    /// Every action, in the order in which they should be listed to the
+    /// player.
+    pub fn all() -> [Action; 6] {
+        [Action::MoveUp, Action::MoveDown, Action::MoveLeft, Action::MoveRight,
+         Action::Fire, Action::Back]
+    }
+}
+
+impl ActionName for Action {
+    fn name(&self) -> &'static str {
+        match *self {
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::Fire => "fire",
+            Action::Back => "back",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::all().iter().cloned().find(|action| action.name() == name)
+    }
+}
+
+
+/// The actions which have a name, so that their bindings may be saved to a
+/// file and read back. See `ActionMap::load`.
+pub trait ActionName: Copy + Eq + Hash {
+    /// The name of the action in the bindings files, such as `move_up`. It
+    /// should not contain `=`.
+    fn name(&self) -> &'static str;
+
+    /// Returns the action called `name`, or `None` if there is no such action.
+    fn from_name(name: &str) -> Option<Self>;
+}
+
+
+/// Something which designates an action: either the action itself, or its
+/// name.
+pub trait ToAction<A> {
+    fn to_action(&self) -> Option<A>;
+}
+
+impl<A: ActionName> ToAction<A> for A {
+    fn to_action(&self) -> Option<A> {
+        Some(*self)
+    }
+}
+
+impl<'a, A: ActionName> ToAction<A> for &'a str {
+    fn to_action(&self) -> Option<A> {
+        A::from_name(self)
+    }
+}
+
+
+/// Associates every action with the keys and controller buttons which
+/// trigger it. An action may be bound to several keys and buttons, and a key
+/// or a button to several actions.
+///
+/// The actions are usually an enum defined by the game, or by a view, such
+/// as `Action`. The bindings of `Action` are kept in `Phi::bindings`.
+pub struct ActionMap<A: Copy + Eq + Hash> {
+    keys: HashMap<A, Vec<Keycode>>,
+    buttons: HashMap<A, Vec<Button>>,
+}
+
+/// Something to which an action may be bound.
+enum Input {
+    Key(Keycode),
+    Button(Button),
+}
+
+
+/// The bindings of the actions used by the views of the game.
+pub type KeyBindings = ActionMap<Action>;
+
+impl<A: Copy + Eq + Hash> ActionMap<A> {
+    /// Creates a map where no action is bound to anything.
+    pub fn new() -> ActionMap<A> {
+        ActionMap {
+            keys: HashMap::new(),
+            buttons: HashMap::new(),
+        }
+    }
+
+    /// Returns the first key which is currently bound to `action`.
+    pub fn key(&self, action: A) -> Option<Keycode> {
+        self.keys(action).first().cloned()
+    }
+
+    /// Returns all of the keys which are currently bound to `action`.
+    pub fn keys(&self, action: A) -> &[Keycode] {
+        self.keys.get(&action).map(|keys| &keys[..]).unwrap_or(&[])
+    }
+
+    /// Returns all of the controller buttons which are currently bound to
+    /// `action`.
+    pub fn buttons(&self, action: A) -> &[Button] {
+        self.buttons.get(&action).map(|buttons| &buttons[..]).unwrap_or(&[])
+    }
+
+    /// Binds `action` to `keycode`, replacing its previous keys. Its buttons
+    /// are left untouched.
+    pub fn bind(&mut self, action: A, keycode: Keycode) {
+        self.keys.insert(action, vec![keycode]);
+    }
+
+    /// Binds `action` to `keycode`, in addition to its current keys.
+    pub fn add(&mut self, action: A, keycode: Keycode) {
+        let keys = self.keys.entry(action).or_insert(Vec::new());
+
+        if !keys.contains(&keycode) {
+            keys.push(keycode);
+        }
+    }
+
+    /// Binds `action` to a button of the player's controller, replacing its
+    /// previous buttons. Its keys are left untouched.
+    pub fn bind_button(&mut self, action: A, button: Button) {
+        self.buttons.insert(action, vec![button]);
+    }
+
+    /// Binds `action` to a button of the player's controller, in addition to
+    /// its current buttons.
+    pub fn add_button(&mut self, action: A, button: Button) {
+        let buttons = self.buttons.entry(action).or_insert(Vec::new());
+
+        if !buttons.contains(&button) {
+            buttons.push(button);
+        }
+    }
+
+    /// Forgets every key and button bound to `action`.
+    pub fn unbind(&mut self, action: A) {
+        self.keys.remove(&action);
+        self.buttons.remove(&action);
+    }
+
+    /// Binds `action` to the first key pressed during the current frame, if
+    /// any. A view can call this every frame while it waits for the player's
+    /// choice; it returns `true` once the action has been rebound.
+    pub fn capture(&mut self, action: A, events: &Events) -> bool {
+        let pressed = events.now.keys.iter()
+            .find(|&(_, &pressed)| pressed)
+            .map(|(&keycode, _)| keycode);
+
+        match pressed {
+            Some(keycode) => {
+                self.bind(action, keycode);
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+impl<A: ActionName> ActionMap<A> {
+    /// Reads the bindings from a file with one `key = action` pair per line,
+    /// where `key` is the name that SDL gives to a key, such as `Space` or
+    /// `Left Shift`, or `pad:` followed by the name that SDL gives to a button
+    /// of a controller, such as `pad:a` or `pad:start`. Empty lines and those
+    /// starting with `#` are ignored.
+    ///
+    /// The actions listed in the file are bound to the keys and buttons given
+    /// there only; the others keep their current bindings. Returns an error if
+    /// the file cannot be read, or if it names an unknown key, button or
+    /// action, in which case the bindings are left unchanged.
+    pub fn load(&mut self, path: &str) -> Result<(), String> {
+        let mut source = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut source))
+            .map_err(|e| format!("{}: {}", path, e))?;
+
+        self.parse(path, &source)
+    }
+
+    /// Same as `load`, but with the contents of the file. `origin` is only
+    /// used in the error messages.
+    pub fn parse(&mut self, origin: &str, source: &str) -> Result<(), String> {
+        let mut bindings = Vec::new();
+
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let input_name = parts.next().unwrap_or("").trim();
+            let action_name = parts.next()
+                .ok_or(format!("{}:{}: expected `key = action`", origin, i + 1))?.trim();
+
+            let action = A::from_name(action_name)
+                .ok_or(format!("{}:{}: unknown action `{}`", origin, i + 1, action_name))?;
+
+            let input =
+                if input_name.starts_with("pad:") {
+                    let button_name = &input_name[4..];
+                    Input::Button(Button::from_string(button_name)
+                        .ok_or(format!("{}:{}: unknown button `{}`", origin, i + 1, button_name))?)
+                } else {
+                    Input::Key(Keycode::from_name(input_name)
+                        .ok_or(format!("{}:{}: unknown key `{}`", origin, i + 1, input_name))?)
+                };
+
+            bindings.push((action, input));
+        }
+
+        // The whole file is valid, so we can apply it. The first time that an
+        // action is listed, forget its previous bindings.
+        let mut listed = Vec::new();
+
+        for (action, input) in bindings {
+            if !listed.contains(&action) {
+                listed.push(action);
+                self.unbind(action);
+            }
+
+            match input {
+                Input::Key(keycode) => self.add(action, keycode),
+                Input::Button(button) => self.add_button(action, button),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the bindings in the format read by `load`, sorted by action, so
+    /// that the player's choices survive a restart.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        ::phi::save::write_atomically(::std::path::Path::new(path), self.to_config().as_bytes())
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// The bindings in the format read by `load`.
+    pub fn to_config(&self) -> String {
+        let mut lines = Vec::new();
+
+        for (action, keys) in self.keys.iter() {
+            for keycode in keys {
+                lines.push((action.name(), keycode.name()));
+            }
+        }
+
+        for (action, buttons) in self.buttons.iter() {
+            for button in buttons {
+                lines.push((action.name(), format!("pad:{}", button.string())));
+            }
+        }
+
+        // The maps are not ordered, and the file should not change if the
+        // bindings do not.
+        lines.sort();
+
+        lines.into_iter()
+            .map(|(action, input)| format!("{} = {}\n", input, action))
+            .collect()
+    }
+
+    /// Same as `bind`, but with the name of the action, which may come from the
+    /// player. Returns an error if there is no action with this name.
+    pub fn rebind(&mut self, action: &str, keycode: Keycode) -> Result<(), String> {
+        let action = A::from_name(action)
+            .ok_or(format!("Unknown action: {}", action))?;
+
+        self.bind(action, keycode);
+        Ok(())
+    }
+}
+
+impl ActionMap<Action> {
+    /// The default bindings of the game, which match the keys used by the
+    /// views before the bindings were introduced, and the buttons commonly
+    /// used for the same purposes on a controller.
+    pub fn defaults() -> KeyBindings {
+        let mut bindings = ActionMap::new();
+
+        bindings.bind(Action::MoveUp, Keycode::Up);
+        bindings.bind(Action::MoveDown, Keycode::Down);
+        bindings.bind(Action::MoveLeft, Keycode::Left);
+        bindings.bind(Action::MoveRight, Keycode::Right);
+        bindings.bind(Action::Fire, Keycode::Space);
+        bindings.bind(Action::Back, Keycode::Escape);
+
+        bindings.bind_button(Action::MoveUp, Button::DPadUp);
+        bindings.bind_button(Action::MoveDown, Button::DPadDown);
+        bindings.bind_button(Action::MoveLeft, Button::DPadLeft);
+        bindings.bind_button(Action::MoveRight, Button::DPadRight);
+        bindings.bind_button(Action::Fire, Button::A);
+        bindings.bind_button(Action::Back, Button::Back);
+
+        bindings
+    }
+}
+
+
+impl Events {
+    /// Same as the `now.key_*` fields, but for the keys and buttons currently
+    /// bound to the given action, which may be designated by its name. That
+    /// is, `Some(true)` if one of them was pressed during this frame,
+    /// `Some(false)` if one was released, and `None` otherwise, including when
+    /// there is no such action.
+    pub fn action_pressed<A, T>(&self, bindings: &ActionMap<A>, action: T) -> Option<bool>
+    where A: Copy + Eq + Hash, T: ToAction<A> {
+        let action = match action.to_action() {
+            Some(action) => action,
+            None => return None,
+        };
+
+        let changes: Vec<bool> = bindings.keys(action).iter()
+            .filter_map(|&keycode| self.now.key(keycode))
+            .chain(bindings.buttons(action).iter()
+                .filter_map(|&button| self.now.controller_button(button)))
+            .collect();
+
+        if changes.contains(&true) { Some(true) }
+        else if changes.contains(&false) { Some(false) }
+        else { None }
+    }
+
+    /// Same as the `key_*` fields, but for the keys and buttons currently
+    /// bound to the given action, which may be designated by its name. That
+    /// is, whether one of them is currently held down.
+    pub fn action_down<A, T>(&self, bindings: &ActionMap<A>, action: T) -> bool
+    where A: Copy + Eq + Hash, T: ToAction<A> {
+        let action = match action.to_action() {
+            Some(action) => action,
+            None => return false,
+        };
+
+        bindings.keys(action).iter().any(|&keycode| self.held.key(keycode)) ||
+            self.gamepad().map_or(false, |pad| {
+                bindings.buttons(action).iter().any(|&button| pad.button(button))
+            })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_round_trips() {
+        let mut bindings = KeyBindings::defaults();
+        bindings.add(Action::Fire, Keycode::Z);
+        bindings.add_button(Action::Fire, Button::X);
+
+        let mut parsed = ActionMap::new();
+        parsed.parse("bindings", &bindings.to_config()).unwrap();
+
+        assert_eq!(parsed.to_config(), bindings.to_config());
+        for &action in Action::all().iter() {
+            assert_eq!(parsed.keys(action), bindings.keys(action));
+            assert_eq!(parsed.buttons(action), bindings.buttons(action));
+        }
+    }
+
+    #[test]
+    fn parse_only_replaces_the_actions_it_lists() {
+        let mut bindings = KeyBindings::defaults();
+        bindings.parse("bindings", "# Left-handed\n\nA = fire\n  Left Shift = fire  \npad:b = fire\n").unwrap();
+
+        assert_eq!(bindings.keys(Action::Fire), &[Keycode::A, Keycode::LShift]);
+        assert_eq!(bindings.buttons(Action::Fire), &[Button::B]);
+        assert_eq!(bindings.keys(Action::Back), &[Keycode::Escape]);
+        assert_eq!(bindings.buttons(Action::Back), &[Button::Back]);
+    }
+
+    #[test]
+    fn a_key_may_trigger_several_actions() {
+        let mut bindings = KeyBindings::new();
+        bindings.parse("bindings", "Space = fire\nSpace = back\n").unwrap();
+
+        assert_eq!(bindings.keys(Action::Fire), &[Keycode::Space]);
+        assert_eq!(bindings.keys(Action::Back), &[Keycode::Space]);
+    }
+
+    #[test]
+    fn errors_give_the_line_and_leave_the_bindings_unchanged() {
+        let cases = [
+            ("Z = fire\n# ok\nSpace = jump\n", "bindings:3: unknown action `jump`"),
+            ("Z = fire\nNot A Key = back\n", "bindings:2: unknown key `Not A Key`"),
+            ("pad:y = fire\npad:trigger = back\n", "bindings:2: unknown button `trigger`"),
+            ("Z = fire\nSpace\n", "bindings:2: expected `key = action`"),
+        ];
+
+        for &(source, error) in cases.iter() {
+            let mut bindings = KeyBindings::defaults();
+            let before = bindings.to_config();
+
+            assert_eq!(bindings.parse("bindings", source), Err(error.to_string()));
+            assert_eq!(bindings.to_config(), before);
+        }
+    }
+
+    #[test]
+    fn rebind_rejects_unknown_actions() {
+        let mut bindings = KeyBindings::defaults();
+
+        assert!(bindings.rebind("jump", Keycode::Z).is_err());
+        assert!(bindings.rebind("fire", Keycode::Z).is_ok());
+        assert_eq!(bindings.keys(Action::Fire), &[Keycode::Z]);
+    }
+}
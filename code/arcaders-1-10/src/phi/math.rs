@@ -0,0 +1,215 @@
+use ::std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+use ::sdl2::rect::{Point, Rect as SdlRect};
+
+
+/// A rectangle in the coordinates of the world. It is the same type as the
+/// `Rectangle` used everywhere else, so that both names can be mixed freely.
+pub use ::phi::data::Rectangle as Rect2f;
+
+
+/// A point, or a displacement, in the coordinates of the world.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec2f {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Vec2f {
+    pub fn new(x: f64, y: f64) -> Vec2f {
+        Vec2f { x: x, y: y }
+    }
+
+    pub fn zero() -> Vec2f {
+        Vec2f::new(0.0, 0.0)
+    }
+
+    /// The unit vector pointing `angle` radians clockwise from the x axis,
+    /// since the y axis points down.
+    pub fn from_angle(angle: f64) -> Vec2f {
+        let (sin, cos) = angle.sin_cos();
+        Vec2f::new(cos, sin)
+    }
+
+    pub fn dot(self, other: Vec2f) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn len(self) -> f64 {
+        self.len_squared().sqrt()
+    }
+
+    /// The square of the length, which is cheaper to compute and enough to
+    /// compare distances.
+    pub fn len_squared(self) -> f64 {
+        self.dot(self)
+    }
+
+    /// Returns the vector pointing in the same direction with a length of 1,
+    /// or the zero vector if `self` is zero, so that it can be used on the
+    /// velocity of something which stands still.
+    pub fn normalized(self) -> Vec2f {
+        let len = self.len();
+        if len == 0.0 { self } else { self * (1.0 / len) }
+    }
+
+    /// The angle of the vector, in radians, clockwise from the x axis.
+    pub fn angle(self) -> f64 {
+        self.y.atan2(self.x)
+    }
+}
+
+impl Add for Vec2f {
+    type Output = Vec2f;
+
+    fn add(self, other: Vec2f) -> Vec2f {
+        Vec2f::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl AddAssign for Vec2f {
+    fn add_assign(&mut self, other: Vec2f) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vec2f {
+    type Output = Vec2f;
+
+    fn sub(self, other: Vec2f) -> Vec2f {
+        Vec2f::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl SubAssign for Vec2f {
+    fn sub_assign(&mut self, other: Vec2f) {
+        *self = *self - other;
+    }
+}
+
+impl Mul<f64> for Vec2f {
+    type Output = Vec2f;
+
+    fn mul(self, factor: f64) -> Vec2f {
+        Vec2f::new(self.x * factor, self.y * factor)
+    }
+}
+
+impl Neg for Vec2f {
+    type Output = Vec2f;
+
+    fn neg(self) -> Vec2f {
+        Vec2f::new(-self.x, -self.y)
+    }
+}
+
+impl From<(f64, f64)> for Vec2f {
+    fn from((x, y): (f64, f64)) -> Vec2f {
+        Vec2f::new(x, y)
+    }
+}
+
+impl From<Vec2f> for (f64, f64) {
+    fn from(v: Vec2f) -> (f64, f64) {
+        (v.x, v.y)
+    }
+}
+
+impl From<Vec2f> for Point {
+    /// Rounds the vector to the nearest pixel.
+    fn from(v: Vec2f) -> Point {
+        Point::new(v.x.round() as i32, v.y.round() as i32)
+    }
+}
+
+impl From<Rect2f> for Option<SdlRect> {
+    /// See `Rectangle::to_sdl`.
+    fn from(rect: Rect2f) -> Option<SdlRect> {
+        rect.to_sdl()
+    }
+}
+
+
+/// A 2D affine transform: a linear map, such as a rotation or a scaling,
+/// followed by a translation. Transforms are combined with `*`, where
+/// `a * b` applies `b` first, then `a`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform2f {
+    // x' = a * x + c * y + tx
+    // y' = b * x + d * y + ty
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    tx: f64,
+    ty: f64,
+}
+
+impl Transform2f {
+    pub fn identity() -> Transform2f {
+        Transform2f { a: 1.0, b: 0.0, c: 0.0, d: 1.0, tx: 0.0, ty: 0.0 }
+    }
+
+    pub fn translation(offset: Vec2f) -> Transform2f {
+        Transform2f { tx: offset.x, ty: offset.y, ..Transform2f::identity() }
+    }
+
+    /// Turns points by `angle` radians, clockwise on the screen, around the
+    /// origin.
+    pub fn rotation(angle: f64) -> Transform2f {
+        let (sin, cos) = angle.sin_cos();
+        Transform2f { a: cos, b: sin, c: -sin, d: cos, tx: 0.0, ty: 0.0 }
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Transform2f {
+        Transform2f { a: sx, d: sy, ..Transform2f::identity() }
+    }
+
+    /// Returns where `self` moves the point `p`.
+    pub fn apply(&self, p: Vec2f) -> Vec2f {
+        Vec2f::new(self.a * p.x + self.c * p.y + self.tx,
+                   self.b * p.x + self.d * p.y + self.ty)
+    }
+
+    /// Returns where `self` moves the displacement `v`, which, unlike a point,
+    /// is not translated.
+    pub fn apply_vector(&self, v: Vec2f) -> Vec2f {
+        Vec2f::new(self.a * v.x + self.c * v.y,
+                   self.b * v.x + self.d * v.y)
+    }
+
+    /// The transform undoing `self`, or `None` if `self` flattens the plane,
+    /// for instance a scaling by zero.
+    pub fn inverse(&self) -> Option<Transform2f> {
+        let det = self.a * self.d - self.b * self.c;
+        if det == 0.0 {
+            return None;
+        }
+
+        let (a, b, c, d) = (self.d / det, -self.b / det, -self.c / det, self.a / det);
+        Some(Transform2f {
+            a: a,
+            b: b,
+            c: c,
+            d: d,
+            tx: -(a * self.tx + c * self.ty),
+            ty: -(b * self.tx + d * self.ty),
+        })
+    }
+}
+
+impl Mul for Transform2f {
+    type Output = Transform2f;
+
+    fn mul(self, other: Transform2f) -> Transform2f {
+        let origin = self.apply(Vec2f::new(other.tx, other.ty));
+
+        Transform2f {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            tx: origin.x,
+            ty: origin.y,
+        }
+    }
+}
@@ -1,75 +1,872 @@
 #[macro_use]
 mod events;
+pub mod assets;
+pub mod audio;
+pub mod camera;
+pub mod collisions;
 pub mod data;
+pub mod debug;
+pub mod ecs;
+pub mod fx;
 pub mod gfx;
+pub mod input;
+pub mod math;
+pub mod pool;
+pub mod save;
+pub mod scene;
+pub mod tilemap;
+pub mod timer;
+pub mod transition;
+pub mod tween;
+pub mod ui;
 
-use self::gfx::Sprite;
-use ::sdl2::render::Renderer;
-use ::sdl2::pixels::Color;
-use ::std::collections::HashMap;
-use ::std::path::Path;
+use self::assets::AssetCache;
+use self::audio::{Audio, ChannelId};
+use self::camera::Camera;
+use self::data::{Circle, Rectangle};
+use self::debug::DebugOverlay;
+use self::gfx::{CopySprite, FontCache, RenderQueue, Sprite};
+use self::input::KeyBindings;
+use self::save::Serializable;
+use self::timer::{FrameClock, TimerQueue};
+use self::transition::{FadeToBlackTransition, Transition, TransitionView};
+use ::sdl2::render::{Renderer, Texture, TextureAccess};
+use ::sdl2::pixels::{Color, PixelFormatEnum};
+use ::sdl2::rect::{Point, Rect as SdlRect};
+use ::sdl2::rwops::RWops;
+use ::sdl2::video::FullscreenType;
+use ::sdl2_image::{ImageRWops, LoadTexture};
+use ::std::cell::RefCell;
+use ::std::path::{Component, Path, PathBuf};
+use ::std::rc::Rc;
+use ::std::thread::JoinHandle;
 
 
 struct_events! {
+    // Every key is given its three fields of `ImmediateEvents`: whether it was
+    // pressed, whether it was released, and whether it is down.
     keyboard: {
-        key_escape: Escape,
-        key_up: Up,
-        key_down: Down,
-        key_left: Left,
-        key_right: Right,
-        key_space: Space,
-        key_enter: Return
+        key_escape [key_escape_released, key_escape_down]: Escape,
+        key_up [key_up_released, key_up_down]: Up,
+        key_down [key_down_released, key_down_down]: Down,
+        key_left [key_left_released, key_left_down]: Left,
+        key_right [key_right_released, key_right_down]: Right,
+        key_space [key_space_released, key_space_down]: Space,
+        key_enter [key_enter_released, key_enter_down]: Return,
+        key_p [key_p_released, key_p_down]: P,
+        key_f3 [key_f3_released, key_f3_down]: F3,
+        key_f11 [key_f11_released, key_f11_down]: F11,
+        key_f12 [key_f12_released, key_f12_down]: F12
+    },
+    controller: {
+        pad_a: A,
+        pad_b: B,
+        pad_x: X,
+        pad_y: Y,
+        pad_left_shoulder: LeftShoulder,
+        pad_right_shoulder: RightShoulder,
+        pad_start: Start,
+        pad_back: Back,
+        pad_up: DPadUp,
+        pad_down: DPadDown,
+        pad_left: DPadLeft,
+        pad_right: DPadRight
+    },
+    mouse: {
+        mouse_left: Left,
+        mouse_middle: Middle,
+        mouse_right: Right
     },
     else: {
         quit: Quit { .. }
     }
 }
 
+impl ImmediateEvents {
+    /// Whether the left mouse button went down during this frame. Use it with
+    /// `mouse_pos` and `Rectangle::contains_point` to tell if something on the
+    /// screen was clicked.
+    pub fn mouse_left_clicked(&self) -> bool {
+        self.mouse_left == Some(true)
+    }
+
+    /// The text typed during this frame, or `None` if nothing was typed. This
+    /// is `text`, for the views which would rather match on it.
+    pub fn text_input(&self) -> Option<&str> {
+        if self.text.is_empty() { None } else { Some(&self.text) }
+    }
+}
+
 
 /// Bundles the Phi abstractions in a single structure which
 /// can be passed easily between functions.
 pub struct Phi<'window> {
     pub events: Events,
     pub renderer: Renderer<'window>,
+    pub bindings: KeyBindings,
+    pub audio: Audio,
+    pub timers: TimerQueue,
+    pub camera: Camera,
+
+    /// The size of the drawable area of the window, in pixels, which is kept
+    /// up to date when the window is resized.
+    pub window_size: (u32, u32),
+
+    // The durations of the last frames. See `raw_delta` and `delta`.
+    clock: FrameClock,
+
+    // The size of the texture which the views render to, if they do not render
+    // to the window directly. See `set_internal_resolution`.
+    internal_resolution: Option<(u32, u32)>,
+
+    // The sizes of the textures which `render_to_texture` and
+    // `with_render_target` are rendering to, innermost last.
+    target_sizes: Vec<(u32, u32)>,
 
-    cached_fonts: HashMap<(&'static str, i32), ::sdl2_ttf::Font>,
+    /// Whether the debug overlay, with the frame rate, the duration of the
+    /// last frame and what was submitted with `debug_line`, `debug_rect` and
+    /// `debug_segment` in debug builds, is drawn on top of every frame. It can
+    /// also be toggled from any view by pressing F3.
+    pub debug: bool,
+
+    fps_cap: Option<f64>,
+
+    // What the view submitted to the debug overlay since the last frame.
+    debug_overlay: DebugOverlay,
+
+    fonts: FontCache,
+
+    /// The directory where `save` and `load` keep their files. It defaults to
+    /// the `save::config_dir` named after the title of the window.
+    pub save_dir: PathBuf,
+
+    // What was submitted with `draw` during this frame.
+    render_queue: RenderQueue,
+
+    // Whether F12 was pressed since the last frame was presented, the time of
+    // the last screenshot taken with it and how many were taken during that
+    // second, and the threads which are still writing screenshots.
+    screenshot_requested: bool,
+    last_screenshot: (u64, u32),
+    screenshot_writers: Vec<JoinHandle<Result<(), String>>>,
+
+    // The textures stay loaded until they are evicted, so that going back and
+    // forth between views does not reload them from the disk.
+    cached_textures: AssetCache<Texture>,
+
+    // The libraries must stay initialized for as long as the context is used,
+    // so they are dropped last.
+    timer: ::sdl2::TimerSubsystem,
+    _ttf_context: ::sdl2_ttf::Sdl2TtfContext,
 }
 
 impl<'window> Phi<'window> {
-    fn new(events: Events, renderer: Renderer<'window>) -> Phi<'window> {
+    fn new(events: Events, renderer: Renderer<'window>, audio: Audio, timer: ::sdl2::TimerSubsystem,
+           ttf_context: ::sdl2_ttf::Sdl2TtfContext) -> Phi<'window> {
         ::sdl2_image::init(::sdl2_image::INIT_PNG);
 
+        let (w, h) = renderer.output_size().unwrap();
+
         Phi {
             events: events,
             renderer: renderer,
-            cached_fonts: HashMap::new(),
+            bindings: KeyBindings::defaults(),
+            audio: audio,
+            timers: TimerQueue::new(),
+            camera: Camera::new(w as f64, h as f64),
+            window_size: (w, h),
+            clock: FrameClock::new(DEFAULT_MAX_DELTA),
+            internal_resolution: None,
+            target_sizes: Vec::new(),
+            debug: false,
+            debug_overlay: DebugOverlay::new(),
+            fps_cap: Some(60.0),
+            fonts: FontCache::new(TEXT_CACHE_SIZE),
+            save_dir: PathBuf::from("."),
+            render_queue: RenderQueue::new(),
+            screenshot_requested: false,
+            last_screenshot: (0, 0),
+            screenshot_writers: Vec::new(),
+            cached_textures: AssetCache::new(),
+            timer: timer,
+            _ttf_context: ttf_context,
         }
     }
 
+    /// The size of the area which the views draw to. This is the logical size
+    /// if one was set, and the size of the window, in pixels, otherwise.
+    ///
+    /// It is queried anew on every call, so it is always up to date, even
+    /// right after the window was resized or went fullscreen. Views should call
+    /// it whenever they lay things out, rather than keep its result around.
     pub fn output_size(&self) -> (f64, f64) {
-        let (w, h) = self.renderer.output_size().unwrap();
+        let (w, h) = match self.renderer.logical_size() {
+            (0, 0) => self.renderer.output_size().unwrap(),
+            size => size,
+        };
+
         (w as f64, h as f64)
     }
 
+    /// Lets the views render at a fixed resolution, such as 320x240 for pixel
+    /// art, whatever the size of the window. Every frame is drawn to a texture
+    /// of this size, which is then scaled up by the largest whole factor that
+    /// fits in the window, without filtering, and centered with black bars.
+    /// The mouse position is converted to this resolution.
+    ///
+    /// This should not be combined with `set_logical_size`.
+    pub fn set_internal_resolution(&mut self, width: u32, height: u32) -> Result<(), String> {
+        self.clear_internal_resolution();
 
-    /// Renders a string of text as a sprite using the provided parameters.
-    pub fn ttf_str_sprite(&mut self, text: &str, font_path: &'static str, size: i32, color: Color) -> Option<Sprite> {
-        if let Some(font) = self.cached_fonts.get(&(font_path, size)) {
-            return font.render(text, ::sdl2_ttf::blended(color)).ok()
-                .and_then(|surface| self.renderer.create_texture_from_surface(&surface).ok())
-                .map(Sprite::new)
+        // The filtering used to scale a texture is chosen when creating it.
+        let quality = ::sdl2::hint::get("SDL_RENDER_SCALE_QUALITY");
+        ::sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "0");
+        let texture = self.renderer.create_texture_target(PixelFormatEnum::RGBA8888, (width, height));
+        ::sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", &quality.unwrap_or("1".to_string()));
+
+        let texture = texture.map_err(|e| e.0)?;
+
+        // The texture stays the render target, except while it is copied to
+        // the window at the end of every frame.
+        let mut target = self.renderer.render_target()
+            .ok_or("Render targets are not supported".to_string())?;
+        target.set(texture).map_err(|e| e.0)?;
+
+        self.internal_resolution = Some((width, height));
+        self.camera.w = width as f64;
+        self.camera.h = height as f64;
+        self.camera.clamp();
+        Ok(())
+    }
+
+    /// Makes the views render directly to the window again.
+    pub fn clear_internal_resolution(&mut self) {
+        if self.internal_resolution.take().is_some() {
+            // This drops the texture.
+            let _ = self.renderer.render_target().unwrap().reset();
+            self.events.internal_viewport = None;
+
+            let (w, h) = self.output_size();
+            self.camera.w = w;
+            self.camera.h = h;
+            self.camera.clamp();
+        }
+    }
+
+    /// Shows the frame which was just rendered, with the frame rate on top.
+    fn present(&mut self) {
+        self.render_queue.flush(&mut self.renderer);
+
+        // The next frame starts with an empty overlay. The shapes are drawn
+        // before the frame is scaled to the window, so that they use the same
+        // coordinates as the view, and the text after, so that it stays sharp.
+        let overlay = ::std::mem::replace(&mut self.debug_overlay, DebugOverlay::new());
+        if self.debug {
+            overlay.render_shapes(&mut self.renderer);
+        }
+
+        let (w, h) = match self.internal_resolution {
+            Some(size) => size,
+            None => {
+                self.take_requested_screenshot();
+                self.render_debug_overlay(&overlay);
+                self.renderer.present();
+                return;
+            },
+        };
+
+        let texture = match self.renderer.render_target().unwrap().reset() {
+            Ok(Some(texture)) => texture,
+            // A view changed the render target; there is nothing we can do.
+            _ => return self.renderer.present(),
+        };
+
+        let (win_w, win_h) = self.renderer.output_size().unwrap();
+        let scale = ::std::cmp::max(1, ::std::cmp::min(win_w / w, win_h / h));
+        let (left, top) = ((win_w as i32 - (w * scale) as i32) / 2, (win_h as i32 - (h * scale) as i32) / 2);
+
+        self.renderer.set_draw_color(Color::RGB(0, 0, 0));
+        self.renderer.clear();
+        self.renderer.copy(&texture, None, Rectangle {
+            x: left as f64,
+            y: top as f64,
+            w: (w * scale) as f64,
+            h: (h * scale) as f64,
+        }.to_sdl());
+
+        self.events.internal_viewport = Some((left, top, scale as i32));
+
+        self.take_requested_screenshot();
+        self.render_debug_overlay(&overlay);
+        self.renderer.present();
+
+        self.renderer.render_target().unwrap().set(texture).unwrap();
+    }
+
+    /// Plays the music track at `path`, looping `loops` more times, or forever
+    /// if it is negative. The music keeps playing when the view changes. See
+    /// `Audio::play_music`.
+    pub fn play_music(&mut self, path: &str, loops: i32) -> Result<(), String> {
+        self.audio.play_music(path, loops)
+    }
+
+    /// Stops the music, fading it out over `fade_ms` milliseconds, or at once
+    /// if it is zero.
+    pub fn stop_music(&mut self, fade_ms: u32) {
+        if fade_ms == 0 {
+            self.audio.stop_music();
+        } else {
+            self.audio.fade_out_music(fade_ms);
+        }
+    }
+
+    /// Plays the sound effect at `path`, unless every channel is busy. See
+    /// `Audio::play`.
+    pub fn play_sound(&mut self, path: &str) -> Option<ChannelId> {
+        self.audio.play(path).ok()
+    }
+
+    /// Lets the views draw as if the window were always `width` by `height`
+    /// pixels. SDL scales the result to the actual size of the window,
+    /// keeping the aspect ratio, and adds black bars where it does not match.
+    pub fn set_logical_size(&mut self, width: u32, height: u32) -> Result<(), String> {
+        self.renderer.set_logical_size(width, height).map_err(|e| e.0)?;
+
+        self.camera.w = width as f64;
+        self.camera.h = height as f64;
+        self.camera.clamp();
+        Ok(())
+    }
+
+    /// Resizes the window to `width` by `height` pixels. If it is fullscreen,
+    /// then this is the size that it will have once it goes back to windowed
+    /// mode. The views are notified through `View::on_resize` once SDL has
+    /// resized the window.
+    pub fn set_resolution(&mut self, width: u32, height: u32) -> Result<(), String> {
+        if width == 0 || height == 0 {
+            return Err(format!("Invalid window size: {}x{}", width, height));
+        }
+
+        let window = self.renderer.window_mut()
+            .ok_or("The renderer does not draw to a window".to_string())?;
+
+        window.set_size(width, height);
+        Ok(())
+    }
+
+    /// Switches the window between fullscreen, at the resolution of the
+    /// desktop, and windowed mode.
+    pub fn toggle_fullscreen(&mut self) -> Result<(), String> {
+        let window = self.renderer.window_mut()
+            .ok_or("The renderer does not draw to a window".to_string())?;
+
+        let fullscreen = window.window_flags() & FullscreenType::True as u32 != 0;
+        window.set_fullscreen(if fullscreen { FullscreenType::Off } else { FullscreenType::Desktop })
+            .map_err(|e| e.0)
+    }
+
+
+    /// Sets the number of frames over which `delta` is averaged. With `1`, the
+    /// default, no smoothing is applied. Passing `0` is the same as `1`.
+    pub fn set_delta_smoothing(&mut self, frames: usize) {
+        self.clock.set_smoothing(frames);
+    }
+
+    /// Sets the longest duration, in seconds, that a single frame may count
+    /// for. Defaults to 1/15th of a second.
+    ///
+    /// Without this limit, the time lost while the window is dragged or the
+    /// process is suspended would be caught up with all at once, which would
+    /// teleport the objects across the screen. Worse, if updating the game
+    /// takes longer than the time it simulates, then every frame has more
+    /// catching up to do than the last, until the game freezes: the "spiral
+    /// of death". Past this limit, the game slows down instead.
+    pub fn set_max_delta(&mut self, max_delta: f64) {
+        self.clock.max_delta = max_delta;
+    }
+
+    /// The time elapsed between the last two frames, in seconds, exactly as
+    /// measured by the timer. Useful, for instance, to profile the game.
+    pub fn raw_delta(&self) -> f64 {
+        self.clock.raw_delta()
+    }
+
+    /// The average of `raw_delta` over the last few frames, where every frame
+    /// is counted as lasting at most `set_max_delta` seconds. This is what the
+    /// game loop uses to decide how many updates to run. This way, a single
+    /// slow frame does not cause a burst of updates. See `set_delta_smoothing`.
+    pub fn delta(&self) -> f64 {
+        self.clock.delta()
+    }
+
+    /// Limits the number of frames rendered every second, or lets the game run
+    /// as fast as it can if `cap` is `None`. By default, the game is capped at
+    /// 60 frames per second.
+    pub fn set_fps_cap(&mut self, cap: Option<u32>) {
+        // A cap of zero would never render anything.
+        self.fps_cap = cap.map(|cap| ::std::cmp::max(cap, 1) as f64);
+    }
+
+    /// Returns the number of frames rendered per second, averaged over roughly
+    /// the last second.
+    pub fn fps(&self) -> f64 {
+        self.clock.fps()
+    }
+
+    /// Whether the frame rate should be shown in the top-left corner of the
+    /// window. Same as setting `debug`.
+    pub fn show_fps(&mut self, show: bool) {
+        self.debug = show;
+    }
+
+    /// Adds a line of text to the debug overlay of the current frame, under the
+    /// frame rate, for instance the number of entities alive. Views can call it
+    /// on every frame, whether the overlay is shown or not. In release builds,
+    /// it does nothing, like the shapes below, so that the views need no
+    /// `cfg` of their own.
+    ///
+    /// As with the shapes below, the overlay only keeps what was submitted for
+    /// the current frame, so views should submit from `render`, which runs once
+    /// per frame, rather than from `update`, which may not.
+    pub fn debug_line(&mut self, line: &str) {
+        if self.debug_shown() {
+            self.debug_overlay.line(line);
+        }
+    }
+
+    /// Draws the outline of `rect`, in the coordinates which the view renders
+    /// to, on the debug overlay of the current frame, for instance to see the
+    /// collision box of an entity. Nothing happens while the overlay is hidden.
+    pub fn debug_rect(&mut self, rect: Rectangle, color: Color) {
+        if self.debug_shown() {
+            self.debug_overlay.rect(rect, color);
+        }
+    }
+
+    /// Draws a segment from `a` to `b` on the debug overlay of the current
+    /// frame, like `debug_rect`.
+    pub fn debug_segment(&mut self, a: (f64, f64), b: (f64, f64), color: Color) {
+        if self.debug_shown() {
+            self.debug_overlay.segment(a, b, color);
+        }
+    }
+
+    /// Draws a small cross centered on `(x, y)` on the debug overlay, since a
+    /// single pixel would be hard to see.
+    pub fn debug_point(&mut self, x: f64, y: f64, color: Color) {
+        self.debug_segment((x - 2.0, y), (x + 2.0, y), color);
+        self.debug_segment((x, y - 2.0), (x, y + 2.0), color);
+    }
+
+    /// Draws the outline of `circle` on the debug overlay.
+    pub fn debug_circle(&mut self, circle: Circle, color: Color) {
+        if !self.debug_shown() {
+            return;
+        }
+
+        // Enough segments for the outline to look round at any size.
+        let segments = ::std::cmp::max(12, (circle.radius * 0.5) as usize);
+        let point = |i: usize| {
+            let angle = i as f64 / segments as f64 * 2.0 * ::std::f64::consts::PI;
+            (circle.x + circle.radius * angle.cos(), circle.y + circle.radius * angle.sin())
+        };
+
+        for i in 0..segments {
+            self.debug_segment(point(i), point(i + 1), color);
+        }
+    }
+
+    // Whether what is submitted to the debug overlay should be kept. It never
+    // is in release builds.
+    #[cfg(debug_assertions)]
+    fn debug_shown(&self) -> bool {
+        self.debug
+    }
+
+    #[cfg(not(debug_assertions))]
+    fn debug_shown(&self) -> bool {
+        false
+    }
+
+    /// Renders the text of the debug overlay on top of the current frame, if
+    /// it is shown.
+    fn render_debug_overlay(&mut self, overlay: &DebugOverlay) {
+        if !self.debug {
+            return;
+        }
+
+        let header = format!("{:.0} FPS ({:.1} ms)", self.fps(), self.raw_delta() * 1000.0);
+
+        for (i, line) in Some(&header).into_iter().chain(overlay.lines().iter()).enumerate() {
+            self.render_text_at(line, Color::RGB(255, 255, 0), 4.0, 4.0 + i as f64 * 18.0, 16);
+        }
+    }
+
+    /// Calls `f`, which renders to a new `width` by `height` texture rather
+    /// than to the window, and returns this texture. Calls may be nested, in
+    /// which case every texture is rendered independently. `f` must not
+    /// change the render target itself.
+    pub fn render_to_texture<F: FnOnce(&mut Phi)>(&mut self, width: u32, height: u32, f: F) -> Result<Texture, String> {
+        // The texture which was the render target before this call, if any
+        let previous = {
+            let mut target = self.renderer.render_target()
+                .ok_or("Render targets are not supported".to_string())?;
+            target.create_and_set(PixelFormatEnum::RGBA8888, (width, height)).map_err(|e| e.0)?
+        };
+
+        self.target_sizes.push((width, height));
+        self.with_own_render_queue(f);
+        self.target_sizes.pop();
+
+        // Restore the previous render target, which gives us back ours.
+        let mut target = self.renderer.render_target().unwrap();
+        let texture = match previous {
+            Some(previous) => target.set(previous),
+            None => target.reset(),
+        };
+
+        texture.map_err(|e| e.0)
+            .and_then(|texture| texture.ok_or("The render target was changed".to_string()))
+    }
+
+    // Calls `f`, drawing what it submits with `draw` to the current render
+    // target once it returns, while what was submitted before waits for the
+    // end of the frame.
+    fn with_own_render_queue<F: FnOnce(&mut Phi)>(&mut self, f: F) {
+        let outer = ::std::mem::replace(&mut self.render_queue, RenderQueue::new());
+        f(self);
+        self.render_queue.flush(&mut self.renderer);
+        self.render_queue = outer;
+    }
+
+    /// Submits `sprite`, to be drawn at `dest`, in the coordinates of the
+    /// screen, once the view has rendered the frame. Whatever the order in
+    /// which they are submitted, sprites of lower `z` are drawn first, and
+    /// those which share the same `z` are drawn in order of submission. For
+    /// instance, with the backgrounds at 0, the entities at 10 and the HUD at
+    /// 100, the HUD is never hidden by an explosion.
+    ///
+    /// Everything drawn directly to the renderer during the frame ends up
+    /// behind the sprites submitted this way. Within `render_to_texture` and
+    /// `with_render_target`, the sprites are drawn to the texture when the
+    /// closure returns.
+    pub fn draw(&mut self, sprite: &Sprite, dest: Rectangle, z: i32) {
+        self.render_queue.push(sprite, dest, z);
+    }
+
+    /// Same as `draw`, but `dest` is in the coordinates of the world, and the
+    /// sprite is drawn where `camera` shows it, if it is on the screen.
+    pub fn draw_world(&mut self, sprite: &Sprite, camera: &Camera, dest: Rectangle, z: i32) {
+        if camera.is_visible(dest) {
+            self.draw(sprite, camera.to_screen(dest), z);
+        }
+    }
+
+    /// Copies what has been rendered so far to the current render target into
+    /// a new sprite, for example to show the frozen game under a pause menu.
+    /// Returns an error if the pixels could not be read back.
+    pub fn capture_frame(&mut self) -> Result<Sprite, String> {
+        let format = PixelFormatEnum::ARGB8888;
+        let (w, h) = self.render_target_size()?;
+
+        // Without a region, SDL would read as many pixels as the window holds,
+        // even when the target is a smaller texture.
+        let region = SdlRect::new(0, 0, w, h).map_err(|e| e.0)?
+            .ok_or("The render target is empty".to_string())?;
+        let pixels = self.renderer.read_pixels(Some(region), format).map_err(|e| e.0)?;
+        let pitch = w as usize * format.byte_size_per_pixel();
+
+        if pixels.len() != pitch * h as usize {
+            return Err(format!("Expected {} bytes of pixels for a {}x{} frame, got {}",
+                               pitch * h as usize, w, h, pixels.len()));
+        }
+
+        let mut texture = self.renderer.create_texture_static(format, (w, h)).map_err(|e| e.0)?;
+        texture.update(None, &pixels, pitch).map_err(|e| e.0)?;
+        Ok(Sprite::new(texture))
+    }
+
+    // The size, in pixels, of what the views are currently rendering to: the
+    // texture of `render_to_texture` or `with_render_target`, or that of the
+    // internal resolution, or the window.
+    fn render_target_size(&self) -> Result<(u32, u32), String> {
+        match self.target_sizes.last().cloned().or(self.internal_resolution) {
+            Some(size) => Ok(size),
+            None => self.renderer.output_size().map_err(|e| e.0),
+        }
+    }
+
+    /// Lets the player type text, which is then collected in
+    /// `events.now.text`, until `stop_text_input` is called. Same as
+    /// `events.start_text_input`.
+    pub fn start_text_input(&mut self) {
+        self.events.start_text_input();
+    }
+
+    pub fn stop_text_input(&mut self) {
+        self.events.stop_text_input();
+    }
+
+    /// Saves `data` in `save_dir`, in the file named `key`, for instance the
+    /// settings of the player. The previous file is only replaced once the new
+    /// one is complete; see `save::write_atomically`.
+    ///
+    /// Returns an error if `key` is not a plain file name; see `save_path`.
+    pub fn save<T: Serializable>(&self, key: &str, data: &T) -> ::std::io::Result<()> {
+        let path = self.save_path(key).ok_or_else(|| {
+            ::std::io::Error::new(::std::io::ErrorKind::InvalidInput, format!("Invalid save key: {:?}", key))
+        })?;
+
+        self::save::save_state(&path, data)
+    }
+
+    /// Reads what was saved with `save` under `key`, or returns `None` if it
+    /// is missing or corrupt, for instance because the game was never saved,
+    /// or if `key` is not a plain file name.
+    pub fn load<T: Serializable>(&self, key: &str) -> Option<T> {
+        self.save_path(key).and_then(|path| self::save::load_state(&path).ok())
+    }
+
+    /// The file in `save_dir` where `save` keeps what is saved under `key`, or
+    /// `None` if `key` is empty, contains a path separator or `..`, or is not
+    /// a file name for another reason, so that it cannot point outside of
+    /// `save_dir`.
+    pub fn save_path(&self, key: &str) -> Option<PathBuf> {
+        if key.contains("..") || key.contains('/') || key.contains('\\') {
+            return None;
+        }
+
+        let mut components = Path::new(key).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => Some(self.save_dir.join(key)),
+            _ => None,
+        }
+    }
+
+    /// Saves what has been rendered so far to the window as a PNG image at
+    /// `path`, creating its directory if needed. Only reading the pixels back
+    /// is done right away; the file is written on a background thread, so that
+    /// the frame is not held up, and errors while writing are ignored. Phi
+    /// waits for the pending writes when it is dropped.
+    pub fn save_screenshot(&mut self, path: &Path) -> Result<(), String> {
+        let format = self.renderer.window()
+            .map(|window| window.window_pixel_format())
+            .unwrap_or(PixelFormatEnum::ARGB8888);
+
+        let (w, h) = self.renderer.output_size().map_err(|e| e.0)?;
+        let mut pixels = self.renderer.read_pixels(None, format).map_err(|e| e.0)?;
+        let pitch = w * format.byte_size_per_pixel() as u32;
+        let path = path.to_path_buf();
+
+        // SDL already flips the rows of the OpenGL renderers, so the image is
+        // top-down whatever the renderer. A window resized during the frame
+        // is the only way to get a truncated image, which is not worth saving.
+        if pixels.len() != (pitch * h) as usize {
+            return Err(format!("Expected {} bytes of pixels for a {}x{} screenshot, got {}",
+                               pitch * h, w, h, pixels.len()));
+        }
+
+        let writer = ::std::thread::spawn(move || {
+            use ::sdl2_image::SaveSurface;
+
+            if let Some(dir) = path.parent() {
+                ::std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+
+            let surface = ::sdl2::surface::Surface::from_data(&mut pixels, w, h, pitch, format)
+                .map_err(|e| e.0)?;
+            surface.save(&path).map_err(|e| e.0)
+        });
+
+        self.screenshot_writers.push(writer);
+        Ok(())
+    }
+
+    /// Same as `save_screenshot`, with the path as a string.
+    pub fn screenshot(&mut self, path: &str) -> Result<(), String> {
+        self.save_screenshot(Path::new(path))
+    }
+
+    /// Saves a screenshot to `screenshots/`, if F12 was pressed since the last
+    /// frame. It is called right before the frame rate is drawn, so that it
+    /// does not appear on the image.
+    fn take_requested_screenshot(&mut self) {
+        if !self.screenshot_requested {
+            return;
+        }
+
+        self.screenshot_requested = false;
+
+        let now = ::std::time::SystemTime::now()
+            .duration_since(::std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+
+        // Several screenshots taken during the same second get a suffix, since
+        // the earlier ones may not even be on the disk yet.
+        let path = if self.last_screenshot.0 == now {
+            self.last_screenshot.1 += 1;
+            format!("screenshots/shot-{}-{}.png", now, self.last_screenshot.1)
+        } else {
+            self.last_screenshot = (now, 0);
+            format!("screenshots/shot-{}.png", now)
+        };
+
+        // If it fails, then there is simply no screenshot.
+        let _ = self.screenshot(&path);
+    }
+
+    /// Calls `f`, which renders to the texture of `target`, created with
+    /// `Sprite::new_render_target`, rather than to the current target. The
+    /// previous target is restored afterwards, even if `f` panics. Calls may be
+    /// nested, but `f` must not render `target` itself.
+    pub fn with_render_target<F: FnOnce(&mut Phi)>(&mut self, target: &Sprite, f: F) -> Result<(), String> {
+        if target.texture().borrow().query().access != TextureAccess::Target {
+            return Err("The texture cannot be rendered to".to_string());
+        }
+
+        // SDL takes ownership of the target, so we lend it a placeholder.
+        let placeholder = self.renderer.create_texture_static(PixelFormatEnum::RGBA8888, (1, 1))
+            .map_err(|e| e.0)?;
+        let texture = ::std::mem::replace(&mut *target.texture().borrow_mut(), placeholder);
+        let size = (texture.query().width, texture.query().height);
+
+        let previous = {
+            let mut render_target = self.renderer.render_target()
+                .ok_or("Render targets are not supported".to_string())?;
+            render_target.set(texture).map_err(|e| e.0)?
+        };
+
+        self.target_sizes.push(size);
+        let result = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| self.with_own_render_queue(f)));
+        self.target_sizes.pop();
+
+        // Restore the previous render target, which gives us back ours.
+        let texture = {
+            let mut render_target = self.renderer.render_target().unwrap();
+            match previous {
+                Some(previous) => render_target.set(previous),
+                None => render_target.reset(),
+            }
+        };
+
+        let restored = match texture {
+            Ok(Some(texture)) => {
+                *target.texture().borrow_mut() = texture;
+                Ok(())
+            },
+            Ok(None) => Err("The render target was changed".to_string()),
+            Err(e) => Err(e.0),
+        };
+
+        match result {
+            Ok(()) => restored,
+            Err(panic) => ::std::panic::resume_unwind(panic),
         }
+    }
+
+    /// Returns the texture loaded from the image at `path`. Once loaded, it is
+    /// cached and shared with every later caller, until it is evicted.
+    ///
+    /// This is the asset cache: the texture of a file is only read once, and
+    /// every sprite made from it, with `sprite`, shares it. `evict` and
+    /// `clear_texture_cache` give back the memory.
+    pub fn texture(&mut self, path: &str) -> Result<Rc<RefCell<Texture>>, String> {
+        let renderer = &self.renderer;
+        self.cached_textures.get_or_load(path, |path| {
+            renderer.load_texture(Path::new(path)).map_err(|e| e.0)
+        })
+    }
+
+    /// Decodes the image file whose contents are `bytes`, and caches its
+    /// texture as if it had been loaded from `path` by `texture`. This way,
+    /// the file can be read by another thread, since only the main thread may
+    /// create textures. See `assets::Preloader`.
+    pub fn cache_texture(&mut self, path: &str, bytes: &[u8]) -> Result<Rc<RefCell<Texture>>, String> {
+        let rwops = RWops::from_bytes(bytes).map_err(|e| e.0)?;
+        let surface = rwops.load().map_err(|e| format!("{}: {}", path, e.0))?;
+        let texture = self.renderer.create_texture_from_surface(surface).map_err(|e| e.0)?;
+        Ok(self.cached_textures.insert(path, texture))
+    }
+
+    /// Same as `texture`, but returns `None` if the image could not be loaded.
+    pub fn load_texture(&mut self, path: &str) -> Option<Rc<RefCell<Texture>>> {
+        self.texture(path).ok()
+    }
+
+    /// Removes the texture loaded from `path` from the cache, which is what was
+    /// asked for as `assets.evict(path)`. The sprites which still use it keep
+    /// it alive, but the next call to `texture` reloads it.
+    pub fn evict(&mut self, path: &str) {
+        self.cached_textures.evict(path);
+    }
+
+    /// Removes every texture from the cache, which is what was asked for as
+    /// `assets.clear()`, for example when going from a level to the next one,
+    /// whose assets are mostly different.
+    pub fn clear_texture_cache(&mut self) {
+        self.cached_textures.clear();
+    }
+
+    /// Creates a sprite showing the whole image at `path`, sharing its texture
+    /// with the other sprites loaded from the same file. This is the cached
+    /// `load_sprite` entry point; `Sprite::load` and `Sprite::from_path` go
+    /// through it too, and `Sprite::new` wraps a texture without any cache.
+    pub fn sprite(&mut self, path: &str) -> Result<Sprite, String> {
+        self.texture(path).map(Sprite::from_shared)
+    }
+
+
+    /// Loads the font at `path` with the given point size into the cache, if
+    /// it is not there yet, so that rendering text with it does not read the
+    /// file.
+    pub fn load_font(&mut self, path: &str, size: u16) -> Result<(), String> {
+        self.fonts.font(path, size).map(|_| ())
+    }
+
+    /// Renders a string of text as a sprite using the provided parameters.
+    /// The fonts, and the text that they render, are cached. An empty string
+    /// gives an empty sprite.
+    pub fn ttf_str_sprite(&mut self, text: &str, font_path: &str, size: u16, color: Color) -> Option<Sprite> {
+        self.fonts.text_sprite(&self.renderer, text, font_path, size, color).ok()
+    }
+
+    /// Renders a string of text with its top-left corner at `dest`. As with
+    /// `ttf_str_sprite`, the result is cached, so that the same text can be
+    /// rendered every frame. Returns an error if the font cannot be loaded or
+    /// if the text cannot be rendered.
+    pub fn render_text(&mut self, text: &str, font_path: &str, size: u16, color: Color, dest: Point) -> Result<(), String> {
+        let sprite = self.fonts.text_sprite(&self.renderer, text, font_path, size, color)?;
+        let (w, h) = sprite.size();
 
-        ::sdl2_ttf::Font::from_file(Path::new(font_path), size).ok()
-            .and_then(|font| {
-                self.cached_fonts.insert((font_path, size), font);
-                self.ttf_str_sprite(text, font_path, size, color)
-            })
+        self.renderer.copy_sprite(&sprite, Rectangle {
+            x: dest.x() as f64,
+            y: dest.y() as f64,
+            w: w,
+            h: h,
+        });
+
+        Ok(())
+    }
+
+    /// Renders a string of text in the default font, with its top-left corner
+    /// at `(x, y)`. Nothing is rendered if the font cannot be loaded.
+    pub fn render_text_at(&mut self, text: &str, color: Color, x: f64, y: f64, size: u16) {
+        if let Some(sprite) = self.ttf_str_sprite(text, DEFAULT_FONT, size, color) {
+            let (w, h) = sprite.size();
+            self.renderer.copy_sprite(&sprite, Rectangle { x: x, y: y, w: w, h: h });
+        }
     }
 }
 
 impl<'window> Drop for Phi<'window> {
     fn drop(&mut self) {
+        // The screenshots are written with SDL_image, so they must be finished
+        // before it is shut down.
+        for writer in self.screenshot_writers.drain(..) {
+            let _ = writer.join();
+        }
+
         ::sdl2_image::quit();
     }
 }
@@ -80,116 +877,612 @@ impl<'window> Drop for Phi<'window> {
 /// should be executed before the next rendering.
 pub enum ViewAction {
     None,
-    Quit,
-    ChangeView(Box<View>),
+    /// Exit the game with the given status, which `run` returns. Every view of
+    /// the stack is exited first, from the top one down.
+    Quit(i32),
+    /// Replace the current view, dropping it.
+    ChangeView(Box<dyn View>),
+    /// Pause the current view and show the provided one on top of it.
+    Push(Box<dyn View>),
+    /// Drop the current view and resume the one underneath. If there is no
+    /// such view, then this is equivalent to `Quit(0)`.
+    Pop,
+    /// Drop every view of the stack, paused or not, and replace them with the
+    /// provided one. For example, a pause menu may go back to the main menu
+    /// without resuming the game underneath.
+    Reset(Box<dyn View>),
+    /// Same as `Reset`, but with a new instance of the very first view, as
+    /// returned by the `init` function given to `run`. This way, a game-over
+    /// screen can start the game over without knowing how it begins.
+    Restart,
+    /// Replace the current view, showing the given transition in-between.
+    Transition(Box<dyn View>, Box<dyn Transition>),
+    /// Replace the current view, fading to black and back over the given
+    /// number of seconds. Shorthand for a `FadeToBlackTransition`.
+    FadeTo(Box<dyn View>, f64),
 }
 
 
 pub trait View {
-    /// Called on every frame to take care of both the logic and
-    /// the rendering of the current view.
+    /// Called when the view is added to the game loop, before it is rendered
+    /// for the first time.
+    fn on_enter(&mut self, _context: &mut Phi) {}
+
+    /// Called when the view is removed from the game loop, either because it
+    /// was replaced or popped, or because the game is quitting. This is the
+    /// place to save whatever should outlive the view.
+    fn on_exit(&mut self, _context: &mut Phi) {}
+
+    /// Called when another view is pushed on top of this one.
+    fn pause(&mut self, _context: &mut Phi) {}
+
+    /// Called when this view becomes the top of the stack again, after the
+    /// view above it was popped. Useful to reset timers so that the time spent
+    /// paused is not taken into account.
+    fn resume(&mut self, _context: &mut Phi) {}
+
+    /// Called when the window is resized, with its new size in pixels, for
+    /// instance to lay out the interface again. Every view of the stack is
+    /// notified, including those which are paused.
+    fn on_resize(&mut self, _context: &mut Phi, _new_w: u32, _new_h: u32) {}
+
+    /// Called on every frame while this view is paused, before the views above
+    /// it are rendered, so that those can be drawn on top of it. For example,
+    /// a game may show its frozen state under a pause menu.
+    ///
+    /// By default, nothing is rendered.
+    fn render_background(&mut self, _context: &mut Phi) {}
+
+    /// Called at a fixed rate, `UPDATES_PER_SECOND` times per second, to take
+    /// care of the logic of the current view. Because `dt` is always the same,
+    /// the game behaves identically no matter how fast the frames are drawn.
     ///
-    /// `elapsed` is expressed in seconds.
-    fn render(&mut self, context: &mut Phi, elapsed: f64) -> ViewAction;
+    /// `dt` is expressed in seconds. By default, nothing happens.
+    fn update(&mut self, _context: &mut Phi, _dt: f64) -> ViewAction {
+        ViewAction::None
+    }
+
+    /// Called once per frame, after the updates, to render the current view.
+    ///
+    /// `interpolation`, in [0.0, 1.0), is the fraction of an update which has
+    /// elapsed since the last one, and can be used to smooth movements between
+    /// two updates.
+    fn render(&mut self, context: &mut Phi, interpolation: f64);
+}
+
+
+/// The longest duration that a single frame may count for, by default. See
+/// `Phi::set_max_delta`.
+const DEFAULT_MAX_DELTA: f64 = 1.0 / 15.0;
+
+/// The number of rendered strings kept by `Phi::ttf_str_sprite`.
+const TEXT_CACHE_SIZE: usize = 128;
+
+/// The font used by `Phi::render_text_at`, for instance to show the frame rate.
+pub const DEFAULT_FONT: &'static str = "assets/belligerent.ttf";
+
+/// The number of times per second that `View::update` is called.
+pub const UPDATES_PER_SECOND: f64 = 60.0;
+
+/// The maximum number of updates in a single frame. If the game cannot keep
+/// up, then it slows down instead of spending ever more time updating to catch
+/// up with the frames it missed, which would freeze it.
+const MAX_UPDATES_PER_FRAME: u32 = 5;
+
+
+/// Configures and creates a `Phi` context. Every option has a sensible
+/// default, so that only those which matter to the game need to be set.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use arcade_rs::phi::PhiBuilder;
+/// let context = PhiBuilder::new()
+///     .title("ArcadeRS")
+///     .size(800, 600)
+///     .target_fps(60.0)
+///     .delta_smoothing(5)
+///     .build().unwrap();
+/// ```
+pub struct PhiBuilder {
+    title: String,
+    size: (u32, u32),
+    resizable: bool,
+    target_fps: Option<f64>,
+    delta_smoothing: usize,
+    max_delta: f64,
+}
+
+impl PhiBuilder {
+    pub fn new() -> PhiBuilder {
+        PhiBuilder {
+            title: "Phi".to_string(),
+            size: (800, 600),
+            resizable: false,
+            target_fps: Some(60.0),
+            delta_smoothing: 1,
+            max_delta: DEFAULT_MAX_DELTA,
+        }
+    }
+
+    /// The title of the window.
+    pub fn title(mut self, title: &str) -> PhiBuilder {
+        self.title = title.to_string();
+        self
+    }
+
+    /// The initial size of the window, in pixels. Defaults to 800x600.
+    pub fn size(mut self, width: u32, height: u32) -> PhiBuilder {
+        self.size = (width, height);
+        self
+    }
+
+    /// Lets the player resize the window. The views are notified through
+    /// `View::on_resize`. By default, the window has a fixed size.
+    pub fn resizable(mut self) -> PhiBuilder {
+        self.resizable = true;
+        self
+    }
+
+    /// The maximum number of frames rendered every second. Defaults to 60.
+    pub fn target_fps(mut self, fps: f64) -> PhiBuilder {
+        self.target_fps = Some(fps);
+        self
+    }
+
+    /// Lets the game render as many frames as it can.
+    pub fn uncapped(mut self) -> PhiBuilder {
+        self.target_fps = None;
+        self
+    }
+
+    /// The number of frames over which `Phi::delta` is averaged. Defaults to 1,
+    /// that is, no smoothing.
+    pub fn delta_smoothing(mut self, frames: usize) -> PhiBuilder {
+        self.delta_smoothing = frames;
+        self
+    }
+
+    /// The longest duration, in seconds, that a single frame may count for.
+    /// Defaults to 1/15th of a second. See `Phi::set_max_delta`.
+    pub fn max_delta(mut self, max_delta: f64) -> PhiBuilder {
+        self.max_delta = max_delta;
+        self
+    }
+
+    /// Initializes the underlying libraries and creates the window. Returns an
+    /// error if the options are invalid or if the initialization failed.
+    pub fn build(self) -> Result<Phi<'static>, String> {
+        // Validate the options
+        if self.size.0 == 0 || self.size.1 == 0 {
+            return Err(format!("Invalid window size: {}x{}", self.size.0, self.size.1));
+        }
+
+        if let Some(fps) = self.target_fps {
+            if !(fps > 0.0) || fps.is_infinite() {
+                return Err(format!("Invalid fps cap: {}", fps));
+            }
+        }
+
+        if !(self.max_delta > 0.0) {
+            return Err(format!("Invalid maximum delta: {}", self.max_delta));
+        }
+
+        if self.delta_smoothing == 0 {
+            return Err("Delta smoothing must span at least one frame".to_string());
+        }
+
+        // Initialize SDL2
+        let sdl_context = ::sdl2::init().map_err(|e| e.0)?;
+        let video = sdl_context.video().map_err(|e| e.0)?;
+        let timer = sdl_context.timer().map_err(|e| e.0)?;
+        let game_controller = sdl_context.game_controller().map_err(|e| e.0)?;
+        let joystick = sdl_context.joystick().map_err(|e| e.0)?;
+        // The game can run without sound, for instance if there is no device.
+        let audio = sdl_context.audio().ok();
+        let ttf_context = ::sdl2_ttf::init().map_err(|e| format!("{:?}", e))?;
+
+        // Create the window
+        let mut window_builder = video.window(&self.title, self.size.0, self.size.1);
+        window_builder.position_centered().opengl();
+
+        if self.resizable {
+            window_builder.resizable();
+        }
+
+        let window = window_builder.build().map_err(|e| e.0)?;
+
+        let renderer = window.renderer()
+            .accelerated()
+            .target_texture()
+            .build().map_err(|e| e.0)?;
+
+        let event_pump = sdl_context.event_pump().map_err(|e| e.0)?;
+
+        // Create the context
+        let mut context = Phi::new(
+            Events::new(event_pump, game_controller, joystick, video.text_input()),
+            renderer, Audio::new(audio), timer, ttf_context);
+
+        context.fps_cap = self.target_fps;
+        context.save_dir = self::save::config_dir(&self.title);
+        context.set_delta_smoothing(self.delta_smoothing);
+        context.set_max_delta(self.max_delta);
+
+        Ok(context)
+    }
 }
 
 
 /// Create a window with name `title`, initialize the underlying libraries and
-/// start the game with the `View` returned by `init()`.
+/// start the game with the `View` returned by `init()`. For more options, use
+/// a `PhiBuilder` and `run`.
 ///
 /// # Examples
 ///
 /// Here, we simply show a window with color #ffff00 and exit when escape is
 /// pressed or when the window is closed.
 ///
-/// ```
+/// ```no_run
+/// # extern crate arcade_rs;
+/// # extern crate sdl2;
+/// # use arcade_rs::phi::{spawn, Phi, View, ViewAction};
+/// # use sdl2::pixels::Color;
+/// # fn main() {
 /// struct MyView;
 ///
 /// impl View for MyView {
-///     fn resume(&mut self, _: &mut Phi) {}
-///     fn pause(&mut self, _: &mut Phi) {}
-///     fn render(&mut self, context: &mut Phi, _: f64) -> ViewAction {
-///         if context.events.now.quit {
-///             return ViewAction::Quit;
+///     fn update(&mut self, context: &mut Phi, _: f64) -> ViewAction {
+///         if context.events.now.quit || context.events.now.key_escape == Some(true) {
+///             return ViewAction::Quit(0);
 ///         }
 ///
+///         ViewAction::None
+///     }
+///
+///     fn render(&mut self, context: &mut Phi, _: f64) {
 ///         context.renderer.set_draw_color(Color::RGB(255, 255, 0));
 ///         context.renderer.clear();
-///         ViewAction::None
 ///     }
 /// }
 ///
-/// spawn("Example", |_| {
+/// let status = spawn("Example", |_| {
 ///     Box::new(MyView)
 /// });
+///
+/// ::std::process::exit(status);
+/// # }
 /// ```
-pub fn spawn<F>(title: &str, init: F)
-where F: Fn(&mut Phi) -> Box<View> {
-    // Initialize SDL2
-    let sdl_context = ::sdl2::init().unwrap();
-    let video = sdl_context.video().unwrap();
-    let mut timer = sdl_context.timer().unwrap();
-    let _ttf_context = ::sdl2_ttf::init();
-
-    // Create the window
-    let window = video.window(title, 800, 600)
-        .position_centered().opengl().resizable()
-        .build().unwrap();
-
-    // Create the context
-    let mut context = Phi::new(
-        Events::new(sdl_context.event_pump().unwrap()),
-        window.renderer()
-            .accelerated()
-            .build().unwrap());
+pub fn spawn<F>(title: &str, init: F) -> i32
+where F: Fn(&mut Phi) -> Box<dyn View> {
+    run(PhiBuilder::new().title(title).resizable().build().unwrap(), init)
+}
 
-    // Create the default view
-    let mut current_view = init(&mut context);
+
+/// Start the game with the `View` returned by `init()`, using a context
+/// created beforehand, usually with a `PhiBuilder`. `init` is called again
+/// whenever a view returns `ViewAction::Restart`. Returns the status passed
+/// to `ViewAction::Quit`, or 0 if the last view was popped.
+pub fn run<F>(mut context: Phi, init: F) -> i32
+where F: Fn(&mut Phi) -> Box<dyn View> {
+    // Create the default view. Views are kept in a stack, of which only the
+    // top one is rendered.
+    let mut views = vec![init(&mut context)];
+    views[0].on_enter(&mut context);
 
 
     // Frame timing
-    let interval = 1_000 / 60;
-    let mut before = timer.ticks();
-    let mut last_second = timer.ticks();
-    let mut fps = 0u16;
+    let frequency = context.timer.performance_frequency();
+    let mut before = context.timer.performance_counter();
 
-    loop {
-        // Frame timing (bis)
+    // The time which has not yet been consumed by updates, in seconds.
+    let dt = 1.0 / UPDATES_PER_SECOND;
+    let mut accumulator = 0.0;
 
-        let now = timer.ticks();
-        let dt = now - before;
-        let elapsed = dt as f64 / 1_000.0;
+    let status = 'running: loop {
+        // Frame timing (bis)
 
         // If the time elapsed since the last frame is too small, wait out the
-        // difference and try again.
-        if dt < interval {
-            timer.delay(interval - dt);
-            continue;
+        // difference. `delay` is only precise to the millisecond, and often
+        // sleeps longer than asked, so we sleep for all but the last
+        // millisecond and busy-wait for the rest.
+        if let Some(cap) = context.fps_cap {
+            let interval = (frequency as f64 / cap) as u64;
+
+            loop {
+                let frame_time = context.timer.performance_counter() - before;
+                if frame_time >= interval {
+                    break;
+                }
+
+                let remaining_ms = (interval - frame_time) * 1_000 / frequency;
+                if remaining_ms > 1 {
+                    context.timer.delay(remaining_ms as u32 - 1);
+                }
+            }
         }
 
+        let now = context.timer.performance_counter();
+        let elapsed = (now - before) as f64 / frequency as f64;
         before = now;
-        fps += 1;
 
-        if now - last_second > 1_000 {
-            println!("FPS: {}", fps);
-            last_second = now;
-            fps = 0;
-        }
 
+        // Logic
+
+        accumulator += context.clock.record(elapsed);
+        let mut updates = 0;
+
+        while accumulator >= dt {
+            if updates == MAX_UPDATES_PER_FRAME {
+                // Drop the time which we could not catch up with.
+                accumulator = 0.0;
+                break;
+            }
+
+            // The events are pumped once per update, so that every update gets
+            // the input which arrived since the previous one, and no input is
+            // seen twice.
+            context.events.pump(&mut context.renderer);
+
+            if context.events.now.key_f3 == Some(true) {
+                context.debug = !context.debug;
+            }
+
+            if let Some((new_w, new_h)) = context.events.now.resized {
+                context.window_size = (new_w, new_h);
+
+                let (w, h) = context.output_size();
+                context.camera.w = w;
+                context.camera.h = h;
+                context.camera.clamp();
+
+                // Paused views are notified as well, since they may still be
+                // rendered under the others.
+                for view in views.iter_mut() {
+                    view.on_resize(&mut context, new_w, new_h);
+                }
+            }
+
+            // The screenshot is taken once the next frame has been rendered.
+            if context.events.now.key_f12 == Some(true) {
+                context.screenshot_requested = true;
+            }
+
+            if context.events.now.key_f11 == Some(true) {
+                // If it fails, then we simply stay in the current mode.
+                let _ = context.toggle_fullscreen();
+            }
+
+            accumulator -= dt;
+            updates += 1;
+
+            self::timer::tick(&mut context, dt);
+
+            match views.last_mut().unwrap().update(&mut context, dt) {
+                ViewAction::None => {},
+
+                ViewAction::Quit(status) => {
+                    // Give every view a chance to save what it needs to.
+                    while let Some(mut view) = views.pop() {
+                        view.on_exit(&mut context);
+                    }
+
+                    break 'running status;
+                },
+
+                ViewAction::ChangeView(mut new_view) => {
+                    views.pop().unwrap().on_exit(&mut context);
+                    new_view.on_enter(&mut context);
+                    views.push(new_view);
+                },
+
+                ViewAction::Push(mut new_view) => {
+                    views.last_mut().unwrap().pause(&mut context);
+                    new_view.on_enter(&mut context);
+                    views.push(new_view);
+                },
+
+                ViewAction::Reset(mut new_view) => {
+                    while let Some(mut view) = views.pop() {
+                        view.on_exit(&mut context);
+                    }
+
+                    new_view.on_enter(&mut context);
+                    views.push(new_view);
+                },
+
+                ViewAction::Restart => {
+                    while let Some(mut view) = views.pop() {
+                        view.on_exit(&mut context);
+                    }
 
-        // Logic & rendering
+                    let mut new_view = init(&mut context);
+                    new_view.on_enter(&mut context);
+                    views.push(new_view);
+                },
 
-        context.events.pump(&mut context.renderer);
+                ViewAction::Transition(new_view, transition) => {
+                    let old_view = views.pop().unwrap();
+                    let mut transition_view = TransitionView::new(old_view, new_view, transition);
+                    transition_view.on_enter(&mut context);
+                    views.push(Box::new(transition_view));
+                },
 
-        match current_view.render(&mut context, elapsed) {
-            ViewAction::None =>
-                context.renderer.present(),
+                ViewAction::FadeTo(new_view, duration) => {
+                    let old_view = views.pop().unwrap();
+                    let transition = Box::new(FadeToBlackTransition::new(duration));
+                    let mut transition_view = TransitionView::new(old_view, new_view, transition);
+                    transition_view.on_enter(&mut context);
+                    views.push(Box::new(transition_view));
+                },
 
-            ViewAction::Quit =>
-                break,
+                ViewAction::Pop => {
+                    views.pop().unwrap().on_exit(&mut context);
 
-            ViewAction::ChangeView(new_view) =>
-                current_view = new_view,
+                    match views.last_mut() {
+                        Some(view) => view.resume(&mut context),
+                        None => break 'running 0,
+                    }
+                },
+            }
         }
+
+
+        // Rendering
+
+        let (top, below) = views.split_last_mut().unwrap();
+
+        for view in below.iter_mut() {
+            view.render_background(&mut context);
+        }
+
+        top.render(&mut context, accumulator / dt);
+        context.present();
+    };
+
+    status
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::sdl2::keyboard::Keycode;
+
+    enum Key {
+        Down(Keycode),
+        Up(Keycode),
+    }
+
+    /// Feeds the key events of every frame the way `Events::pump` does, and
+    /// returns the transitions which were reported for each of them, along
+    /// with what is held down at the end.
+    fn pump(frames: Vec<Vec<Key>>) -> (Vec<ImmediateEvents>, HeldEvents) {
+        let mut held = HeldEvents::new();
+
+        let now = frames.into_iter().map(|events| {
+            let mut now = held.next_frame();
+
+            for event in events {
+                match event {
+                    Key::Down(keycode) => held.press_key(&mut now, keycode),
+                    Key::Up(keycode) => held.release_key(&mut now, keycode),
+                }
+            }
+
+            now
+        }).collect();
+
+        (now, held)
+    }
+
+    #[test]
+    fn held_key_is_pressed_once_and_released_once() {
+        let (now, held) = pump(vec![
+            vec![Key::Down(Keycode::Space)],
+            vec![],
+            vec![],
+            vec![Key::Up(Keycode::Space)],
+            vec![],
+        ]);
+
+        let pressed: Vec<Option<bool>> = now.iter().map(|now| now.key_space).collect();
+        assert_eq!(pressed, vec![Some(true), None, None, Some(false), None]);
+
+        let released: Vec<bool> = now.iter().map(|now| now.key_space_released).collect();
+        assert_eq!(released, vec![false, false, false, true, false]);
+
+        // It stays down during the frames without any event.
+        let down: Vec<bool> = now.iter().map(|now| now.key_space_down).collect();
+        assert_eq!(down, vec![true, true, true, false, false]);
+        assert!(!held.key_space);
+    }
+
+    #[test]
+    fn key_is_held_until_it_is_released() {
+        let mut held = HeldEvents::new();
+
+        let mut now = ImmediateEvents::new();
+        held.press_key(&mut now, Keycode::Space);
+        assert_eq!(now.key_space, Some(true));
+        assert!(held.key_space && held.key(Keycode::Space));
+
+        // Other keys come and go in the meantime.
+        let mut now = ImmediateEvents::new();
+        held.press_key(&mut now, Keycode::Left);
+        held.release_key(&mut now, Keycode::Left);
+        assert_eq!(now.key_space, None);
+        assert!(held.key_space && held.key(Keycode::Space));
+
+        let mut now = ImmediateEvents::new();
+        held.release_key(&mut now, Keycode::Space);
+        assert_eq!(now.key_space, Some(false));
+        assert!(!held.key_space && !held.key(Keycode::Space));
+    }
+
+    #[test]
+    fn repeated_key_down_is_not_pressed_again() {
+        // SDL sends several KeyDown while a key is held, and the game may
+        // miss the `repeat` flag when the window loses focus.
+        let (now, held) = pump(vec![
+            vec![Key::Down(Keycode::Up)],
+            vec![Key::Down(Keycode::Up)],
+        ]);
+
+        assert_eq!(now[0].key_up, Some(true));
+        assert_eq!(now[1].key_up, None);
+        assert_eq!(now[1].key(Keycode::Up), None);
+        assert!(held.key_up);
+    }
+
+    #[test]
+    fn key_tapped_within_a_frame_is_both_pressed_and_released() {
+        let (now, held) = pump(vec![
+            vec![Key::Down(Keycode::Return), Key::Up(Keycode::Return)],
+            vec![],
+        ]);
+
+        assert_eq!(now[0].key_enter, Some(true));
+        assert!(now[0].key_enter_released);
+        assert!(!now[0].key_enter_down);
+        assert_eq!(now[0].key(Keycode::Return), Some(true));
+        assert!(now[0].key_released(Keycode::Return));
+
+        assert_eq!(now[1].key_enter, None);
+        assert!(!now[1].key_enter_released);
+        assert!(!held.key_enter);
+    }
+
+    #[test]
+    fn key_released_and_pressed_again_within_a_frame_stays_down() {
+        let (now, held) = pump(vec![
+            vec![Key::Down(Keycode::Left)],
+            vec![Key::Up(Keycode::Left), Key::Down(Keycode::Left)],
+            vec![],
+        ]);
+
+        assert_eq!(now[1].key_left, Some(true));
+        assert!(now[1].key_left_released);
+        assert!(now[1].key_left_down);
+        assert!(now[2].key_left_down);
+        assert!(held.key_left);
+    }
+
+    #[test]
+    fn keys_without_a_field_are_tracked_too() {
+        let (now, held) = pump(vec![
+            vec![Key::Down(Keycode::Q), Key::Down(Keycode::Space)],
+            vec![Key::Up(Keycode::Space)],
+        ]);
+
+        assert_eq!(now[0].key(Keycode::Q), Some(true));
+        assert_eq!(now[1].key(Keycode::Q), None);
+        assert_eq!(now[1].key(Keycode::Space), Some(false));
+        assert!(held.key(Keycode::Q));
+        assert!(!held.key(Keycode::Space));
+
+        // The other keys are left alone.
+        assert_eq!(now[0].key_escape, None);
+        assert!(!held.key_escape);
     }
 }
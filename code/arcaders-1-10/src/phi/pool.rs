@@ -0,0 +1,293 @@
+use ::std::slice;
+
+
+/// A fixed number of objects which can be reused once they are no longer
+/// needed, such as the bullets on the screen. All of the memory is allocated
+/// when the pool is created, so acquiring and releasing objects never touches
+/// the heap.
+pub struct Pool<T> {
+    slots: Vec<T>,
+    alive: Vec<bool>,
+    /// The indices of the slots which can be acquired, used as a stack.
+    free: Vec<usize>,
+}
+
+impl<T: Clone> Pool<T> {
+    /// Creates a pool of `capacity` slots, all holding a copy of `value`.
+    pub fn new(capacity: usize, value: T) -> Pool<T> {
+        Pool {
+            slots: vec![value; capacity],
+            alive: vec![false; capacity],
+            // Reversed, so that the first slots are acquired first.
+            free: (0..capacity).rev().collect(),
+        }
+    }
+}
+
+impl<T> Pool<T> {
+    /// Marks a free slot as used and returns it, or returns `None` if every
+    /// slot is already in use. The slot still holds the value of the object
+    /// which used it last, so it should be overwritten.
+    pub fn acquire(&mut self) -> Option<&mut T> {
+        match self.free.pop() {
+            Some(index) => {
+                self.alive[index] = true;
+                Some(&mut self.slots[index])
+            },
+            None => None,
+        }
+    }
+
+    /// Releases every live object for which `keep` returns `false`. The
+    /// objects are visited in the order of their slots, and may be modified,
+    /// for example to move them before deciding whether they are still on
+    /// the screen.
+    pub fn retain<F: FnMut(&mut T) -> bool>(&mut self, mut keep: F) {
+        for (index, (slot, alive)) in self.slots.iter_mut().zip(self.alive.iter_mut()).enumerate() {
+            if *alive && !keep(slot) {
+                *alive = false;
+                self.free.push(index);
+            }
+        }
+    }
+
+    /// Releases every object.
+    pub fn clear(&mut self) {
+        self.retain(|_| false);
+    }
+
+
+    /// The number of objects currently in use.
+    pub fn active_count(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    /// The number of objects which can be used at the same time.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+
+    /// Iterates over the objects which are in use.
+    pub fn iter<'a>(&'a self) -> Iter<'a, T> {
+        Iter {
+            slots: self.slots.iter(),
+            alive: self.alive.iter(),
+        }
+    }
+
+    /// Iterates mutably over the objects which are in use.
+    pub fn iter_mut<'a>(&'a mut self) -> IterMut<'a, T> {
+        IterMut {
+            slots: self.slots.iter_mut(),
+            alive: self.alive.iter(),
+        }
+    }
+}
+
+
+pub struct Iter<'a, T: 'a> {
+    slots: slice::Iter<'a, T>,
+    alive: slice::Iter<'a, bool>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        while let (Some(slot), Some(&alive)) = (self.slots.next(), self.alive.next()) {
+            if alive {
+                return Some(slot);
+            }
+        }
+
+        None
+    }
+}
+
+
+pub struct IterMut<'a, T: 'a> {
+    slots: slice::IterMut<'a, T>,
+    alive: slice::Iter<'a, bool>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        while let (Some(slot), Some(&alive)) = (self.slots.next(), self.alive.next()) {
+            if alive {
+                return Some(slot);
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Pool<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Pool<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acquires_until_full() {
+        let mut pool = Pool::new(3, 0);
+
+        for i in 0..3 {
+            *pool.acquire().unwrap() = i;
+        }
+
+        assert!(pool.acquire().is_none());
+        assert_eq!(pool.active_count(), 3);
+        assert_eq!(pool.capacity(), 3);
+    }
+
+    #[test]
+    fn freed_slots_are_reused() {
+        let mut pool = Pool::new(3, 0);
+        for i in 1..4 {
+            *pool.acquire().unwrap() = i;
+        }
+
+        pool.retain(|&mut value| value != 2);
+        assert_eq!(pool.active_count(), 2);
+
+        // The slot which was freed is handed out again, with its old value,
+        // and no other.
+        let slot = pool.acquire().unwrap();
+        assert_eq!(*slot, 2);
+        *slot = 4;
+
+        assert!(pool.acquire().is_none());
+        assert_eq!(pool.iter().cloned().collect::<Vec<_>>(), vec![1, 4, 3]);
+    }
+
+    #[test]
+    fn iteration_skips_dead_slots() {
+        let mut pool = Pool::new(5, 0);
+        for i in 0..5 {
+            *pool.acquire().unwrap() = i;
+        }
+
+        pool.retain(|&mut value| value % 2 == 0);
+        assert_eq!(pool.active_count(), 3);
+        assert_eq!(pool.iter().cloned().collect::<Vec<_>>(), vec![0, 2, 4]);
+
+        for value in &mut pool {
+            *value *= 10;
+        }
+
+        assert_eq!((&pool).into_iter().cloned().collect::<Vec<_>>(), vec![0, 20, 40]);
+    }
+
+    #[test]
+    fn retain_may_change_the_objects_it_keeps() {
+        let mut pool = Pool::new(2, 0);
+        *pool.acquire().unwrap() = 1;
+        *pool.acquire().unwrap() = 5;
+
+        pool.retain(|value| {
+            *value += 1;
+            *value < 4
+        });
+
+        assert_eq!(pool.iter().cloned().collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn clear_frees_every_slot() {
+        let mut pool = Pool::new(2, 0);
+        pool.acquire();
+        pool.acquire();
+        pool.clear();
+
+        assert_eq!(pool.active_count(), 0);
+        assert_eq!(pool.iter().count(), 0);
+        assert!(pool.acquire().is_some());
+    }
+
+    #[test]
+    fn empty_pool_has_nothing_to_acquire() {
+        let mut pool = Pool::new(0, 0);
+
+        assert!(pool.acquire().is_none());
+        assert_eq!(pool.active_count(), 0);
+    }
+
+    /// Compares the pool of bullets of the game with the `Vec<Box<Bullet>>`
+    /// which it replaced, firing a bullet every frame, moving them all, and
+    /// dropping those which leave the screen. Run with
+    /// `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_pool_against_boxed_vec() {
+        use ::phi::data::Rectangle;
+        use ::std::time::Instant;
+
+        // The same as in `views::game`.
+        #[derive(Clone, Copy)]
+        struct Bullet {
+            rect: Rectangle,
+        }
+
+        const FRAMES: usize = 100_000;
+        const MAX_BULLETS: usize = 64;
+        const SCREEN_W: f64 = 800.0;
+        const STEP: f64 = 4.0;
+
+        let bullet = Bullet { rect: Rectangle { x: 0.0, y: 300.0, w: 8.0, h: 4.0 } };
+
+        let start = Instant::now();
+        let mut pool = Pool::new(MAX_BULLETS, bullet);
+        for _ in 0..FRAMES {
+            if let Some(slot) = pool.acquire() {
+                *slot = bullet;
+            }
+
+            pool.retain(|bullet| {
+                bullet.rect.x += STEP;
+                bullet.rect.x < SCREEN_W
+            });
+        }
+        let pool_time = start.elapsed();
+        let pool_alive = pool.active_count();
+
+        let start = Instant::now();
+        let mut boxes: Vec<Box<Bullet>> = Vec::new();
+        for _ in 0..FRAMES {
+            if boxes.len() < MAX_BULLETS {
+                boxes.push(Box::new(bullet));
+            }
+
+            boxes = boxes.into_iter()
+                .filter_map(|mut bullet| {
+                    bullet.rect.x += STEP;
+                    if bullet.rect.x < SCREEN_W { Some(bullet) } else { None }
+                })
+                .collect();
+        }
+        let boxes_time = start.elapsed();
+
+        println!("{} frames: Pool in {:?}, Vec<Box<Bullet>> in {:?}", FRAMES, pool_time, boxes_time);
+        assert_eq!(pool_alive, boxes.len());
+    }
+}
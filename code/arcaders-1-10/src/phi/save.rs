@@ -0,0 +1,242 @@
+use ::phi::data::scores::MAX_SCORES;
+use ::rustc_serialize::json::Json;
+use ::std::collections::BTreeMap;
+use ::std::fs::{self, File};
+use ::std::io::{self, Read, Write};
+use ::std::path::{Path, PathBuf};
+
+
+/// A score which was saved, along with who achieved it and when.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoreEntry {
+    pub name: String,
+    pub score: u64,
+    /// When the score was achieved, in seconds since the Unix epoch.
+    pub date: u64,
+}
+
+impl ScoreEntry {
+    fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        object.insert("name".to_string(), Json::String(self.name.clone()));
+        object.insert("score".to_string(), Json::U64(self.score));
+        object.insert("date".to_string(), Json::U64(self.date));
+        Json::Object(object)
+    }
+
+    fn from_json(json: &Json) -> Option<ScoreEntry> {
+        let name = match json.find("name").and_then(|name| name.as_string()) {
+            Some(name) => name,
+            None => return None,
+        };
+
+        let score = match json.find("score").and_then(|score| score.as_u64()) {
+            Some(score) => score,
+            None => return None,
+        };
+
+        Some(ScoreEntry {
+            name: name.to_string(),
+            score: score,
+            // Older files may not have a date.
+            date: json.find("date").and_then(|date| date.as_u64()).unwrap_or(0),
+        })
+    }
+}
+
+
+/// Something which can be saved to a file and read back, for instance the
+/// state of a game in progress, so that the player may resume it later.
+///
+/// Only plain data should be saved: sprites and other resources are loaded
+/// again when the state is read back.
+pub trait Serializable: Sized {
+    fn to_bytes(&self) -> Vec<u8>;
+    fn from_bytes(bytes: &[u8]) -> Result<Self, String>;
+}
+
+
+/// The directory where the game should keep the files of the player, such as
+/// their scores, following the conventions of the system: `%APPDATA%\app` on
+/// Windows, `~/Library/Application Support/app` on macOS, and
+/// `$XDG_CONFIG_HOME/app` or `~/.config/app` elsewhere. If none of them can be
+/// found, the files are kept in the working directory.
+pub fn config_dir(app: &str) -> PathBuf {
+    let var = |name: &str| ::std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+
+    let base =
+        if cfg!(windows) { var("APPDATA") }
+        else if cfg!(target_os = "macos") { var("HOME").map(|home| home.join("Library/Application Support")) }
+        else { var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|home| home.join(".config"))) };
+
+    match base {
+        Some(base) => base.join(app),
+        None => PathBuf::from("."),
+    }
+}
+
+/// Writes `bytes` to `path`, creating its directory if needed.
+///
+/// They are first written to a temporary file next to it, which then replaces
+/// it, so that a crash while saving leaves the previous file as it was rather
+/// than half-written.
+pub fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+
+    {
+        let mut file = File::create(&temporary)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+    }
+
+    fs::rename(&temporary, path)
+}
+
+/// Writes the scores to `path` as JSON. See `write_atomically`.
+pub fn save_scores(path: &Path, scores: &[ScoreEntry]) -> io::Result<()> {
+    let json = Json::Array(scores.iter().map(ScoreEntry::to_json).collect());
+    write_atomically(path, json.pretty().to_string().as_bytes())
+}
+
+/// Writes `state` to `path`. See `write_atomically`.
+pub fn save_state<S: Serializable>(path: &Path, state: &S) -> io::Result<()> {
+    write_atomically(path, &state.to_bytes())
+}
+
+/// Reads the state saved at `path` with `save_state`.
+pub fn load_state<S: Serializable>(path: &Path) -> Result<S, String> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    S::from_bytes(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Reads the scores saved at `path`, from the best to the worst, keeping only
+/// the `MAX_SCORES` best ones.
+///
+/// A missing file means that nobody has played yet, and a corrupt one cannot
+/// be recovered anyway, so both give an empty list rather than an error. Only
+/// a file which exists but cannot be read is an error.
+pub fn load_scores(path: &Path) -> io::Result<Vec<ScoreEntry>> {
+    let mut source = String::new();
+
+    match File::open(path) {
+        Ok(mut file) => { file.read_to_string(&mut source)?; },
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    }
+
+    let mut scores: Vec<ScoreEntry> = match Json::from_str(&source) {
+        Ok(Json::Array(entries)) => entries.iter().filter_map(ScoreEntry::from_json).collect(),
+        _ => Vec::new(),
+    };
+
+    // The sort is stable, so that older scores stay above newer ties.
+    scores.sort_by(|a, b| b.score.cmp(&a.score));
+    scores.truncate(MAX_SCORES);
+
+    Ok(scores)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A file named after the test in the temporary directory, which does not
+    /// exist yet.
+    fn temporary(name: &str) -> PathBuf {
+        let path = ::std::env::temp_dir()
+            .join(format!("arcade-rs-{}-{}", ::std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn load_source(name: &str, source: &str) -> Vec<ScoreEntry> {
+        let path = temporary(name);
+        write_atomically(&path, source.as_bytes()).unwrap();
+        let scores = load_scores(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        scores
+    }
+
+    fn entry(name: &str, score: u64, date: u64) -> ScoreEntry {
+        ScoreEntry { name: name.to_string(), score: score, date: date }
+    }
+
+    #[test]
+    fn scores_round_trip() {
+        let path = temporary("round-trip.json");
+        let scores = vec![entry("ada", 300, 1_500_000_000), entry("bob", 100, 0)];
+
+        save_scores(&path, &scores).unwrap();
+        assert_eq!(load_scores(&path).unwrap(), scores);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn missing_file_has_no_scores() {
+        assert_eq!(load_scores(&temporary("missing.json")).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn garbage_has_no_scores() {
+        assert_eq!(load_source("garbage.json", "not json at all {"), vec![]);
+        assert_eq!(load_source("object.json", r#"{"name": "ada", "score": 300}"#), vec![]);
+        assert_eq!(load_source("empty.json", ""), vec![]);
+    }
+
+    #[test]
+    fn entries_missing_fields_are_dropped() {
+        let source = r#"[
+            {"score": 300},
+            {"name": "bob"},
+            {"name": "eve", "score": "lots"},
+            {"name": "ada", "score": 200}
+        ]"#;
+
+        // Only the date is optional.
+        assert_eq!(load_source("fields.json", source), vec![entry("ada", 200, 0)]);
+        assert_eq!(load_source("no-entries.json", r#"[{"score": 1}, 42]"#), vec![]);
+    }
+
+    #[test]
+    fn loaded_scores_are_sorted_and_truncated() {
+        let source = format!("[{}]", (0..15)
+            .map(|i| format!(r#"{{"name": "p{}", "score": {}}}"#, i, (i % 5) * 10))
+            .collect::<Vec<_>>()
+            .join(", "));
+        let scores = load_source("many.json", &source);
+
+        assert_eq!(scores.len(), MAX_SCORES);
+        assert_eq!(scores.iter().map(|entry| entry.score).collect::<Vec<_>>(),
+                   vec![40, 40, 40, 30, 30, 30, 20, 20, 20, 10]);
+        // Ties keep the order of the file.
+        assert_eq!(scores[0].name, "p4");
+        assert_eq!(scores[2].name, "p14");
+    }
+
+    #[test]
+    fn writing_replaces_the_file() {
+        let path = temporary("replaced.txt");
+        write_atomically(&path, b"first").unwrap();
+        write_atomically(&path, b"second").unwrap();
+
+        let mut contents = String::new();
+        File::open(&path).unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "second");
+
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        assert!(!Path::new(&temporary).exists());
+        fs::remove_file(&path).unwrap();
+    }
+}
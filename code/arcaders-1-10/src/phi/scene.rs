@@ -0,0 +1,77 @@
+use ::phi::{Phi, View, ViewAction};
+
+
+/// A part of a scene, such as its background, the gameplay or the HUD, which
+/// is updated and rendered independently of the others.
+pub trait Layer {
+    /// Where the layer is drawn with respect to the others of the scene: the
+    /// layers are rendered from the lowest `z_order` to the highest, so that
+    /// the latter are drawn on top.
+    fn z_order(&self) -> i32;
+
+    /// Called once per update of the scene. The first layer to return
+    /// something other than `ViewAction::None` decides what the scene does,
+    /// and the layers behind it are not updated until the next time.
+    fn update(&mut self, _phi: &mut Phi, _dt: f64) -> ViewAction {
+        ViewAction::None
+    }
+
+    /// Same as `View::render`, but the layer must not clear the screen, since
+    /// it would hide the layers underneath.
+    fn render(&mut self, phi: &mut Phi, interpolation: f64);
+}
+
+
+/// A view made of several layers, which know nothing of each other. It clears
+/// the screen, then renders them back-to-front.
+pub struct Scene {
+    /// Sorted by `z_order`. Layers with the same `z_order` stay in the order in
+    /// which they were added.
+    layers: Vec<Box<dyn Layer>>,
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        Scene {
+            layers: Vec::new(),
+        }
+    }
+
+    /// Adds a layer to the scene, above the layers with the same `z_order`.
+    pub fn add(&mut self, layer: Box<dyn Layer>) {
+        let z = layer.z_order();
+        let index = self.layers.iter()
+            .position(|other| other.z_order() > z)
+            .unwrap_or(self.layers.len());
+
+        self.layers.insert(index, layer);
+    }
+
+    pub fn layers(&self) -> &[Box<dyn Layer>] {
+        &self.layers
+    }
+}
+
+impl View for Scene {
+    /// Updates the layers from the front to the back, so that the layers on
+    /// top, such as the HUD, get a chance to react to the input first.
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
+        for layer in self.layers.iter_mut().rev() {
+            match layer.update(phi, dt) {
+                ViewAction::None => {},
+                action => return action,
+            }
+        }
+
+        ViewAction::None
+    }
+
+    fn render(&mut self, phi: &mut Phi, interpolation: f64) {
+        phi.renderer.set_draw_color(::sdl2::pixels::Color::RGB(0, 0, 0));
+        phi.renderer.clear();
+
+        for layer in self.layers.iter_mut() {
+            layer.render(phi, interpolation);
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use ::phi::Phi;
+use ::phi::camera::Camera;
+use ::phi::data::Rectangle;
+use ::phi::gfx::{CopySprite, Sprite, SpriteSheet};
+use ::rustc_serialize::json::Json;
+use ::std::collections::HashSet;
+use ::std::fs::File;
+use ::std::io::Read;
+use ::std::ops::Range;
+use ::std::path::Path;
+
+
+/// Tiled stores whether a tile is flipped in the highest bits of its index.
+const TILED_INDEX_MASK: u64 = 0x1fff_ffff;
+
+
+/// A level made of tiles of the same size, laid out on a grid, such as the
+/// background of a top-down game. Every cell holds the index of its tile in an
+/// atlas, counting from 1 in row-major order, or 0 if it is empty.
+pub struct Tilemap {
+    /// The index of the tile of every cell, row by row.
+    cells: Vec<u32>,
+    columns: usize,
+    rows: usize,
+    tile_w: f64,
+    tile_h: f64,
+    /// The tiles of the atlas, the one of index 1 first, or none until
+    /// `set_atlas` is called.
+    tiles: Vec<Sprite>,
+    /// The indices of the tiles which cannot be walked through, such as walls.
+    solid: HashSet<u32>,
+}
+
+impl Tilemap {
+    /// Loads the grid from `csv_path`, where every line is a row of tile
+    /// indices separated by commas, and cuts the image at `atlas_path` into
+    /// tiles of `tile_w` by `tile_h` pixels.
+    ///
+    /// Returns an error if either file cannot be read, if the rows are not all
+    /// as long, or if a cell refers to a tile which the atlas does not have.
+    pub fn from_csv(phi: &mut Phi, csv_path: &str, atlas_path: &str, tile_w: u32, tile_h: u32) -> Result<Tilemap, String> {
+        let mut source = String::new();
+        File::open(csv_path)
+            .and_then(|mut file| file.read_to_string(&mut source))
+            .map_err(|e| format!("{}: {}", csv_path, e))?;
+
+        let atlas = SpriteSheet::new(phi.sprite(atlas_path)?, tile_w, tile_h);
+        let mut map = Tilemap::parse(csv_path, &source, atlas.frames().len(), tile_w, tile_h)?;
+        map.set_atlas(&atlas)?;
+        Ok(map)
+    }
+
+    /// Loads a map saved by the Tiled editor in its JSON format, and the image
+    /// of its tileset, whose path is relative to the map. Only the first tile
+    /// layer is loaded, and the map must have a single tileset, embedded in it
+    /// and made of one image. Flipped tiles are loaded unflipped.
+    pub fn from_tiled(phi: &mut Phi, path: &str) -> Result<Tilemap, String> {
+        let mut source = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut source))
+            .map_err(|e| format!("{}: {}", path, e))?;
+
+        let json = Json::from_str(&source).map_err(|e| format!("{}: {}", path, e))?;
+        let number = |name: &str| json.find(name).and_then(Json::as_u64)
+            .ok_or_else(|| format!("{}: missing or invalid {:?}", path, name));
+        let (columns, tile_w, tile_h) = (number("width")?, number("tilewidth")?, number("tileheight")?);
+
+        let data = json.find("layers").and_then(Json::as_array)
+            .and_then(|layers| layers.iter()
+                .find(|layer| layer.find("type").and_then(Json::as_string) == Some("tilelayer")))
+            .and_then(|layer| layer.find("data")).and_then(Json::as_array)
+            .ok_or_else(|| format!("{}: no tile layer", path))?;
+
+        let tileset = json.find("tilesets").and_then(Json::as_array)
+            .and_then(|tilesets| if tilesets.len() == 1 { tilesets.first() } else { None })
+            .ok_or_else(|| format!("{}: expected a single tileset", path))?;
+        let first_index = tileset.find("firstgid").and_then(Json::as_u64).unwrap_or(1).max(1);
+        let image = tileset.find("image").and_then(Json::as_string)
+            .ok_or_else(|| format!("{}: the tileset must be embedded, and made of one image", path))?;
+
+        let image_path = Path::new(path).parent().unwrap_or(Path::new("")).join(image);
+        let atlas = SpriteSheet::new(phi.sprite(&image_path.to_string_lossy())?,
+                                     tile_w as u32, tile_h as u32);
+        let tile_count = atlas.frames().len() as u64;
+
+        // Tiled counts the tiles of the tileset from `firstgid`, and uses 0 for
+        // empty cells as well.
+        let cells = data.iter().enumerate()
+            .map(|(i, cell)| match cell.as_u64().map(|index| index & TILED_INDEX_MASK) {
+                Some(0) => Ok(0),
+                Some(index) if index >= first_index && index - first_index < tile_count =>
+                    Ok((index - first_index + 1) as u32),
+                Some(index) => Err(format!("{}: tile {} of cell {} is not in the tileset", path, index, i)),
+                None => Err(format!("{}: invalid tile index {} in cell {}", path, cell, i)),
+            })
+            .collect::<Result<Vec<u32>, String>>()?;
+
+        if columns == 0 || cells.len() as u64 % columns != 0 {
+            return Err(format!("{}: {} cells do not make rows of {}", path, cells.len(), columns));
+        }
+
+        let rows = cells.len() / columns as usize;
+        let mut map = Tilemap::from_cells(cells, columns as usize, rows, tile_w as u32, tile_h as u32);
+        map.set_atlas(&atlas)?;
+        Ok(map)
+    }
+
+    /// Same as `from_csv`, but reads the grid from `source`, where the cells
+    /// may refer to `tile_count` tiles. The map has no sprites to render until
+    /// `set_atlas` is called. `origin` is only used in error messages.
+    pub fn parse(origin: &str, source: &str, tile_count: usize, tile_w: u32, tile_h: u32) -> Result<Tilemap, String> {
+        let mut cells = Vec::new();
+        let mut columns = None;
+        let mut rows = 0;
+
+        for (number, line) in source.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+            // Editors often leave an empty line at the end of the file.
+            if line.is_empty() {
+                continue;
+            }
+
+            let row = line.split(',')
+                .map(|cell| cell.trim().parse::<u32>()
+                    .map_err(|_| format!("{}:{}: invalid tile index {:?}", origin, number, cell.trim())))
+                .collect::<Result<Vec<u32>, String>>()?;
+
+            if let Some(&index) = row.iter().find(|&&index| index as usize > tile_count) {
+                return Err(format!("{}:{}: tile {} is not in the atlas, which has {} tiles",
+                                   origin, number, index, tile_count));
+            }
+
+            match columns {
+                Some(columns) if columns != row.len() =>
+                    return Err(format!("{}:{}: expected {} tiles, found {}",
+                                       origin, number, columns, row.len())),
+                _ => columns = Some(row.len()),
+            }
+
+            cells.extend(row);
+            rows += 1;
+        }
+
+        Ok(Tilemap::from_cells(cells, columns.unwrap_or(0), rows, tile_w, tile_h))
+    }
+
+    fn from_cells(cells: Vec<u32>, columns: usize, rows: usize, tile_w: u32, tile_h: u32) -> Tilemap {
+        Tilemap {
+            cells: cells,
+            columns: columns,
+            rows: rows,
+            tile_w: tile_w as f64,
+            tile_h: tile_h as f64,
+            tiles: Vec::new(),
+            solid: HashSet::new(),
+        }
+    }
+
+    /// Renders the cells with the tiles of `atlas`, the one of index 1 first,
+    /// stretched to the size of the cells if needed. Returns an error if a cell
+    /// refers to a tile which the atlas does not have.
+    pub fn set_atlas(&mut self, atlas: &SpriteSheet) -> Result<(), String> {
+        let tiles = atlas.frames();
+
+        match self.cells.iter().max() {
+            Some(&index) if index as usize > tiles.len() =>
+                Err(format!("Tile {} is not in the atlas, which has {} tiles", index, tiles.len())),
+            _ => {
+                self.tiles = tiles;
+                Ok(())
+            },
+        }
+    }
+
+    /// Marks the tiles of the given indices as solid, and every other one as
+    /// not solid. No tile is solid by default, and empty cells never are.
+    pub fn set_solid<I: IntoIterator<Item = u32>>(&mut self, tiles: I) {
+        self.solid = tiles.into_iter().filter(|&index| index != 0).collect();
+    }
+
+    pub fn is_solid(&self, index: u32) -> bool {
+        self.solid.contains(&index)
+    }
+
+    /// The number of columns and rows of the grid.
+    pub fn size(&self) -> (usize, usize) {
+        (self.columns, self.rows)
+    }
+
+    /// The region covered by the map, in the coordinates of the world, its
+    /// first tile being at the origin.
+    pub fn bounds(&self) -> Rectangle {
+        Rectangle {
+            x: 0.0,
+            y: 0.0,
+            w: self.columns as f64 * self.tile_w,
+            h: self.rows as f64 * self.tile_h,
+        }
+    }
+
+    /// The index of the tile covering the point `(world_x, world_y)`, which is
+    /// 0 if the cell is empty, or `None` if the point is outside of the map.
+    pub fn tile_at(&self, world_x: f64, world_y: f64) -> Option<u32> {
+        if world_x < 0.0 || world_y < 0.0 {
+            return None;
+        }
+
+        let (column, row) = ((world_x / self.tile_w) as usize, (world_y / self.tile_h) as usize);
+        if column < self.columns && row < self.rows {
+            Some(self.cells[row * self.columns + column])
+        } else {
+            None
+        }
+    }
+
+    /// Whether `rect`, in the coordinates of the world, overlaps a solid tile.
+    /// As with `Rectangle::overlaps`, touching a tile is not overlapping it.
+    /// The outside of the map is not solid.
+    pub fn rect_collides(&self, rect: Rectangle) -> bool {
+        let rect = rect.normalized();
+        if self.solid.is_empty() || rect.w == 0.0 || rect.h == 0.0 {
+            return false;
+        }
+
+        let (columns, mut rows) = self.cells_covering(rect);
+        rows.any(|row| columns.clone().any(|column| {
+            self.is_solid(self.cells[row * self.columns + column])
+        }))
+    }
+
+    /// The columns and rows of the cells which `rect` covers, its right and
+    /// bottom edges excluded, limited to the map.
+    fn cells_covering(&self, rect: Rectangle) -> (Range<usize>, Range<usize>) {
+        let first_column = (rect.x / self.tile_w).floor().max(0.0) as usize;
+        let first_row = (rect.y / self.tile_h).floor().max(0.0) as usize;
+        let last_column = (((rect.x + rect.w) / self.tile_w).ceil().max(0.0) as usize).min(self.columns);
+        let last_row = (((rect.y + rect.h) / self.tile_h).ceil().max(0.0) as usize).min(self.rows);
+
+        (first_column..last_column.max(first_column), first_row..last_row.max(first_row))
+    }
+
+    /// Renders the tiles which `camera` shows, skipping the empty cells, and
+    /// every cell if there is no atlas yet.
+    pub fn render(&self, phi: &mut Phi, camera: &Camera) {
+        if self.columns == 0 || self.rows == 0 {
+            return;
+        }
+
+        // Only the cells overlapping the viewport are visited, however large
+        // the map is.
+        let (columns, rows) = self.cells_covering(camera.viewport());
+
+        for row in rows {
+            for column in columns.clone() {
+                let index = self.cells[row * self.columns + column];
+                let tile = match index.checked_sub(1).and_then(|i| self.tiles.get(i as usize)) {
+                    Some(tile) => tile,
+                    None => continue,
+                };
+
+                let dest = Rectangle {
+                    x: column as f64 * self.tile_w,
+                    y: row as f64 * self.tile_h,
+                    w: self.tile_w,
+                    h: self.tile_h,
+                };
+
+                phi.renderer.copy_sprite_world(tile, camera, dest);
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A map of 16x16 tiles, where the cells may refer to 4 tiles.
+    fn parse(source: &str) -> Result<Tilemap, String> {
+        Tilemap::parse("level.csv", source, 4, 16, 16)
+    }
+
+    fn rect(x: f64, y: f64, w: f64, h: f64) -> Rectangle {
+        Rectangle { x: x, y: y, w: w, h: h }
+    }
+
+    #[test]
+    fn parses_rows_of_tile_indices() {
+        let map = parse("1, 2, 0\n 0,4,3 \n\n").unwrap();
+
+        assert_eq!(map.size(), (3, 2));
+        assert_eq!(map.bounds(), rect(0.0, 0.0, 48.0, 32.0));
+        assert_eq!(map.tile_at(17.0, 0.0), Some(2));
+        assert_eq!(map.tile_at(47.9, 31.9), Some(3));
+        assert_eq!(map.tile_at(48.0, 0.0), None);
+        assert_eq!(map.tile_at(-0.1, 0.0), None);
+    }
+
+    #[test]
+    fn ragged_rows_are_rejected() {
+        assert_eq!(parse("1,2,3\n1,2\n").err(),
+                   Some("level.csv:2: expected 3 tiles, found 2".to_string()));
+        assert_eq!(parse("1,2\n\n1,2,3\n").err(),
+                   Some("level.csv:3: expected 2 tiles, found 3".to_string()));
+    }
+
+    #[test]
+    fn non_numeric_cells_are_rejected() {
+        assert_eq!(parse("1,2\n1,wall\n").err(),
+                   Some("level.csv:2: invalid tile index \"wall\"".to_string()));
+        assert_eq!(parse("1,-2\n").err(),
+                   Some("level.csv:1: invalid tile index \"-2\"".to_string()));
+        assert_eq!(parse("1,,2\n").err(),
+                   Some("level.csv:1: invalid tile index \"\"".to_string()));
+    }
+
+    #[test]
+    fn indices_past_the_atlas_are_rejected() {
+        assert!(parse("4,0\n").is_ok());
+        assert_eq!(parse("0,0\n5,0\n").err(),
+                   Some("level.csv:2: tile 5 is not in the atlas, which has 4 tiles".to_string()));
+    }
+
+    #[test]
+    fn empty_source_gives_an_empty_map() {
+        let map = parse("\n\n").unwrap();
+
+        assert_eq!(map.size(), (0, 0));
+        assert_eq!(map.tile_at(0.0, 0.0), None);
+    }
+
+    #[test]
+    fn touching_a_solid_tile_does_not_collide() {
+        let mut map = parse("0,0,0\n0,1,0\n0,0,0\n").unwrap();
+        map.set_solid(vec![1]);
+
+        // The solid tile covers 16..32 in both directions.
+        assert!(!map.rect_collides(rect(0.0, 16.0, 16.0, 16.0)));
+        assert!(!map.rect_collides(rect(32.0, 16.0, 16.0, 16.0)));
+        assert!(!map.rect_collides(rect(16.0, 0.0, 16.0, 16.0)));
+        assert!(!map.rect_collides(rect(16.0, 32.0, 16.0, 16.0)));
+        assert!(!map.rect_collides(rect(0.0, 0.0, 16.0, 16.0)));
+
+        assert!(map.rect_collides(rect(0.5, 16.0, 16.0, 16.0)));
+        assert!(map.rect_collides(rect(31.5, 31.5, 16.0, 16.0)));
+        // A rectangle with a negative size covers the same region.
+        assert!(map.rect_collides(rect(20.0, 20.0, -8.0, -8.0)));
+    }
+
+    #[test]
+    fn only_solid_tiles_collide() {
+        let mut map = parse("1,2\n3,0\n").unwrap();
+        let whole_map = rect(0.0, 0.0, 32.0, 32.0);
+        assert!(!map.rect_collides(whole_map));
+
+        map.set_solid(vec![0, 2]);
+        assert!(map.rect_collides(whole_map));
+        assert!(!map.rect_collides(rect(0.0, 0.0, 16.0, 32.0)));
+        // Empty cells are never solid, and neither is the outside of the map.
+        assert!(!map.rect_collides(rect(16.0, 16.0, 100.0, 100.0)));
+        assert!(!map.rect_collides(rect(-50.0, -50.0, 40.0, 40.0)));
+    }
+
+    #[test]
+    fn cells_covering_excludes_the_far_edges() {
+        let map = parse("0,0,0,0\n0,0,0,0\n0,0,0,0\n").unwrap();
+
+        assert_eq!(map.cells_covering(rect(0.0, 0.0, 16.0, 16.0)), (0..1, 0..1));
+        assert_eq!(map.cells_covering(rect(8.0, 8.0, 16.0, 16.0)), (0..2, 0..2));
+        assert_eq!(map.cells_covering(rect(16.0, 16.0, 32.0, 16.0)), (1..3, 1..2));
+    }
+
+    #[test]
+    fn cells_covering_is_limited_to_the_map() {
+        let map = parse("0,0,0,0\n0,0,0,0\n0,0,0,0\n").unwrap();
+
+        assert_eq!(map.cells_covering(rect(-20.0, -20.0, 1000.0, 1000.0)), (0..4, 0..3));
+        assert_eq!(map.cells_covering(rect(40.0, 40.0, 1000.0, 1000.0)), (2..4, 2..3));
+
+        // Entirely outside of the map
+        let (columns, rows) = map.cells_covering(rect(100.0, 100.0, 10.0, 10.0));
+        assert!(columns.count() == 0 || rows.count() == 0);
+        let (columns, rows) = map.cells_covering(rect(-40.0, -40.0, 10.0, 10.0));
+        assert!(columns.count() == 0 || rows.count() == 0);
+    }
+}
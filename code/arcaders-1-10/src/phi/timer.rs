@@ -0,0 +1,579 @@
+use ::phi::Phi;
+use ::std::collections::VecDeque;
+
+
+/// Identifies a timer, so that it can be cancelled before it fires.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TimerHandle(u64);
+
+
+enum Callback {
+    Once(Box<dyn FnOnce(&mut Phi)>),
+    /// Called every so many seconds, for as long as it returns `true`.
+    Every(f64, Box<dyn FnMut(&mut Phi) -> bool>),
+}
+
+struct Scheduled {
+    id: u64,
+    /// The time left before the callback is called, in seconds.
+    remaining: f64,
+    callback: Callback,
+}
+
+
+/// The callbacks which should be called after some time. The queue of the
+/// context, `Phi::timers`, is advanced by the game loop before every update,
+/// so that views do not need to keep track of it themselves.
+///
+/// Timers are independent of the views: they keep running when the view which
+/// created them is paused or replaced, unless they are cancelled.
+pub struct TimerQueue {
+    timers: Vec<Scheduled>,
+    next_id: u64,
+
+    /// The timer whose callback is being called, if any, and whether it was
+    /// cancelled from inside of it.
+    running: Option<u64>,
+    running_cancelled: bool,
+}
+
+impl TimerQueue {
+    pub fn new() -> TimerQueue {
+        TimerQueue {
+            timers: Vec::new(),
+            next_id: 0,
+            running: None,
+            running_cancelled: false,
+        }
+    }
+
+    /// Calls `f` once, in `delay` seconds.
+    pub fn after(&mut self, delay: f64, f: Box<dyn FnOnce(&mut Phi)>) -> TimerHandle {
+        self.push(delay, Callback::Once(f))
+    }
+
+    /// Calls `f` every `interval` seconds, starting in `interval` seconds,
+    /// until it returns `false` or is cancelled. It is called at most once per
+    /// update, even if the interval is shorter than an update.
+    pub fn every(&mut self, interval: f64, f: Box<dyn FnMut(&mut Phi) -> bool>) -> TimerHandle {
+        let interval = interval.max(0.0);
+        self.push(interval, Callback::Every(interval, f))
+    }
+
+    /// Prevents the timer from firing again. Does nothing if it has already
+    /// fired, or if it was already cancelled.
+    pub fn cancel(&mut self, handle: TimerHandle) {
+        if self.running == Some(handle.0) {
+            self.running_cancelled = true;
+        }
+
+        self.timers.retain(|timer| timer.id != handle.0);
+    }
+
+    /// Whether the timer will fire again.
+    pub fn is_pending(&self, handle: TimerHandle) -> bool {
+        self.timers.iter().any(|timer| timer.id == handle.0) ||
+            (self.running == Some(handle.0) && !self.running_cancelled)
+    }
+
+    /// Cancels every timer.
+    pub fn clear(&mut self) {
+        self.timers.clear();
+        self.running_cancelled = self.running.is_some();
+    }
+
+
+    fn push(&mut self, delay: f64, callback: Callback) -> TimerHandle {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.timers.push(Scheduled {
+            id: id,
+            remaining: delay,
+            callback: callback,
+        });
+
+        TimerHandle(id)
+    }
+
+    /// Advances every timer by `dt` seconds and returns those which are due,
+    /// the most overdue first.
+    fn due(&mut self, dt: f64) -> Vec<u64> {
+        for timer in self.timers.iter_mut() {
+            timer.remaining -= dt;
+        }
+
+        let mut due: Vec<(f64, u64)> = self.timers.iter()
+            .filter(|timer| timer.remaining <= 0.0)
+            .map(|timer| (timer.remaining, timer.id))
+            .collect();
+
+        due.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(::std::cmp::Ordering::Equal));
+        due.into_iter().map(|(_, id)| id).collect()
+    }
+
+    fn take(&mut self, id: u64) -> Option<Scheduled> {
+        self.timers.iter().position(|timer| timer.id == id)
+            .map(|index| self.timers.remove(index))
+    }
+}
+
+
+/// Advances the timers of `phi` by `dt` seconds and calls the callbacks of
+/// those which are due. The callbacks may freely create and cancel timers.
+pub fn tick(phi: &mut Phi, dt: f64) {
+    for id in phi.timers.due(dt) {
+        // The timer may have been cancelled by a previous callback.
+        let timer = match phi.timers.take(id) {
+            Some(timer) => timer,
+            None => continue,
+        };
+
+        phi.timers.running = Some(id);
+        phi.timers.running_cancelled = false;
+
+        match timer.callback {
+            Callback::Once(f) => f(phi),
+
+            Callback::Every(interval, mut f) => {
+                if f(phi) && !phi.timers.running_cancelled {
+                    phi.timers.timers.push(Scheduled {
+                        id: id,
+                        remaining: timer.remaining + interval,
+                        callback: Callback::Every(interval, f),
+                    });
+                }
+            },
+        }
+
+        phi.timers.running = None;
+    }
+}
+
+
+/// Limits how often something may happen, such as the ship firing. Unlike the
+/// timers of a `TimerQueue`, it belongs to the view which uses it, and only
+/// advances when the view updates it, so it stops while the view is paused.
+#[derive(Clone, Copy, Debug)]
+pub struct Cooldown {
+    /// The time between two triggers, in seconds.
+    pub duration: f64,
+    /// The time left before it may trigger again. It may be negative if it is
+    /// accumulating.
+    remaining: f64,
+    accumulate: bool,
+}
+
+impl Cooldown {
+    /// Creates a cooldown which is ready right away, and becomes ready again
+    /// `duration` seconds after every trigger.
+    pub fn new(duration: f64) -> Cooldown {
+        Cooldown {
+            duration: duration,
+            remaining: 0.0,
+            accumulate: false,
+        }
+    }
+
+    /// Same as `new`, but when a single update lasts longer than `duration`,
+    /// the time left over is kept, so that it may trigger several times in a
+    /// row to catch up and the average rate stays the same whatever the
+    /// frame rate. At most one trigger is kept in reserve, so that it does not
+    /// fire a burst after a long idle period.
+    pub fn accumulating(duration: f64) -> Cooldown {
+        Cooldown {
+            accumulate: true,
+            ..Cooldown::new(duration)
+        }
+    }
+
+    /// Advances the cooldown by `dt` seconds.
+    pub fn update(&mut self, dt: f64) {
+        let floor = if self.accumulate { -self.duration } else { 0.0 };
+        self.remaining = (self.remaining - dt).max(floor);
+    }
+
+    /// Whether `try_trigger` would succeed.
+    pub fn is_ready(&self) -> bool {
+        self.remaining <= 0.0
+    }
+
+    /// The time left before it is ready, in seconds.
+    pub fn remaining(&self) -> f64 {
+        self.remaining.max(0.0)
+    }
+
+    /// Starts the cooldown and returns `true` if it is ready, and otherwise
+    /// does nothing and returns `false`.
+    pub fn try_trigger(&mut self) -> bool {
+        if !self.is_ready() {
+            return false;
+        }
+
+        self.remaining += self.duration;
+        true
+    }
+
+    /// Makes it ready right away.
+    pub fn reset(&mut self) {
+        self.remaining = 0.0;
+    }
+}
+
+
+/// Expires `duration` seconds after it is created or reset, for instance to
+/// make the ship invulnerable for a while after it respawns. Like `Cooldown`,
+/// it belongs to the view which updates it.
+#[derive(Clone, Copy, Debug)]
+pub struct Timer {
+    /// The time between two expirations, in seconds.
+    pub duration: f64,
+    elapsed: f64,
+    repeat: bool,
+    expired: bool,
+}
+
+impl Timer {
+    /// Creates a timer which expires once, `duration` seconds from now.
+    pub fn new(duration: f64) -> Timer {
+        Timer {
+            duration: duration,
+            elapsed: 0.0,
+            repeat: false,
+            expired: false,
+        }
+    }
+
+    /// Creates a timer which expires every `duration` seconds, for instance to
+    /// spawn an enemy at a steady rate.
+    pub fn repeating(duration: f64) -> Timer {
+        Timer {
+            repeat: true,
+            ..Timer::new(duration)
+        }
+    }
+
+    /// Advances the timer by `dt` seconds, and returns how many times it
+    /// expired in the meantime. This is at most 1 for a timer which does not
+    /// repeat, but may be more for one which does if `dt` is longer than its
+    /// duration, so that nothing is skipped when the frame rate drops.
+    pub fn update(&mut self, dt: f64) -> u32 {
+        if self.expired {
+            return 0;
+        }
+
+        self.elapsed += dt;
+        if self.elapsed < self.duration {
+            return 0;
+        }
+
+        if !self.repeat || self.duration <= 0.0 {
+            self.elapsed = self.duration;
+            self.expired = !self.repeat;
+            return 1;
+        }
+
+        let count = (self.elapsed / self.duration).floor();
+        self.elapsed -= count * self.duration;
+        count as u32
+    }
+
+    /// Whether a timer which does not repeat has expired. A repeating timer
+    /// never is.
+    pub fn is_expired(&self) -> bool {
+        self.expired
+    }
+
+    /// The time left before it expires next, in seconds.
+    pub fn remaining(&self) -> f64 {
+        (self.duration - self.elapsed).max(0.0)
+    }
+
+    /// Starts counting down from `duration` again, even if it had expired.
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+        self.expired = false;
+    }
+}
+
+
+
+/// Turns the measured durations of the frames into the time which every frame
+/// simulates, for the game loop. It is kept by `Phi`, which reports its values
+/// as `Phi::raw_delta` and `Phi::delta`.
+pub struct FrameClock {
+    raw_delta: f64,
+    delta: f64,
+
+    /// The longest duration, in seconds, that a single frame may count for.
+    /// See `Phi::set_max_delta`.
+    pub max_delta: f64,
+
+    // The last durations, the most recent one last, once clamped.
+    history: VecDeque<f64>,
+    smoothing: usize,
+
+    // The durations of the frames of roughly the last second, the most recent
+    // one last, as they were measured, and their sum.
+    frame_times: VecDeque<f64>,
+    frame_times_sum: f64,
+}
+
+impl FrameClock {
+    /// Creates a clock which does not smooth the durations, and where no
+    /// frame was recorded yet.
+    pub fn new(max_delta: f64) -> FrameClock {
+        FrameClock {
+            raw_delta: 0.0,
+            delta: 0.0,
+            max_delta: max_delta,
+            history: VecDeque::new(),
+            smoothing: 1,
+            frame_times: VecDeque::new(),
+            frame_times_sum: 0.0,
+        }
+    }
+
+    /// Sets the number of frames over which `delta` is averaged. With `1`, the
+    /// default, no smoothing is applied. Passing `0` is the same as `1`.
+    pub fn set_smoothing(&mut self, frames: usize) {
+        self.smoothing = ::std::cmp::max(frames, 1);
+
+        while self.history.len() > self.smoothing {
+            self.history.pop_front();
+        }
+    }
+
+    /// Records that the last frame lasted `raw_delta` seconds, and returns how
+    /// long it counts for, that is, the new `delta`.
+    pub fn record(&mut self, raw_delta: f64) -> f64 {
+        self.raw_delta = raw_delta;
+
+        // Keep the frames of the last second, but at least the last one, so
+        // that the frame rate stays meaningful when it drops below 1.
+        self.frame_times.push_back(raw_delta);
+        self.frame_times_sum += raw_delta;
+
+        while self.frame_times.len() > 1 && self.frame_times_sum - self.frame_times[0] >= 1.0 {
+            self.frame_times_sum -= self.frame_times.pop_front().unwrap();
+        }
+
+        if self.history.len() == self.smoothing {
+            self.history.pop_front();
+        }
+
+        self.history.push_back(raw_delta.min(self.max_delta));
+        self.delta = self.history.iter().fold(0.0, |sum, dt| sum + dt) / self.history.len() as f64;
+        self.delta
+    }
+
+    /// The duration of the last frame, exactly as it was measured.
+    pub fn raw_delta(&self) -> f64 {
+        self.raw_delta
+    }
+
+    /// The average duration of the last few frames, where every frame counts
+    /// for at most `max_delta` seconds.
+    pub fn delta(&self) -> f64 {
+        self.delta
+    }
+
+    /// The number of frames per second, averaged over roughly the last second,
+    /// from the measured durations.
+    pub fn fps(&self) -> f64 {
+        if self.frame_times_sum > 0.0 { self.frame_times.len() as f64 / self.frame_times_sum }
+        else { 0.0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timer_expires_once() {
+        let mut timer = Timer::new(0.5);
+
+        assert_eq!(timer.update(0.25), 0);
+        assert_eq!(timer.remaining(), 0.25);
+        assert!(!timer.is_expired());
+
+        assert_eq!(timer.update(0.25), 1);
+        assert!(timer.is_expired());
+        assert_eq!(timer.remaining(), 0.0);
+
+        assert_eq!(timer.update(10.0), 0);
+        assert!(timer.is_expired());
+    }
+
+    #[test]
+    fn timer_expires_once_even_with_a_big_dt() {
+        let mut timer = Timer::new(0.25);
+
+        assert_eq!(timer.update(2.0), 1);
+        assert_eq!(timer.update(2.0), 0);
+    }
+
+    #[test]
+    fn repeating_timer_counts_every_expiry_in_a_big_dt() {
+        let mut timer = Timer::repeating(0.25);
+
+        assert_eq!(timer.update(0.875), 3);
+        assert_eq!(timer.remaining(), 0.125);
+        assert!(!timer.is_expired());
+
+        assert_eq!(timer.update(0.125), 1);
+        assert_eq!(timer.remaining(), 0.25);
+
+        assert_eq!(timer.update(2.5), 10);
+        assert_eq!(timer.remaining(), 0.25);
+    }
+
+    #[test]
+    fn repeating_timer_does_not_drift() {
+        let mut timer = Timer::repeating(0.1);
+        let expiries: u32 = (0..600).map(|_| timer.update(1.0 / 60.0)).sum();
+
+        // 10 seconds, give or take the rounding of the last frame.
+        assert!(expiries == 99 || expiries == 100, "{} expiries", expiries);
+    }
+
+    #[test]
+    fn reset_restarts_an_expired_timer() {
+        let mut timer = Timer::new(0.5);
+        timer.update(1.0);
+        timer.reset();
+
+        assert!(!timer.is_expired());
+        assert_eq!(timer.remaining(), 0.5);
+        assert_eq!(timer.update(0.5), 1);
+    }
+
+    /// Holds the fire key for `seconds`, updating the cooldown every `dt`
+    /// seconds and triggering it as many times as it allows, like the game
+    /// does, and returns how many times it fired.
+    fn fire(mut cooldown: Cooldown, dt: f64, seconds: f64) -> u32 {
+        let mut fired = 0;
+
+        for _ in 0..(seconds / dt).round() as u32 {
+            cooldown.update(dt);
+            while cooldown.try_trigger() {
+                fired += 1;
+            }
+        }
+
+        fired
+    }
+
+    #[test]
+    fn cooldown_is_ready_right_away() {
+        let mut cooldown = Cooldown::new(0.25);
+
+        assert!(cooldown.is_ready());
+        assert!(cooldown.try_trigger());
+        assert!(!cooldown.try_trigger());
+        assert_eq!(cooldown.remaining(), 0.25);
+    }
+
+    #[test]
+    fn cooldown_fires_at_its_rate_when_frames_are_shorter() {
+        for &dt in &[1.0 / 64.0, 1.0 / 32.0, 0.125, 0.25] {
+            assert_eq!(fire(Cooldown::new(0.25), dt, 4.0), 16, "dt = {}", dt);
+            // It keeps the time of the first frame, while it was already
+            // ready, so it fires both at the start and at the end.
+            assert_eq!(fire(Cooldown::accumulating(0.25), dt, 4.0), 17, "dt = {}", dt);
+        }
+    }
+
+    #[test]
+    fn cooldown_fires_at_its_rate_with_inexact_frames() {
+        for &dt in &[1.0 / 60.0, 1.0 / 30.0] {
+            // Rounding may make a trigger wait for one more frame, and the
+            // frames lost that way add up unless it accumulates.
+            let fired = fire(Cooldown::new(0.2), dt, 2.0);
+            assert!(fired >= 9 && fired <= 10, "dt = {}: fired {}", dt, fired);
+
+            let fired = fire(Cooldown::accumulating(0.2), dt, 2.0);
+            assert!(fired == 10 || fired == 11, "dt = {}: fired {}", dt, fired);
+        }
+    }
+
+    #[test]
+    fn cooldown_fires_once_per_frame_when_frames_are_longer() {
+        // Eight frames, in which it could have fired twelve times.
+        assert_eq!(fire(Cooldown::new(0.25), 0.375, 3.0), 8);
+    }
+
+    #[test]
+    fn accumulating_cooldown_catches_up_when_frames_are_longer() {
+        assert_eq!(fire(Cooldown::accumulating(0.25), 0.375, 3.0), 12);
+        assert_eq!(fire(Cooldown::accumulating(0.25), 0.5, 3.0), 12);
+    }
+
+    #[test]
+    fn accumulating_cooldown_keeps_one_trigger_in_reserve() {
+        let mut cooldown = Cooldown::accumulating(0.25);
+        cooldown.update(10.0);
+
+        assert!(cooldown.try_trigger());
+        assert!(cooldown.try_trigger());
+        assert!(!cooldown.try_trigger());
+        assert_eq!(cooldown.remaining(), 0.25);
+    }
+
+    #[test]
+    fn long_frame_is_clamped() {
+        let mut clock = FrameClock::new(1.0 / 15.0);
+
+        assert_eq!(clock.record(1.0), 1.0 / 15.0);
+        assert_eq!(clock.delta(), 1.0 / 15.0);
+        assert_eq!(clock.raw_delta(), 1.0);
+        assert_eq!(clock.fps(), 1.0);
+
+        // Shorter frames are left alone.
+        assert_eq!(clock.record(0.015625), 0.015625);
+        assert_eq!(clock.raw_delta(), 0.015625);
+    }
+
+    #[test]
+    fn smoothing_averages_the_last_frames() {
+        let mut clock = FrameClock::new(0.25);
+        clock.set_smoothing(4);
+
+        // The average is over the frames recorded so far until there are
+        // enough of them.
+        assert_eq!(clock.record(0.0625), 0.0625);
+        assert_eq!(clock.record(0.125), 0.09375);
+        assert_eq!(clock.record(0.0625), 0.08333333333333333);
+        assert_eq!(clock.record(0.125), 0.09375);
+
+        // Then the oldest frame is dropped, and a long frame is clamped before
+        // it is averaged, so that it only counts for a quarter of `max_delta`.
+        assert_eq!(clock.record(2.0), (0.125 + 0.0625 + 0.125 + 0.25) / 4.0);
+        assert_eq!(clock.raw_delta(), 2.0);
+    }
+
+    #[test]
+    fn reducing_the_smoothing_forgets_the_oldest_frames() {
+        let mut clock = FrameClock::new(1.0);
+        clock.set_smoothing(3);
+        clock.record(0.5);
+        clock.record(0.25);
+        clock.record(0.25);
+
+        clock.set_smoothing(0);
+        assert_eq!(clock.record(0.125), 0.125);
+    }
+
+    #[test]
+    fn frame_rate_covers_about_the_last_second() {
+        let mut clock = FrameClock::new(1.0);
+        assert_eq!(clock.fps(), 0.0);
+
+        for _ in 0..120 {
+            clock.record(1.0 / 60.0);
+        }
+
+        assert!((clock.fps() - 60.0).abs() < 1e-6, "{} FPS", clock.fps());
+    }
+}
@@ -0,0 +1,284 @@
+use ::phi::{Phi, View, ViewAction};
+use ::phi::data::Rectangle;
+use ::phi::tween::Ease;
+use ::sdl2::pixels::Color;
+use ::sdl2::render::{BlendMode, Texture};
+
+
+/// An effect which is shown while going from one view to the next.
+pub trait Transition {
+    /// The time it takes for the transition to complete, in seconds.
+    fn duration(&self) -> f64;
+
+    /// Renders the transition at progress `t`, which goes from 0.0 when it
+    /// starts to 1.0 when it is done. Returns `true` once the transition is
+    /// complete and the `to` view can take over.
+    ///
+    /// The views are not updated while the transition is running, so that
+    /// they do not react to the player's input.
+    fn render_transition(&mut self, from: &mut dyn View, to: &mut dyn View, context: &mut Phi, t: f64) -> bool;
+
+    /// Called when the window is resized during the transition, so that the
+    /// textures rendered at the previous size can be dropped, and rendered
+    /// again at the new one.
+    fn invalidate(&mut self) {}
+}
+
+
+/// A cross-fade from the last frame of the previous view to the new one.
+pub struct FadeTransition {
+    pub duration_s: f64,
+
+    /// The frame of the previous view, rendered offscreen when the transition
+    /// starts.
+    snapshot: Option<Texture>,
+}
+
+impl FadeTransition {
+    pub fn new(duration_s: f64) -> FadeTransition {
+        FadeTransition {
+            duration_s: duration_s,
+            snapshot: None,
+        }
+    }
+
+}
+
+impl Transition for FadeTransition {
+    fn duration(&self) -> f64 {
+        self.duration_s
+    }
+
+    fn render_transition(&mut self, from: &mut dyn View, to: &mut dyn View, context: &mut Phi, t: f64) -> bool {
+        if self.snapshot.is_none() {
+            self.snapshot = render_offscreen(from, context);
+        }
+
+        to.render(context, 0.0);
+
+        // If we could not get a snapshot of the previous view, then we simply
+        // show the new one for the duration of the transition.
+        if let Some(ref mut snapshot) = self.snapshot {
+            snapshot.set_blend_mode(BlendMode::Blend);
+            snapshot.set_alpha_mod(((1.0 - t) * 255.0) as u8);
+            context.renderer.copy(snapshot, None, None);
+        }
+
+        t >= 1.0
+    }
+
+    fn invalidate(&mut self) {
+        self.snapshot = None;
+    }
+}
+
+
+/// Fades the previous view to black during the first half of the transition,
+/// then fades the new one in during the second half.
+pub struct FadeToBlackTransition {
+    pub duration_s: f64,
+    snapshot: Option<Texture>,
+}
+
+impl FadeToBlackTransition {
+    pub fn new(duration_s: f64) -> FadeToBlackTransition {
+        FadeToBlackTransition {
+            duration_s: duration_s,
+            snapshot: None,
+        }
+    }
+}
+
+impl Transition for FadeToBlackTransition {
+    fn duration(&self) -> f64 {
+        self.duration_s
+    }
+
+    fn render_transition(&mut self, from: &mut dyn View, to: &mut dyn View, context: &mut Phi, t: f64) -> bool {
+        // The opacity of the black overlay
+        let alpha =
+            if t < 0.5 {
+                if self.snapshot.is_none() {
+                    self.snapshot = render_offscreen(from, context);
+                }
+
+                match self.snapshot {
+                    Some(ref snapshot) => context.renderer.copy(snapshot, None, None),
+                    None => { from.render(context, 0.0); },
+                }
+
+                t * 2.0
+            } else {
+                to.render(context, 0.0);
+                (1.0 - t) * 2.0
+            };
+
+        let (w, h) = context.output_size();
+        let blend_mode = context.renderer.blend_mode();
+        context.renderer.set_blend_mode(BlendMode::Blend);
+        context.renderer.set_draw_color(Color::RGBA(0, 0, 0, (alpha * 255.0) as u8));
+        // There is nothing to cover while the window is minimized.
+        if let Some(screen) = (Rectangle { x: 0.0, y: 0.0, w: w, h: h }).to_sdl() {
+            context.renderer.fill_rect(screen);
+        }
+        context.renderer.set_blend_mode(blend_mode);
+
+        t >= 1.0
+    }
+
+    fn invalidate(&mut self) {
+        self.snapshot = None;
+    }
+}
+
+
+/// Pushes the previous view out to the left of the screen while the new one
+/// comes in from the right.
+pub struct SlideLeftTransition {
+    pub duration_s: f64,
+    snapshot: Option<Texture>,
+}
+
+impl SlideLeftTransition {
+    pub fn new(duration_s: f64) -> SlideLeftTransition {
+        SlideLeftTransition {
+            duration_s: duration_s,
+            snapshot: None,
+        }
+    }
+}
+
+impl Transition for SlideLeftTransition {
+    fn duration(&self) -> f64 {
+        self.duration_s
+    }
+
+    fn render_transition(&mut self, from: &mut dyn View, to: &mut dyn View, context: &mut Phi, t: f64) -> bool {
+        if self.snapshot.is_none() {
+            self.snapshot = render_offscreen(from, context);
+        }
+
+        // The new view is rendered anew on every frame, at the current size.
+        let incoming = render_offscreen(to, context);
+
+        let (snapshot, incoming) = match (self.snapshot.as_ref(), incoming) {
+            (Some(snapshot), Some(incoming)) => (snapshot, incoming),
+            // Without render targets, the new view is simply shown.
+            _ => {
+                to.render(context, 0.0);
+                return t >= 1.0;
+            },
+        };
+
+        let (w, h) = context.renderer.output_size().unwrap();
+        let (w, h) = (w as f64, h as f64);
+        let offset = Ease::QuadInOut.apply(t) * w;
+
+        context.renderer.copy(snapshot, None, Rectangle { x: -offset, y: 0.0, w: w, h: h }.to_sdl());
+        context.renderer.copy(&incoming, None, Rectangle { x: w - offset, y: 0.0, w: w, h: h }.to_sdl());
+
+        t >= 1.0
+    }
+
+    fn invalidate(&mut self) {
+        self.snapshot = None;
+    }
+}
+
+
+/// Renders `view` to a new texture rather than to the window. Returns `None`
+/// if the platform does not support render targets.
+fn render_offscreen(view: &mut dyn View, context: &mut Phi) -> Option<Texture> {
+    let (w, h) = context.renderer.output_size().unwrap();
+    context.render_to_texture(w, h, |context| view.render(context, 0.0)).ok()
+}
+
+
+/// The view which is rendered by the game loop while a transition is running.
+/// Once the transition is complete, the previous view is dropped and every
+/// call is forwarded to the new one, so that it does not have to be entered
+/// a second time.
+pub struct TransitionView {
+    from: Option<Box<dyn View>>,
+    to: Box<dyn View>,
+    transition: Box<dyn Transition>,
+    elapsed: f64,
+}
+
+impl TransitionView {
+    pub fn new(from: Box<dyn View>, to: Box<dyn View>, transition: Box<dyn Transition>) -> TransitionView {
+        TransitionView {
+            from: Some(from),
+            to: to,
+            transition: transition,
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl View for TransitionView {
+    fn on_enter(&mut self, context: &mut Phi) {
+        self.to.on_enter(context);
+    }
+
+    fn on_exit(&mut self, context: &mut Phi) {
+        if let Some(mut from) = self.from.take() {
+            from.on_exit(context);
+        }
+
+        self.to.on_exit(context);
+    }
+
+    fn pause(&mut self, context: &mut Phi) {
+        self.to.pause(context);
+    }
+
+    fn resume(&mut self, context: &mut Phi) {
+        self.to.resume(context);
+    }
+
+    fn on_resize(&mut self, context: &mut Phi, new_w: u32, new_h: u32) {
+        if let Some(ref mut from) = self.from {
+            from.on_resize(context, new_w, new_h);
+            self.transition.invalidate();
+        }
+
+        self.to.on_resize(context, new_w, new_h);
+    }
+
+    fn render_background(&mut self, context: &mut Phi) {
+        self.to.render_background(context);
+    }
+
+    fn update(&mut self, context: &mut Phi, dt: f64) -> ViewAction {
+        if self.from.is_none() {
+            return self.to.update(context, dt);
+        }
+
+        if context.events.now.quit {
+            return ViewAction::Quit(0);
+        }
+
+        self.elapsed += dt;
+        ViewAction::None
+    }
+
+    fn render(&mut self, context: &mut Phi, interpolation: f64) {
+        let done = match self.from {
+            None => return self.to.render(context, interpolation),
+
+            Some(ref mut from) => {
+                let duration = self.transition.duration();
+                let t =
+                    if duration <= 0.0 { 1.0 }
+                    else { (self.elapsed / duration).min(1.0) };
+
+                self.transition.render_transition(&mut **from, &mut *self.to, context, t)
+            },
+        };
+
+        if done {
+            self.from.take().unwrap().on_exit(context);
+        }
+    }
+}
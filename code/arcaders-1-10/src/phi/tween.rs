@@ -0,0 +1,316 @@
+use ::phi::data::Rectangle;
+use ::sdl2::pixels::Color;
+
+
+/// How the progress of a tween is distributed over its duration.
+#[derive(Clone, Copy, Debug)]
+pub enum Ease {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    /// Overshoots the target and bounces around it before settling.
+    ElasticOut,
+    /// Falls onto the target and bounces off it a few times, like a ball.
+    BounceOut,
+    /// Any other easing. The function is given the fraction of the duration
+    /// which has elapsed, strictly between 0.0 and 1.0, and should return 0.0
+    /// and 1.0 at the ends for the tween to be continuous.
+    Custom(fn(f64) -> f64),
+}
+
+impl Ease {
+    /// Maps the fraction of the duration which has elapsed, in [0.0, 1.0], to
+    /// the fraction of the way from the start to the end. It is exactly 0.0
+    /// at the start and 1.0 at the end, whatever the easing, but may leave
+    /// [0.0, 1.0] in-between.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.max(0.0).min(1.0);
+
+        if t == 0.0 || t == 1.0 {
+            return t;
+        }
+
+        match self {
+            Ease::Linear => t,
+            Ease::QuadIn => t * t,
+            Ease::QuadOut => t * (2.0 - t),
+            Ease::QuadInOut =>
+                if t < 0.5 { 2.0 * t * t }
+                else { 1.0 - 2.0 * (1.0 - t) * (1.0 - t) },
+            Ease::CubicIn => t * t * t,
+            Ease::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Ease::CubicInOut =>
+                if t < 0.5 { 4.0 * t * t * t }
+                else { 1.0 - 4.0 * (1.0 - t).powi(3) },
+            Ease::ElasticOut => {
+                let period = 0.3;
+                2.0f64.powf(-10.0 * t)
+                    * ((t - period / 4.0) * 2.0 * ::std::f64::consts::PI / period).sin()
+                    + 1.0
+            },
+            Ease::BounceOut => {
+                // Four parabolas, each bounce lower and shorter than the last.
+                let (n, d) = (7.5625, 2.75);
+                if t < 1.0 / d {
+                    n * t * t
+                } else if t < 2.0 / d {
+                    let t = t - 1.5 / d;
+                    n * t * t + 0.75
+                } else if t < 2.5 / d {
+                    let t = t - 2.25 / d;
+                    n * t * t + 0.9375
+                } else {
+                    let t = t - 2.625 / d;
+                    n * t * t + 0.984375
+                }
+            },
+            Ease::Custom(ease) => ease(t),
+        }
+    }
+}
+
+
+/// A value which can be interpolated between two others.
+pub trait Lerp: Copy {
+    /// Returns the value at fraction `t` of the way from `self` to `to`. `t`
+    /// may leave [0.0, 1.0], for instance with `Ease::ElasticOut`.
+    fn lerp(self, to: Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(self, to: f64, t: f64) -> f64 {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for f32 {
+    fn lerp(self, to: f32, t: f64) -> f32 {
+        (self as f64).lerp(to as f64, t) as f32
+    }
+}
+
+impl Lerp for (f64, f64) {
+    fn lerp(self, to: (f64, f64), t: f64) -> (f64, f64) {
+        (self.0.lerp(to.0, t), self.1.lerp(to.1, t))
+    }
+}
+
+impl Lerp for Rectangle {
+    fn lerp(self, to: Rectangle, t: f64) -> Rectangle {
+        Rectangle {
+            x: self.x.lerp(to.x, t),
+            y: self.y.lerp(to.y, t),
+            w: self.w.lerp(to.w, t),
+            h: self.h.lerp(to.h, t),
+        }
+    }
+}
+
+impl Lerp for Color {
+    /// Interpolates every component separately, including the alpha, clamping
+    /// them to [0, 255] when the easing overshoots.
+    fn lerp(self, to: Color, t: f64) -> Color {
+        let component = |from: u8, to: u8| {
+            (from as f64).lerp(to as f64, t).round().max(0.0).min(255.0) as u8
+        };
+
+        let rgba = |color: Color| match color {
+            Color::RGB(r, g, b) => (r, g, b, 255),
+            Color::RGBA(r, g, b, a) => (r, g, b, a),
+        };
+
+        let (r1, g1, b1, a1) = rgba(self);
+        let (r2, g2, b2, a2) = rgba(to);
+        Color::RGBA(component(r1, r2), component(g1, g2), component(b1, b2), component(a1, a2))
+    }
+}
+
+
+/// Goes from one value to another over a given duration, for instance to make
+/// a menu slide in, or a health bar go down gradually.
+#[derive(Clone, Copy, Debug)]
+pub struct Tween<T: Lerp = f64> {
+    pub from: T,
+    pub to: T,
+    /// In seconds.
+    pub duration: f64,
+    pub ease: Ease,
+    elapsed: f64,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(from: T, to: T, duration: f64, ease: Ease) -> Tween<T> {
+        Tween {
+            from: from,
+            to: to,
+            duration: duration,
+            ease: ease,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Advances the tween by `dt` seconds and returns its new value.
+    pub fn update(&mut self, dt: f64) -> T {
+        self.elapsed = (self.elapsed + dt).min(self.duration.max(0.0));
+        self.value()
+    }
+
+    /// The current value. It is exactly `from` at the start, and exactly
+    /// `to` once the tween is done.
+    pub fn value(&self) -> T {
+        if self.is_done() {
+            return self.to;
+        }
+
+        let t = self.ease.apply(self.elapsed / self.duration);
+        if t == 0.0 { self.from } else { self.from.lerp(self.to, t) }
+    }
+
+    /// The fraction of the duration which has elapsed, in [0.0, 1.0].
+    pub fn progress(&self) -> f64 {
+        if self.duration > 0.0 { self.elapsed / self.duration } else { 1.0 }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// Starts the tween over, going from its current value to `to`, so that
+    /// a moving cursor can change its destination without jumping.
+    pub fn retarget(&mut self, to: T) {
+        self.from = self.value();
+        self.to = to;
+        self.elapsed = 0.0;
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Does not end at 1.0, which `apply` should hide at the ends.
+    fn half(t: f64) -> f64 {
+        t * 0.5
+    }
+
+    fn eases() -> Vec<Ease> {
+        vec![Ease::Linear, Ease::QuadIn, Ease::QuadOut, Ease::QuadInOut,
+             Ease::CubicIn, Ease::CubicOut, Ease::CubicInOut,
+             Ease::ElasticOut, Ease::BounceOut, Ease::Custom(half)]
+    }
+
+    #[test]
+    fn eases_start_at_zero_and_end_at_one() {
+        for ease in eases() {
+            assert_eq!(ease.apply(0.0), 0.0, "{:?}", ease);
+            assert_eq!(ease.apply(1.0), 1.0, "{:?}", ease);
+        }
+    }
+
+    #[test]
+    fn eases_clamp_the_elapsed_fraction() {
+        for ease in eases() {
+            assert_eq!(ease.apply(-0.5), 0.0, "{:?}", ease);
+            assert_eq!(ease.apply(1.5), 1.0, "{:?}", ease);
+        }
+    }
+
+    #[test]
+    fn eases_are_continuous_at_the_ends() {
+        for ease in eases() {
+            if let Ease::Custom(_) = ease { continue; }
+
+            // The elastic oscillation has not quite died out when it ends.
+
+            assert!(ease.apply(1e-9).abs() < 1e-3, "{:?}", ease);
+            assert!((ease.apply(1.0 - 1e-9) - 1.0).abs() < 1e-3, "{:?}", ease);
+        }
+    }
+
+    #[test]
+    fn in_out_eases_are_halfway_at_the_middle() {
+        assert_eq!(Ease::Linear.apply(0.5), 0.5);
+        assert_eq!(Ease::QuadInOut.apply(0.5), 0.5);
+        assert_eq!(Ease::CubicInOut.apply(0.5), 0.5);
+    }
+
+    #[test]
+    fn bounce_out_stays_below_the_target() {
+        for i in 0..1000 {
+            let t = i as f64 / 1000.0;
+            let value = Ease::BounceOut.apply(t);
+            assert!(value >= 0.0 && value <= 1.0, "{} at {}", value, t);
+        }
+    }
+
+    #[test]
+    fn tween_is_exact_at_both_ends() {
+        for ease in eases() {
+            let mut tween = Tween::new(0.1, 0.7, 0.3, ease);
+            assert_eq!(tween.value(), 0.1);
+            assert_eq!(tween.update(0.1 + 0.2), 0.7);
+            assert!(tween.is_done());
+        }
+    }
+
+    #[test]
+    fn tween_of_pairs_is_exact_at_both_ends() {
+        let mut tween = Tween::new((0.1, -3.3), (0.7, 1.0 / 3.0), 0.5, Ease::ElasticOut);
+        assert_eq!(tween.value(), (0.1, -3.3));
+
+        tween.update(0.25);
+        tween.update(0.25);
+        assert_eq!(tween.value(), (0.7, 1.0 / 3.0));
+    }
+
+    #[test]
+    fn tween_of_colors_is_exact_at_both_ends() {
+        let from = Color::RGB(10, 20, 30);
+        let to = Color::RGBA(200, 100, 0, 128);
+
+        let mut tween = Tween::new(from, to, 1.0, Ease::BounceOut);
+        assert_eq!(tween.value(), from);
+        assert_eq!(tween.update(1.0), to);
+
+        let mut tween = Tween::new(from, to, 1.0, Ease::QuadInOut);
+        assert_eq!(tween.update(0.5), Color::RGBA(105, 60, 15, 192));
+        assert_eq!(tween.update(0.75), to);
+    }
+
+    #[test]
+    fn tween_overshooting_a_color_clamps_it() {
+        let tween = Tween {
+            elapsed: 0.1,
+            ..Tween::new(Color::RGB(0, 0, 0), Color::RGB(255, 255, 255), 1.0, Ease::ElasticOut)
+        };
+
+        // The elastic easing is past 1.0 a tenth of the way in.
+        assert!(Ease::ElasticOut.apply(0.1) > 1.0);
+        assert_eq!(tween.value(), Color::RGBA(255, 255, 255, 255));
+    }
+
+    #[test]
+    fn empty_tween_is_done_right_away() {
+        let tween = Tween::new(1.0, 2.0, 0.0, Ease::Linear);
+
+        assert!(tween.is_done());
+        assert_eq!(tween.value(), 2.0);
+        assert_eq!(tween.progress(), 1.0);
+    }
+
+    #[test]
+    fn retarget_starts_from_the_current_value() {
+        let mut tween = Tween::new(0.0, 1.0, 1.0, Ease::Linear);
+        tween.update(0.5);
+        tween.retarget(2.0);
+
+        assert_eq!(tween.value(), 0.5);
+        assert_eq!(tween.update(1.0), 2.0);
+    }
+}
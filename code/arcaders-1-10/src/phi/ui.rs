@@ -0,0 +1,179 @@
+use ::phi::{Phi, ViewAction};
+use ::phi::data::Rectangle;
+use ::phi::gfx::CopySprite;
+use ::sdl2::pixels::Color;
+
+
+/// A region of the screen which does something when it is clicked, such as an
+/// entry of a menu. How it looks is left to a closure, which is told whether
+/// the button is hovered, so that it can stand out.
+pub struct Button {
+    /// Where the button is on the screen.
+    pub rect: Rectangle,
+    on_click: Box<dyn FnMut(&mut Phi) -> ViewAction>,
+    draw: Box<dyn Fn(&mut Phi, Rectangle, bool)>,
+    hovered: bool,
+}
+
+impl Button {
+    /// Creates a button covering `rect`, which is drawn by calling `draw` with
+    /// its rectangle and whether it is hovered, and which calls `on_click`
+    /// when it is clicked, to tell the view what to do next.
+    pub fn new(rect: Rectangle,
+               on_click: Box<dyn FnMut(&mut Phi) -> ViewAction>,
+               draw: Box<dyn Fn(&mut Phi, Rectangle, bool)>) -> Button {
+        Button {
+            rect: rect,
+            on_click: on_click,
+            draw: draw,
+            hovered: false,
+        }
+    }
+
+    /// Creates a button showing `label` at the center of `rect`, in the given
+    /// font and color, and in white, and with a frame, when it is hovered.
+    /// Returns `None` if the font cannot be loaded.
+    pub fn text(phi: &mut Phi, rect: Rectangle, label: &str, font: &str, size: u16, color: Color,
+                on_click: Box<dyn FnMut(&mut Phi) -> ViewAction>) -> Option<Button> {
+        let idle = match phi.ttf_str_sprite(label, font, size, color) {
+            Some(sprite) => sprite,
+            None => return None,
+        };
+        let hover = match phi.ttf_str_sprite(label, font, size, Color::RGB(255, 255, 255)) {
+            Some(sprite) => sprite,
+            None => return None,
+        };
+
+        Some(Button::new(rect, on_click, Box::new(move |phi, rect, hovered| {
+            let sprite = if hovered { &hover } else { &idle };
+            let (w, h) = sprite.size();
+
+            phi.renderer.copy_sprite(sprite, Rectangle {
+                x: rect.x + (rect.w - w) / 2.0,
+                y: rect.y + (rect.h - h) / 2.0,
+                w: w,
+                h: h,
+            });
+
+            if hovered {
+                if let Some(frame) = rect.to_sdl() {
+                    phi.renderer.set_draw_color(Color::RGB(255, 255, 255));
+                    phi.renderer.draw_rect(frame);
+                }
+            }
+        })))
+    }
+
+    /// Whether the mouse cursor was over the button during the last update.
+    pub fn is_hovered(&self) -> bool {
+        self.hovered
+    }
+
+    /// Checks whether the mouse cursor is over the button, and calls its
+    /// action if it was clicked during this frame.
+    pub fn update(&mut self, phi: &mut Phi) -> ViewAction {
+        self.hovered = self.rect.contains_point(phi.events.now.mouse_pos);
+
+        if self.hovered && phi.events.now.mouse_left_clicked() {
+            self.click(phi)
+        } else {
+            ViewAction::None
+        }
+    }
+
+    /// Calls the action of the button, as if it were clicked.
+    pub fn click(&mut self, phi: &mut Phi) -> ViewAction {
+        (self.on_click)(phi)
+    }
+
+    /// Draws the button, as hovered if `hovered` is true, for instance because
+    /// it has the keyboard focus.
+    pub fn render(&self, phi: &mut Phi, hovered: bool) {
+        (self.draw)(phi, self.rect, hovered);
+    }
+}
+
+
+/// Buttons of which one has the focus: the arrow keys move it up and down,
+/// wrapping around, enter clicks the focused button, and moving the mouse over
+/// a button focuses it.
+pub struct ButtonGroup {
+    buttons: Vec<Button>,
+    focused: usize,
+    /// Where the mouse cursor was during the last update, so that the focus
+    /// only follows it when it moves, and not while the keyboard is used.
+    mouse_pos: (i32, i32),
+}
+
+impl ButtonGroup {
+    /// Creates a group, with the first of `buttons` focused.
+    pub fn new(buttons: Vec<Button>) -> ButtonGroup {
+        ButtonGroup {
+            buttons: buttons,
+            focused: 0,
+            mouse_pos: (0, 0),
+        }
+    }
+
+    pub fn push(&mut self, button: Button) {
+        self.buttons.push(button);
+    }
+
+    pub fn buttons(&self) -> &[Button] {
+        &self.buttons
+    }
+
+    /// The index of the focused button.
+    pub fn focused(&self) -> usize {
+        self.focused
+    }
+
+    pub fn set_focused(&mut self, index: usize) {
+        if index < self.buttons.len() {
+            self.focused = index;
+        }
+    }
+
+    /// Moves the focus, and calls the action of the button which was clicked,
+    /// or which has the focus when enter is pressed, if any.
+    pub fn update(&mut self, phi: &mut Phi) -> ViewAction {
+        if self.buttons.is_empty() {
+            return ViewAction::None;
+        }
+
+        let count = self.buttons.len();
+        if phi.events.now.key_up == Some(true) {
+            self.focused = (self.focused + count - 1) % count;
+        }
+        if phi.events.now.key_down == Some(true) {
+            self.focused = (self.focused + 1) % count;
+        }
+
+        let mouse_moved = phi.events.now.mouse_pos != self.mouse_pos;
+        self.mouse_pos = phi.events.now.mouse_pos;
+
+        for (index, button) in self.buttons.iter_mut().enumerate() {
+            match button.update(phi) {
+                ViewAction::None => {},
+                action => return action,
+            }
+
+            if mouse_moved && button.is_hovered() {
+                self.focused = index;
+            }
+        }
+
+        if phi.events.now.key_enter == Some(true) {
+            return self.buttons[self.focused].click(phi);
+        }
+
+        ViewAction::None
+    }
+
+    /// Draws every button, the focused one as hovered.
+    pub fn render(&self, phi: &mut Phi) {
+        for (index, button) in self.buttons.iter().enumerate() {
+            button.render(phi, index == self.focused);
+        }
+    }
+}
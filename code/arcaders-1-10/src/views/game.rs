@@ -1,16 +1,30 @@
 use ::phi::{Phi, View, ViewAction};
-use ::phi::data::Rectangle;
-use ::phi::gfx::{AnimatedSprite, CopySprite, Sprite};
+use ::phi::camera::Camera;
+use ::phi::collisions::Collidable;
+use ::phi::data::{Circle, QuadTree, Rectangle};
+use ::phi::data::scores::ScoreBoard;
+use ::phi::fx::ScreenShake;
+use ::phi::gfx::{AnimatedSprite, AnimatedSpriteDescr, Background, CopySprite, Emitter, EmitterConfig,
+                 EmitterShape, ParticleSystem, Sprite};
+use ::phi::input::Action;
+use ::phi::pool::Pool;
+use ::phi::save::Serializable;
+use ::phi::timer::Cooldown;
 use ::sdl2::pixels::Color;
+use ::rustc_serialize::json::Json;
 use ::sdl2::render::Renderer;
-use ::views::shared::BgSet;
+use ::std::collections::BTreeMap;
+use ::std::path::PathBuf;
+use ::views::scores::{ScoreView, scores_path};
+use ::views::shared::{PLAYFIELD_Z, starfield};
+use ::views::text_prompt::TextPromptView;
 
 
 /// Pixels traveled by the player's ship every second, when it is moving.
 const PLAYER_SPEED: f64 = 180.0;
 
-const SHIP_W: f64 = 43.0;
-const SHIP_H: f64 = 39.0;
+pub const SHIP_W: f64 = 43.0;
+pub const SHIP_H: f64 = 39.0;
 
 const ASTEROID_PATH: &'static str = "assets/asteroid.png";
 const ASTEROIDS_WIDE: usize = 21;
@@ -18,7 +32,35 @@ const ASTEROIDS_HIGH: usize = 7;
 const ASTEROIDS_TOTAL: usize = ASTEROIDS_WIDE * ASTEROIDS_HIGH - 4;
 const ASTEROID_SIDE: f64 = 96.0;
 
-const DEBUG: bool = false;
+/// Pixels traveled by a bullet every second.
+const BULLET_SPEED: f64 = 240.0;
+const BULLET_W: f64 = 8.0;
+const BULLET_H: f64 = 4.0;
+/// The number of bullets which may be on the screen at the same time. Once it
+/// is reached, the ship cannot fire until one of them leaves the screen.
+const MAX_BULLETS: usize = 64;
+/// The time between two shots while the fire key is held, in seconds.
+const FIRE_COOLDOWN: f64 = 0.2;
+
+/// The sound played when the ship fires, and when the asteroid is destroyed.
+/// See `load_sounds`.
+const LASER_SOUND: (&'static str, &'static str) = ("laser", "assets/laser.wav");
+const EXPLOSION_SOUND: (&'static str, &'static str) = ("explosion", "assets/explosion.wav");
+
+/// The points earned by destroying an asteroid.
+const ASTEROID_POINTS: u64 = 10;
+/// The longest name which may be entered for the high scores, in characters.
+const MAX_NAME_LEN: usize = 16;
+
+/// How hard, in pixels, and how long, in seconds, the screen shakes when the
+/// asteroid is destroyed.
+const EXPLOSION_SHAKE: f64 = 8.0;
+const EXPLOSION_SHAKE_DURATION: f64 = 0.4;
+/// The number of sparks thrown where a bullet hits the asteroid.
+const EXPLOSION_PARTICLES: usize = 48;
+/// The number of particles leaving the thruster every second, while the ship
+/// speeds up.
+const EXHAUST_RATE: f64 = 120.0;
 
 
 /// The different states our ship might be in. In the image, they're ordered
@@ -44,6 +86,12 @@ struct Ship {
 }
 
 
+#[derive(Clone, Copy)]
+struct Bullet {
+    rect: Rectangle,
+}
+
+
 struct Asteroid {
     sprite: AnimatedSprite,
     rect: Rectangle,
@@ -89,24 +137,16 @@ impl Asteroid {
     }
 
     fn get_sprite(phi: &mut Phi, fps: f64) -> AnimatedSprite {
-        let asteroid_spritesheet = Sprite::load(&mut phi.renderer, ASTEROID_PATH).unwrap();
-        let mut asteroid_sprites = Vec::with_capacity(ASTEROIDS_TOTAL);
-
-        for yth in 0..ASTEROIDS_HIGH {
-            for xth in 0..ASTEROIDS_WIDE {
-                if ASTEROIDS_WIDE * yth + xth >= ASTEROIDS_TOTAL {
-                    break;
-                }
-
-                asteroid_sprites.push(
-                    asteroid_spritesheet.region(Rectangle {
-                        w: ASTEROID_SIDE,
-                        h: ASTEROID_SIDE,
-                        x: ASTEROID_SIDE * xth as f64,
-                        y: ASTEROID_SIDE * yth as f64,
-                    }).unwrap());
-            }
-        }
+        let asteroid_sprites = AnimatedSprite::load_frames(phi, AnimatedSpriteDescr {
+            image_path: ASTEROID_PATH,
+            total_frames: ASTEROIDS_TOTAL,
+            frames_wide: ASTEROIDS_WIDE,
+            frames_high: ASTEROIDS_HIGH,
+            frame_w: ASTEROID_SIDE,
+            frame_h: ASTEROID_SIDE,
+            margin: 0.0,
+            spacing: 0.0,
+        });
 
         AnimatedSprite::with_fps(asteroid_sprites, fps)
     }
@@ -121,16 +161,125 @@ impl Asteroid {
         }
     }
 
-    fn render(&mut self, phi: &mut Phi) {
-        phi.renderer.copy_sprite(&self.sprite, self.rect);
+    fn render(&self, phi: &mut Phi, camera: &Camera) {
+        phi.renderer.copy_sprite_world(&self.sprite, camera, self.rect);
     }
 }
 
 
+/// What is needed to resume a game where the player left it. Only the logical
+/// data is kept; the sprites are loaded again when the game is resumed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameState {
+    /// The position of the ship.
+    pub ship: (f64, f64),
+    pub score: u64,
+    /// The position of the asteroid, its speed, and the speed of its
+    /// animation.
+    pub asteroid: (f64, f64),
+    pub asteroid_vel: f64,
+    pub asteroid_fps: f64,
+    /// The positions of the bullets on the screen.
+    pub bullets: Vec<(f64, f64)>,
+}
+
+impl Serializable for GameState {
+    fn to_bytes(&self) -> Vec<u8> {
+        let point = |(x, y): (f64, f64)| Json::Array(vec![Json::F64(x), Json::F64(y)]);
+
+        let mut object = BTreeMap::new();
+        object.insert("ship".to_string(), point(self.ship));
+        object.insert("score".to_string(), Json::U64(self.score));
+        object.insert("asteroid".to_string(), point(self.asteroid));
+        object.insert("asteroid_vel".to_string(), Json::F64(self.asteroid_vel));
+        object.insert("asteroid_fps".to_string(), Json::F64(self.asteroid_fps));
+        object.insert("bullets".to_string(),
+                      Json::Array(self.bullets.iter().cloned().map(point).collect()));
+
+        Json::Object(object).to_string().into_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<GameState, String> {
+        let source = ::std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+        let json = Json::from_str(source).map_err(|e| e.to_string())?;
+
+        let number = |json: &Json, key: &str| {
+            json.find(key).and_then(|value| value.as_f64())
+                .ok_or(format!("missing {}", key))
+        };
+
+        let point = |json: &Json| match json.as_array() {
+            Some(coords) if coords.len() == 2 =>
+                match (coords[0].as_f64(), coords[1].as_f64()) {
+                    (Some(x), Some(y)) => Ok((x, y)),
+                    _ => Err("invalid position".to_string()),
+                },
+            _ => Err("invalid position".to_string()),
+        };
+
+        let field = |key: &str| json.find(key).ok_or(format!("missing {}", key));
+
+        let bullets = field("bullets")?.as_array()
+            .ok_or("invalid bullets".to_string())?;
+
+        Ok(GameState {
+            ship: point(field("ship")?)?,
+            score: field("score")?.as_u64().ok_or("invalid score".to_string())?,
+            asteroid: point(field("asteroid")?)?,
+            asteroid_vel: number(&json, "asteroid_vel")?,
+            asteroid_fps: number(&json, "asteroid_fps")?,
+            bullets: bullets.iter().map(point).collect::<Result<_, String>>()?,
+        })
+    }
+}
+
+
+/// Loads the sound effects of the game into `phi.audio`, so that the views can
+/// play them by name. It only needs to be called once.
+pub fn load_sounds(phi: &mut Phi) {
+    for &(name, path) in &[LASER_SOUND, EXPLOSION_SOUND] {
+        // Without the file, the game simply plays without this sound.
+        let _ = phi.audio.load(name, path);
+    }
+}
+
+
+/// The key under which the game in progress is saved when the player quits,
+/// to be resumed from the main menu. See `Phi::save`.
+pub const SAVED_GAME: &'static str = "savegame.json";
+
+/// Where the game in progress is saved, in the save directory of `phi`.
+pub fn saved_game_path(phi: &Phi) -> PathBuf {
+    phi.save_dir.join(SAVED_GAME)
+}
+
+
 pub struct ShipView {
     player: Ship,
     asteroid: Asteroid,
-    bg: BgSet,
+    bullets: Pool<Bullet>,
+    fire_cooldown: Cooldown,
+    /// The obstacles which the bullets may hit, indexed anew on every update.
+    obstacles: QuadTree<usize>,
+    shake: ScreenShake,
+    particles: ParticleSystem,
+    exhaust: Emitter,
+    score: u64,
+    /// Whether the player ended the game, in which case it is not saved when
+    /// the view exits.
+    finished: bool,
+    pausing: Pausing,
+    bg: Background,
+}
+
+/// How far the game is from showing the pause menu. The frame which the menu
+/// shows under itself is captured once it has been rendered as usual.
+enum Pausing {
+    No,
+    /// The player pressed P, and the game no longer moves.
+    Requested,
+    /// The frozen game was rendered, and captured if possible.
+    Captured(Option<Sprite>),
 }
 
 impl ShipView {
@@ -138,13 +287,13 @@ impl ShipView {
     /// `main` while developing it further.
     #[allow(dead_code)]
     pub fn new(phi: &mut Phi) -> ShipView {
-        let bg = BgSet::new(&mut phi.renderer);
+        let bg = starfield(phi);
         ShipView::with_backgrounds(phi, bg)
     }
 
-    pub fn with_backgrounds(phi: &mut Phi, bg: BgSet) -> ShipView {
+    pub fn with_backgrounds(phi: &mut Phi, bg: Background) -> ShipView {
         // Get the spaceship's sprites
-        let spritesheet = Sprite::load(&mut phi.renderer, "assets/spaceship.png").unwrap();
+        let spritesheet = phi.sprite("assets/spaceship.png").unwrap();
         let mut sprites = Vec::with_capacity(9);
 
         for y in 0..3 {
@@ -172,46 +321,178 @@ impl ShipView {
 
             asteroid: Asteroid::new(phi),
 
+            bullets: Pool::new(MAX_BULLETS, Bullet {
+                rect: Rectangle { x: 0.0, y: 0.0, w: BULLET_W, h: BULLET_H },
+            }),
+
+            fire_cooldown: Cooldown::new(FIRE_COOLDOWN),
+
+            obstacles: QuadTree::new(Rectangle {
+                x: 0.0,
+                y: 0.0,
+                w: phi.output_size().0,
+                h: phi.output_size().1,
+            }, 6, 8),
+
+            shake: ScreenShake::new(),
+            particles: ParticleSystem::new(),
+            exhaust: Emitter::new(EmitterShape::Point(0.0, 0.0), EXHAUST_RATE, EmitterConfig {
+                position: (0.0, 0.0),
+                count: 0,
+                speed: (80.0, 140.0),
+                angle: ::std::f64::consts::PI,
+                spread: 0.4,
+                lifetime: (0.1, 0.3),
+                start_color: Color::RGBA(140, 200, 255, 200),
+                end_color: Color::RGBA(60, 80, 255, 0),
+                start_size: 3.0,
+                end_size: 1.0,
+            }),
+            score: 0,
+            finished: false,
+            pausing: Pausing::No,
             bg: bg,
         }
     }
 }
 
+impl ShipView {
+    /// Resumes a game saved with `state`.
+    pub fn from_state(phi: &mut Phi, bg: Background, state: &GameState) -> ShipView {
+        let mut view = ShipView::with_backgrounds(phi, bg);
+
+        view.player.rect.x = state.ship.0;
+        view.player.rect.y = state.ship.1;
+        view.score = state.score;
+
+        view.asteroid.rect.x = state.asteroid.0;
+        view.asteroid.rect.y = state.asteroid.1;
+        view.asteroid.vel = state.asteroid_vel;
+        view.asteroid.sprite.set_fps(state.asteroid_fps);
+
+        for &(x, y) in &state.bullets {
+            if let Some(bullet) = view.bullets.acquire() {
+                bullet.rect = Rectangle { x: x, y: y, w: BULLET_W, h: BULLET_H };
+            }
+        }
+
+        view
+    }
+
+    /// The state of the game, to resume it later.
+    pub fn state(&self) -> GameState {
+        GameState {
+            ship: (self.player.rect.x, self.player.rect.y),
+            score: self.score,
+            asteroid: (self.asteroid.rect.x, self.asteroid.rect.y),
+            asteroid_vel: self.asteroid.vel,
+            asteroid_fps: self.asteroid.sprite.fps(),
+            bullets: self.bullets.iter().map(|bullet| (bullet.rect.x, bullet.rect.y)).collect(),
+        }
+    }
+}
+
 impl View for ShipView {
-    fn render(&mut self, phi: &mut Phi, elapsed: f64) -> ViewAction {
+    /// Saves the game if the player leaves it without finishing it, for
+    /// instance by closing the window, so that it can be resumed later. A
+    /// finished game cannot be resumed.
+    fn on_exit(&mut self, phi: &mut Phi) {
+        // If the game cannot be saved, then it simply cannot be resumed.
+        if self.finished {
+            let _ = ::std::fs::remove_file(saved_game_path(phi));
+        } else {
+            let _ = phi.save(SAVED_GAME, &self.state());
+        }
+    }
+
+    /// The player may come back from the high-score prompt.
+    fn resume(&mut self, _: &mut Phi) {
+        self.finished = false;
+    }
+
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
         if phi.events.now.quit {
-            return ViewAction::Quit;
+            return ViewAction::Quit(0);
+        }
+
+        // Freeze the game under the pause menu. It is not updated while it is
+        // paused, so no time passes for it. The menu is shown once `render`
+        // has captured the frozen game.
+        match ::std::mem::replace(&mut self.pausing, Pausing::No) {
+            Pausing::No => {},
+            Pausing::Requested => {
+                self.pausing = Pausing::Requested;
+                return ViewAction::None;
+            },
+            Pausing::Captured(snapshot) => {
+                return ViewAction::Push(Box::new(
+                    ::views::pause::PauseView::new(snapshot, self.bg.clone())));
+            },
+        }
+
+        if phi.events.now.key_p == Some(true) {
+            self.pausing = Pausing::Requested;
+            return ViewAction::None;
         }
 
-        if phi.events.now.key_escape == Some(true) {
-            return ViewAction::ChangeView(Box::new(
+        if phi.events.now.key_escape == Some(true) ||
+           phi.events.now.pad_back == Some(true) {
+            // Ask for the player's name if they made it to the high scores.
+            // Cancelling the prompt goes back to the game.
+            self.finished = true;
+
+            let score = self.score;
+            if score > 0 && ScoreBoard::load(&scores_path(phi)).qualifies(score) {
+                let bg = self.bg.clone();
+
+                return ViewAction::Push(Box::new(TextPromptView::new(
+                    "New high score! Enter your name:", MAX_NAME_LEN,
+                    Box::new(move |phi, name| {
+                        ViewAction::Reset(Box::new(
+                            ScoreView::with_backgrounds(phi, bg.clone(), Some((name, score)))))
+                    }))));
+            }
+
+            return ViewAction::FadeTo(Box::new(
                 ::views::main_menu::MainMenuView::with_backgrounds(
-                    phi, self.bg.clone())));
+                    phi, self.bg.clone())), 0.5);
         }
 
 
         // Move the player's ship
 
-        let diagonal =
-            (phi.events.key_up ^ phi.events.key_down) &&
-            (phi.events.key_left ^ phi.events.key_right);
+        let up = phi.events.action_down(&phi.bindings, Action::MoveUp);
+        let down = phi.events.action_down(&phi.bindings, Action::MoveDown);
+        let left = phi.events.action_down(&phi.bindings, Action::MoveLeft);
+        let right = phi.events.action_down(&phi.bindings, Action::MoveRight);
+
+        let diagonal = (up ^ down) && (left ^ right);
 
         let moved =
             if diagonal { 1.0 / 2.0f64.sqrt() }
-            else { 1.0 } * PLAYER_SPEED * elapsed;
+            else { 1.0 } * PLAYER_SPEED * dt;
 
-        let dx = match (phi.events.key_left, phi.events.key_right) {
+        let dx = match (left, right) {
             (true, true) | (false, false) => 0.0,
             (true, false) => -moved,
             (false, true) => moved,
         };
 
-        let dy = match (phi.events.key_up, phi.events.key_down) {
+        let dy = match (up, down) {
             (true, true) | (false, false) => 0.0,
             (true, false) => -moved,
             (false, true) => moved,
         };
 
+        // If no key is held, then the player may be using a controller.
+        let (dx, dy) = match phi.events.gamepad() {
+            Some(pad) if dx == 0.0 && dy == 0.0 => {
+                let (x, y) = pad.left_stick;
+                (x * PLAYER_SPEED * dt, y * PLAYER_SPEED * dt)
+            },
+            _ => (dx, dy),
+        };
+
         self.player.rect.x += dx;
         self.player.rect.y += dy;
 
@@ -229,7 +510,7 @@ impl View for ShipView {
 
         // If the player cannot fit in the screen, then there is a problem and
         // the game should be promptly aborted.
-        self.player.rect = self.player.rect.move_inside(movable_region).unwrap();
+        self.player.rect = self.player.rect.moved_inside(movable_region).unwrap();
 
 
         // Select the appropriate sprite of the ship to show.
@@ -245,37 +526,147 @@ impl View for ShipView {
             else if dx < 0.0 && dy > 0.0   { ShipFrame::DownSlow }
             else { unreachable!() };
 
+        // The thruster, along the back of the ship, fires while it goes
+        // forward.
+        if dx > 0.0 {
+            let ship = self.player.rect;
+            let back = ship.x + 4.0;
+            self.exhaust.shape = EmitterShape::Line(
+                (back, ship.y + ship.h * 0.35), (back, ship.y + ship.h * 0.65));
+            self.exhaust.update(dt, &mut self.particles);
+        }
+
+
+        // Move the bullets, and forget about those which left the screen or
+        // hit the asteroid.
+        self.obstacles.clear();
+        self.obstacles.insert(0, self.asteroid.rect);
+
+        let screen_w = phi.output_size().0;
+        let obstacles = &self.obstacles;
+        // The asteroid is round, so that grazing the corners of its sprite is
+        // not a hit.
+        let asteroid = Circle::from_rect(&self.asteroid.rect);
+        let mut asteroid_hit = None;
+
+        self.bullets.retain(|bullet| {
+            bullet.rect.x += BULLET_SPEED * dt;
+
+            if !obstacles.query(bullet.rect).is_empty() && asteroid.collides_with(&bullet.rect) {
+                asteroid_hit = Some((bullet.rect.x + bullet.rect.w, bullet.rect.y + bullet.rect.h / 2.0));
+                return false;
+            }
+
+            bullet.rect.x < screen_w
+        });
+
+        if let Some(impact) = asteroid_hit {
+            // Sparks fly back towards the ship.
+            self.particles.emit(EmitterConfig {
+                position: impact,
+                count: EXPLOSION_PARTICLES,
+                speed: (60.0, 240.0),
+                angle: ::std::f64::consts::PI,
+                spread: ::std::f64::consts::PI,
+                lifetime: (0.3, 0.8),
+                start_color: Color::RGB(255, 230, 120),
+                end_color: Color::RGBA(200, 40, 10, 0),
+                start_size: 4.0,
+                end_size: 1.0,
+            });
+
+            self.asteroid.reset(phi);
+            self.shake.shake(EXPLOSION_SHAKE, EXPLOSION_SHAKE_DURATION);
+            let _ = phi.audio.play(EXPLOSION_SOUND.0);
+            self.score += ASTEROID_POINTS;
+        }
+
+        // Fire a new bullet from the front of the ship, if there is room left,
+        // for as long as the fire key is held.
+        self.fire_cooldown.update(dt);
+
+        if phi.events.action_down(&phi.bindings, Action::Fire) && self.fire_cooldown.try_trigger() {
+            let ship = self.player.rect;
+
+            if let Some(bullet) = self.bullets.acquire() {
+                bullet.rect = Rectangle {
+                    x: ship.x + ship.w,
+                    y: ship.y + (ship.h - BULLET_H) / 2.0,
+                    w: BULLET_W,
+                    h: BULLET_H,
+                };
+
+                let _ = phi.audio.play(LASER_SOUND.0);
+            }
+        }
+
 
         // Update the asteroid
-        self.asteroid.update(phi, elapsed);
+        self.asteroid.update(phi, dt);
+
+        // Move the backgrounds
+        self.bg.update(dt);
 
+        self.shake.update(dt);
+        self.particles.update(dt);
 
+
+        ViewAction::None
+    }
+
+    fn render(&mut self, phi: &mut Phi, _: f64) {
         // Clear the scene
         phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
         phi.renderer.clear();
 
         // Render the Backgrounds
-        self.bg.back.render(&mut phi.renderer, elapsed);
-        self.bg.middle.render(&mut phi.renderer, elapsed);
+        self.bg.render_behind(&mut phi.renderer, PLAYFIELD_Z);
 
-        // Render the bounding box (for debugging purposes)
-        if DEBUG {
-            phi.renderer.set_draw_color(Color::RGB(200, 200, 50));
-            phi.renderer.fill_rect(self.player.rect.to_sdl().unwrap());
-        }
+        // The playfield moves with the screen shake, but the backgrounds do not.
+        let camera = self.shake.apply(&phi.camera);
 
         // Render the ship
-        phi.renderer.copy_sprite(
+        phi.renderer.copy_sprite_world(
             &self.player.sprites[self.player.current as usize],
-            self.player.rect);
+            &camera, self.player.rect);
+
+        // Render the bullets
+        phi.renderer.set_draw_color(Color::RGB(230, 230, 30));
+        for bullet in &self.bullets {
+            if let Some(rect) = camera.to_screen(bullet.rect).to_sdl() {
+                phi.renderer.fill_rect(rect);
+            }
+        }
 
         // Render the asteroid
-        self.asteroid.render(phi);
+        self.asteroid.render(phi, &camera);
+
+        // Render the sparks on top of it
+        self.particles.render_world(&mut phi.renderer, &camera);
 
         // Render the foreground
-        self.bg.front.render(&mut phi.renderer, elapsed);
+        self.bg.render_in_front(&mut phi.renderer, PLAYFIELD_Z);
 
+        // Show the collision boxes on the debug overlay (F3).
+        phi.debug_rect(camera.to_screen(self.player.rect), Color::RGB(200, 200, 50));
+        phi.debug_rect(camera.to_screen(self.asteroid.rect), Color::RGB(230, 80, 80));
+        for bullet in &self.bullets {
+            phi.debug_rect(camera.to_screen(bullet.rect), Color::RGB(80, 200, 230));
+        }
 
-        ViewAction::None
+        phi.debug_line(&format!("Entities: {}", 2 + self.bullets.active_count()));
+        phi.debug_line(&format!("Bullets: {}", self.bullets.active_count()));
+        phi.debug_line(&format!("Particles: {}", self.particles.len()));
+
+        // Render the score, which does not shake
+        let score = format!("Score: {}", self.score);
+        let win_w = phi.output_size().0;
+        phi.render_text_at(&score, Color::RGB(255, 255, 255), win_w - 160.0, 8.0, 20);
+
+        // The pause menu simply does without the game if it cannot be
+        // captured.
+        if let Pausing::Requested = self.pausing {
+            self.pausing = Pausing::Captured(phi.capture_frame().ok());
+        }
     }
 }
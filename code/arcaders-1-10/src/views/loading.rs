@@ -0,0 +1,121 @@
+use ::phi::{DEFAULT_FONT, Phi, View, ViewAction};
+use ::phi::assets::{Preload, Preloader, UPLOADS_PER_FRAME};
+use ::phi::data::Rectangle;
+use ::sdl2::pixels::Color;
+
+
+/// The images used by the game, which are loaded before the main menu shows.
+pub const ASSETS: &'static [&'static str] = &[
+    "assets/starBG.png",
+    "assets/starMG.png",
+    "assets/starFG.png",
+    "assets/spaceship.png",
+    "assets/asteroid.png",
+    "assets/tiles.png",
+];
+
+/// The fonts used by the menus and the game, and their sizes.
+pub const FONTS: &'static [(&'static str, u16)] = &[
+    (DEFAULT_FONT, 20),
+    (DEFAULT_FONT, 24),
+    (DEFAULT_FONT, 32),
+    (DEFAULT_FONT, 42),
+];
+
+const BAR_W: f64 = 400.0;
+const BAR_H: f64 = 16.0;
+const TEXT_SIZE: u16 = 24;
+const ERROR_SIZE: u16 = 16;
+
+
+/// Returns a preloader queuing the assets of the game.
+pub fn game_assets() -> Preloader {
+    let mut preloader = Preloader::new();
+
+    for path in ASSETS {
+        preloader.queue_texture(path);
+    }
+
+    for &(path, size) in FONTS {
+        preloader.queue_font(path, size);
+    }
+
+    preloader
+}
+
+
+/// Loads assets while showing a progress bar, so that the window does not
+/// freeze while they are read.
+///
+/// If some of them could not be loaded, then the errors are listed once the
+/// others are, until the player presses enter to go on anyway.
+pub struct LoadingView {
+    preload: Preload,
+    on_done: Box<dyn Fn(&mut Phi) -> ViewAction>,
+}
+
+impl LoadingView {
+    /// Starts loading the assets of `preloader`, and calls `on_done` once they
+    /// are all in the caches of `Phi`, to know which view comes next.
+    pub fn new(preloader: Preloader, on_done: Box<dyn Fn(&mut Phi) -> ViewAction>) -> LoadingView {
+        LoadingView {
+            preload: preloader.start(),
+            on_done: on_done,
+        }
+    }
+}
+
+impl View for LoadingView {
+    fn update(&mut self, phi: &mut Phi, _: f64) -> ViewAction {
+        if phi.events.now.quit {
+            return ViewAction::Quit(0);
+        }
+
+        if self.preload.is_done() &&
+           (self.preload.failures().is_empty() || phi.events.now.key_enter == Some(true)) {
+            return (self.on_done)(phi);
+        }
+
+        ViewAction::None
+    }
+
+    fn render(&mut self, phi: &mut Phi, _: f64) {
+        self.preload.step(phi, UPLOADS_PER_FRAME);
+
+        let (win_w, win_h) = phi.output_size();
+        let left = (win_w - BAR_W) / 2.0;
+
+        phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
+        phi.renderer.clear();
+
+        if self.preload.is_done() && !self.preload.failures().is_empty() {
+            phi.render_text_at("Some assets could not be loaded. Press enter to continue.",
+                               Color::RGB(220, 220, 220), left, 40.0, TEXT_SIZE);
+
+            for (i, failure) in self.preload.failures().iter().enumerate() {
+                phi.render_text_at(failure, Color::RGB(230, 80, 80),
+                                   left, 80.0 + i as f64 * (ERROR_SIZE as f64 + 6.0), ERROR_SIZE);
+            }
+
+            return;
+        }
+
+        phi.render_text_at("Loading...", Color::RGB(220, 220, 220),
+                           left, win_h / 2.0 - BAR_H - 40.0, TEXT_SIZE);
+
+        let bar = Rectangle {
+            x: left,
+            y: (win_h - BAR_H) / 2.0,
+            w: BAR_W,
+            h: BAR_H,
+        };
+
+        phi.renderer.set_draw_color(Color::RGB(220, 220, 220));
+        phi.renderer.draw_rect(bar.to_sdl().unwrap());
+
+        // Nothing is filled until the first asset is loaded.
+        if let Some(filled) = (Rectangle { w: BAR_W * self.preload.progress(), ..bar }).to_sdl() {
+            phi.renderer.fill_rect(filled);
+        }
+    }
+}
@@ -1,16 +1,23 @@
 use ::phi::{Phi, View, ViewAction};
 use ::phi::data::Rectangle;
-use ::phi::gfx::{CopySprite, Sprite};
+use ::phi::gfx::{Background, CopySprite, Sprite};
+use ::phi::transition::{FadeTransition, SlideLeftTransition};
 use ::sdl2::pixels::Color;
-use ::views::shared::BgSet;
+use ::views::game::{GameState, SAVED_GAME, ShipView};
+use ::views::shared::starfield;
 
 
 const ACTION_FONT: &'static str = "assets/belligerent.ttf";
 
+// The size of the box which holds the labels, and of every label in it.
+const BOX_W: f64 = 360.0;
+const LABEL_H: f64 = 50.0;
 
-struct Action {
+
+/// An entry of the main menu.
+pub struct Action {
     /// The function which should be executed if the action is chosen.
-    func: Box<Fn(&mut Phi, BgSet) -> ViewAction>,
+    func: Box<dyn Fn(&mut Phi, Background) -> ViewAction>,
 
     /// The sprite which is rendered when the player does not focus on this
     /// action's label.
@@ -22,7 +29,13 @@ struct Action {
 }
 
 impl Action {
-    fn new(phi: &mut Phi, label: &'static str, func: Box<Fn(&mut Phi, BgSet) -> ViewAction>) -> Action {
+    /// Creates an entry showing `label`, which runs `func` when it is chosen.
+    /// The function receives the menu's backgrounds, so that the next view
+    /// may keep scrolling them where the menu left off.
+    ///
+    /// Both versions of the label are rendered here, once, so that moving
+    /// the focus does not render any text.
+    pub fn new(phi: &mut Phi, label: &str, func: Box<dyn Fn(&mut Phi, Background) -> ViewAction>) -> Action {
         Action {
             func: func,
             idle_sprite: phi.ttf_str_sprite(label, ACTION_FONT, 32, Color::RGB(220, 220, 220)).unwrap(),
@@ -35,76 +48,168 @@ impl Action {
 pub struct MainMenuView {
     actions: Vec<Action>,
     selected: i8,
-    bg: BgSet,
+    bg: Background,
+    /// Where the mouse cursor was during the last update, so that the focus
+    /// only follows it when it moves, rather than fighting with the keyboard.
+    mouse_pos: (i32, i32),
 }
 
 impl MainMenuView {
     pub fn new(phi: &mut Phi) -> MainMenuView {
-        let bg = BgSet::new(&mut phi.renderer);
+        let bg = starfield(phi);
         MainMenuView::with_backgrounds(phi, bg)
     }
 
-    pub fn with_backgrounds(phi: &mut Phi, bg: BgSet) -> MainMenuView {
+    pub fn with_backgrounds(phi: &mut Phi, bg: Background) -> MainMenuView {
+        let mut actions = Vec::new();
+
+        // Offer to resume the game which the player left, if any.
+        if let Some(state) = phi.load::<GameState>(SAVED_GAME) {
+            actions.push(Action::new(phi, "Continue", Box::new(move |phi, bg| {
+                ViewAction::Transition(
+                    Box::new(ShipView::from_state(phi, bg, &state)),
+                    Box::new(FadeTransition::new(0.5)))
+            })));
+        }
+
+        actions.extend(vec![
+            Action::new(phi, "New Game", Box::new(|phi, bg| {
+                ViewAction::Transition(
+                    Box::new(ShipView::with_backgrounds(phi, bg)),
+                    Box::new(FadeTransition::new(0.5)))
+            })),
+            Action::new(phi, "High Scores", Box::new(|phi, bg| {
+                ViewAction::Transition(
+                    Box::new(::views::scores::ScoreView::with_backgrounds(phi, bg, None)),
+                    Box::new(SlideLeftTransition::new(0.4)))
+            })),
+            Action::new(phi, "Tiles", Box::new(|phi, _| {
+                match ::views::tiles::TilemapView::new(phi) {
+                    Ok(view) => ViewAction::ChangeView(Box::new(view)),
+                    Err(_) => ViewAction::None,
+                }
+            })),
+            Action::new(phi, "Sprites", Box::new(|phi, _| {
+                match ::views::sprites::SpritesView::new(phi) {
+                    Ok(view) => ViewAction::ChangeView(Box::new(view)),
+                    Err(_) => ViewAction::None,
+                }
+            })),
+            Action::new(phi, "Quit", Box::new(|_, _| {
+                ViewAction::Quit(0)
+            })),
+        ]);
+
+        MainMenuView::with_actions(bg, actions)
+    }
+
+    /// Creates a menu listing `actions`, from top to bottom, with the first
+    /// one focused.
+    ///
+    /// Panics if `actions` is empty.
+    pub fn with_actions(bg: Background, actions: Vec<Action>) -> MainMenuView {
+        assert!(!actions.is_empty(), "A menu needs at least one action");
+
         MainMenuView {
-            actions: vec![
-                Action::new(phi, "New Game", Box::new(|phi, bg| {
-                    ViewAction::ChangeView(Box::new(
-                        ::views::game::ShipView::with_backgrounds(phi, bg)))
-                })),
-                Action::new(phi, "Quit", Box::new(|_, _| {
-                    ViewAction::Quit
-                })),
-            ],
+            actions: actions,
             selected: 0,
             bg: bg,
+            mouse_pos: (0, 0),
         }
     }
+
+    /// The region of the screen covered by the `i`th action, which the player
+    /// may click.
+    fn action_box(&self, phi: &Phi, i: usize) -> Rectangle {
+        let (win_w, win_h) = phi.output_size();
+        let box_h = self.actions.len() as f64 * LABEL_H;
+
+        Rectangle {
+            w: BOX_W,
+            h: LABEL_H,
+            x: (win_w - BOX_W) / 2.0,
+            y: (win_h - box_h) / 2.0 + LABEL_H * i as f64,
+        }
+    }
+
+    /// The action under the mouse cursor, if any.
+    fn action_at(&self, phi: &Phi, pos: (i32, i32)) -> Option<usize> {
+        (0..self.actions.len()).find(|&i| self.action_box(phi, i).contains_point(pos))
+    }
 }
 
 impl View for MainMenuView {
-    fn render(&mut self, phi: &mut Phi, elapsed: f64) -> ViewAction {
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
         if phi.events.now.quit || phi.events.now.key_escape == Some(true) {
-            return ViewAction::Quit;
+            return ViewAction::Quit(0);
         }
 
 
         // Execute the currently selected option.
         if phi.events.now.key_space == Some(true) ||
-           phi.events.now.key_enter == Some(true) {
+           phi.events.now.key_enter == Some(true) ||
+           phi.events.now.pad_a == Some(true) ||
+           phi.events.now.pad_start == Some(true) {
             let bg = self.bg.clone();
             return (self.actions[self.selected as usize].func)(phi, bg);
         }
 
+        // Focus the action under the mouse cursor when it moves, and execute
+        // it when it is clicked.
+        let mouse_pos = phi.events.now.mouse_pos;
+        let hovered = self.action_at(phi, mouse_pos);
+
+        if mouse_pos != self.mouse_pos {
+            self.mouse_pos = mouse_pos;
+
+            if let Some(i) = hovered {
+                self.selected = i as i8;
+            }
+        }
+
+        if phi.events.now.mouse_left_clicked() {
+            if let Some(i) = hovered {
+                let bg = self.bg.clone();
+                return (self.actions[i].func)(phi, bg);
+            }
+        }
+
         // Change the selected action using the keyboard.
-        if phi.events.now.key_up == Some(true) {
+        if phi.events.now.key_up == Some(true) ||
+           phi.events.now.pad_up == Some(true) {
             self.selected -= 1;
             if self.selected < 0 {
                 self.selected = self.actions.len() as i8 - 1;
             }
         }
 
-        if phi.events.now.key_down == Some(true) {
+        if phi.events.now.key_down == Some(true) ||
+           phi.events.now.pad_down == Some(true) {
             self.selected += 1;
             if self.selected >= self.actions.len() as i8 {
                 self.selected = 0;
             }
         }
 
+        // Move the backgrounds
+        self.bg.update(dt);
+
+        ViewAction::None
+    }
 
+    fn render(&mut self, phi: &mut Phi, _: f64) {
         // Clear the screen.
         phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
         phi.renderer.clear();
 
         // Render the backgrounds
-        self.bg.back.render(&mut phi.renderer, elapsed);
-        self.bg.middle.render(&mut phi.renderer, elapsed);
-        self.bg.front.render(&mut phi.renderer, elapsed);
+        self.bg.render(&mut phi.renderer);
 
         // Definitions for the menu's layout
         let (win_w, win_h) = phi.output_size();
-        let label_h = 50.0;
+        let label_h = LABEL_H;
         let border_width = 3.0;
-        let box_w = 360.0;
+        let box_w = BOX_W;
         let box_h = self.actions.len() as f64 * label_h;
         let margin_h = 10.0;
 
@@ -146,8 +251,5 @@ impl View for MainMenuView {
                 });
             }
         }
-
-
-        ViewAction::None
     }
 }
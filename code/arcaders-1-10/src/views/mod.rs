@@ -1,3 +1,9 @@
 pub mod game;
+pub mod loading;
 pub mod main_menu;
+pub mod pause;
+pub mod scores;
 pub mod shared;
+pub mod sprites;
+pub mod text_prompt;
+pub mod tiles;
@@ -0,0 +1,77 @@
+use ::phi::{DEFAULT_FONT, Phi, View, ViewAction};
+use ::phi::data::Rectangle;
+use ::phi::gfx::{Background, CopySprite, Sprite};
+use ::sdl2::pixels::Color;
+use ::sdl2::render::BlendMode;
+
+
+/// Shown on top of the game while it is paused. The game stays on the stack,
+/// untouched, and is resumed when this view is popped.
+pub struct PauseView {
+    /// The last frame of the game, if it could be captured.
+    snapshot: Option<Sprite>,
+    /// The backgrounds of the game, so that the main menu scrolls them from
+    /// where they were paused.
+    bg: Background,
+}
+
+impl PauseView {
+    pub fn new(snapshot: Option<Sprite>, bg: Background) -> PauseView {
+        PauseView {
+            snapshot: snapshot,
+            bg: bg,
+        }
+    }
+}
+
+impl View for PauseView {
+    fn update(&mut self, phi: &mut Phi, _: f64) -> ViewAction {
+        if phi.events.now.quit {
+            return ViewAction::Quit(0);
+        }
+
+        if phi.events.now.key_p == Some(true) {
+            return ViewAction::Pop;
+        }
+
+        // Leave the game altogether, rather than the program.
+        if phi.events.now.key_escape == Some(true) {
+            return ViewAction::Reset(Box::new(
+                ::views::main_menu::MainMenuView::with_backgrounds(phi, self.bg.clone())));
+        }
+
+        ViewAction::None
+    }
+
+    fn render(&mut self, phi: &mut Phi, _: f64) {
+        let (win_w, win_h) = phi.output_size();
+        let screen = Rectangle { x: 0.0, y: 0.0, w: win_w, h: win_h };
+
+        phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
+        phi.renderer.clear();
+
+        if let Some(ref snapshot) = self.snapshot {
+            phi.renderer.copy_sprite(snapshot, screen);
+        }
+
+        // Dim the game
+        let blend_mode = phi.renderer.blend_mode();
+        phi.renderer.set_blend_mode(BlendMode::Blend);
+        phi.renderer.set_draw_color(Color::RGBA(0, 0, 0, 128));
+        if let Some(screen) = screen.to_sdl() {
+            phi.renderer.fill_rect(screen);
+        }
+        phi.renderer.set_blend_mode(blend_mode);
+
+        if let Some(label) = phi.ttf_str_sprite("Paused - press P to resume", DEFAULT_FONT, 32,
+                                                Color::RGB(255, 255, 255)) {
+            let (w, h) = label.size();
+            phi.renderer.copy_sprite(&label, Rectangle {
+                w: w,
+                h: h,
+                x: (win_w - w) / 2.0,
+                y: (win_h - h) / 2.0,
+            });
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use ::phi::{DEFAULT_FONT, Phi, View, ViewAction};
+use ::phi::data::Rectangle;
+use ::phi::data::scores::{MAX_SCORES, ScoreBoard};
+use ::phi::gfx::{Background, CopySprite};
+use ::sdl2::pixels::Color;
+use ::std::path::PathBuf;
+
+
+const TITLE_SIZE: u16 = 42;
+const ENTRY_SIZE: u16 = 24;
+const ENTRY_H: f64 = 32.0;
+
+
+/// Where the high scores are kept, in the save directory of `phi`.
+pub fn scores_path(phi: &Phi) -> PathBuf {
+    phi.save_dir.join("scores.json")
+}
+
+
+/// Lists the best scores, and returns to the main menu when escape is pressed.
+pub struct ScoreView {
+    board: ScoreBoard,
+    /// The rank of the score which was just added, if any, so that it stands
+    /// out.
+    highlighted: Option<usize>,
+    bg: Background,
+}
+
+impl ScoreView {
+    /// Shows the board over `bg`. If `new_score` is given, then it is added to
+    /// the board, which is saved right away.
+    pub fn with_backgrounds(phi: &Phi, bg: Background, new_score: Option<(String, u64)>) -> ScoreView {
+        let path = scores_path(phi);
+        let mut board = ScoreBoard::load(&path);
+
+        let highlighted = new_score.and_then(|(name, score)| {
+            let rank = board.insert(&name, score);
+            // If the file cannot be written, then the score is only lost once
+            // the game quits.
+            let _ = board.save(&path);
+
+            if rank < MAX_SCORES { Some(rank) } else { None }
+        });
+
+        ScoreView {
+            board: board,
+            highlighted: highlighted,
+            bg: bg,
+        }
+    }
+}
+
+impl View for ScoreView {
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
+        if phi.events.now.quit {
+            return ViewAction::Quit(0);
+        }
+
+        if phi.events.now.key_escape == Some(true) ||
+           phi.events.now.key_enter == Some(true) ||
+           phi.events.now.pad_back == Some(true) {
+            return ViewAction::ChangeView(Box::new(
+                ::views::main_menu::MainMenuView::with_backgrounds(phi, self.bg.clone())));
+        }
+
+        self.bg.update(dt);
+
+        ViewAction::None
+    }
+
+    fn render(&mut self, phi: &mut Phi, _: f64) {
+        let (win_w, win_h) = phi.output_size();
+
+        phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
+        phi.renderer.clear();
+
+        self.bg.render(&mut phi.renderer);
+
+        let top = (win_h - ENTRY_H * (MAX_SCORES + 2) as f64) / 2.0;
+
+        if let Some(title) = phi.ttf_str_sprite("High Scores", DEFAULT_FONT, TITLE_SIZE,
+                                                Color::RGB(255, 255, 255)) {
+            let (w, h) = title.size();
+            phi.renderer.copy_sprite(&title, Rectangle { x: (win_w - w) / 2.0, y: top, w: w, h: h });
+        }
+
+        if self.board.scores().is_empty() {
+            phi.render_text_at("Nobody has played yet", Color::RGB(220, 220, 220),
+                               (win_w - 240.0) / 2.0, top + ENTRY_H * 2.0, ENTRY_SIZE);
+        }
+
+        for (rank, entry) in self.board.scores().iter().enumerate() {
+            let color =
+                if self.highlighted == Some(rank) { Color::RGB(255, 255, 0) }
+                else { Color::RGB(220, 220, 220) };
+
+            let y = top + ENTRY_H * (rank + 2) as f64;
+            let line = format!("{:>2}. {}", rank + 1, entry.name);
+
+            phi.render_text_at(&line, color, win_w / 2.0 - 200.0, y, ENTRY_SIZE);
+
+            // The scores are aligned on the right.
+            if let Some(score) = phi.ttf_str_sprite(&entry.score.to_string(), DEFAULT_FONT, ENTRY_SIZE, color) {
+                let (w, h) = score.size();
+                phi.renderer.copy_sprite(&score, Rectangle { x: win_w / 2.0 + 200.0 - w, y: y, w: w, h: h });
+            }
+        }
+    }
+}
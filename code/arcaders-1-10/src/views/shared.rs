@@ -1,75 +1,18 @@
-use ::phi::data::Rectangle;
-use ::phi::gfx::{CopySprite, Sprite};
-use ::sdl2::render::Renderer;
+use ::phi::Phi;
+use ::phi::gfx::{Background, ParallaxLayer};
 
 
-#[derive(Clone)]
-pub struct Background {
-    pub pos: f64,
-    // The amount of pixels moved to the left every second
-    pub vel: f64,
-    pub sprite: Sprite,
-}
-
-impl Background {
-    pub fn render(&mut self, renderer: &mut Renderer, elapsed: f64) {
-        // We define a logical position as depending solely on the time and the
-        // dimensions of the image, not on the screen's size.
-        let size = self.sprite.size();
-        self.pos += self.vel * elapsed;
-        if self.pos > size.0 {
-            self.pos -= size.0;
-        }
-
-        // We determine the scale ratio of the window to the sprite.
-        let (win_w, win_h) = renderer.output_size().unwrap();
-        let scale = win_h as f64 / size.1;
-
-        // We render as many copies of the background as necessary to fill
-        // the screen.
-        let mut physical_left = -self.pos * scale;
-
-        while physical_left < win_w as f64 {
-            renderer.copy_sprite(&self.sprite, Rectangle {
-                x: physical_left,
-                y: 0.0,
-                w: size.0 * scale,
-                h: win_h as f64,
-            });
+/// The depth of the player's ship and of everything it can collide with. The
+/// layers of the background with a greater depth are drawn on top of them.
+pub const PLAYFIELD_Z: i32 = 0;
 
-            physical_left += size.0 * scale;
-        }
-    }
-}
-
-
-/// Utility structure to reduce the number of arguments certain methods accept,
-/// and the amount of `clone` calls required when switching views.
-#[derive(Clone)]
-pub struct BgSet {
-    pub back: Background,
-    pub middle: Background,
-    pub front: Background,
-}
 
-impl BgSet {
-    pub fn new(renderer: &mut Renderer) -> BgSet {
-        BgSet {
-            back: Background {
-                pos: 0.0,
-                vel: 20.0,
-                sprite: Sprite::load(renderer, "assets/starBG.png").unwrap(),
-            },
-            middle: Background {
-                pos: 0.0,
-                vel: 40.0,
-                sprite: Sprite::load(renderer, "assets/starMG.png").unwrap(),
-            },
-            front: Background {
-                pos: 0.0,
-                vel: 80.0,
-                sprite: Sprite::load(renderer, "assets/starFG.png").unwrap(),
-            },
-        }
-    }
+/// The starfield scrolling behind the menus and the game. It is passed from
+/// one view to the next, so that it does not jump when the view changes.
+pub fn starfield(phi: &mut Phi) -> Background {
+    Background::new(vec![
+        ParallaxLayer::new(phi.sprite("assets/starBG.png").unwrap(), 20.0, PLAYFIELD_Z - 2),
+        ParallaxLayer::new(phi.sprite("assets/starMG.png").unwrap(), 40.0, PLAYFIELD_Z - 1),
+        ParallaxLayer::new(phi.sprite("assets/starFG.png").unwrap(), 80.0, PLAYFIELD_Z + 1),
+    ])
 }
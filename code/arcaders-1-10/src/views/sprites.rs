@@ -0,0 +1,120 @@
+use ::phi::{Phi, View, ViewAction};
+use ::phi::data::Rectangle;
+use ::phi::gfx::{CopySprite, Sprite};
+use ::phi::math::{Transform2f, Vec2f};
+use ::sdl2::pixels::Color;
+use ::sdl2::rect::Point;
+use ::views::game::{SHIP_H, SHIP_W};
+
+
+/// The ships are shown twice as large as in the game, to make them easier to
+/// follow.
+const SCALE: f64 = 2.0;
+
+/// How far the mirrored ships rock back and forth, in degrees, and how many
+/// times they do so every second.
+const ROCK_ANGLE: f64 = 20.0;
+const ROCK_SPEED: f64 = 0.5;
+
+
+/// Shows what `copy_sprite_ex` can do with a single image: a ship which turns
+/// towards the mouse cursor around its center, and mirrored copies of it which
+/// rock around their tail. Escape returns to the main menu.
+pub struct SpritesView {
+    ship: Sprite,
+    /// Where the nose of the ship points, in degrees, clockwise from the
+    /// right, which is where it points in the image.
+    angle: f64,
+    /// The time since the view was opened, in seconds.
+    elapsed: f64,
+}
+
+impl SpritesView {
+    pub fn new(phi: &mut Phi) -> Result<SpritesView, String> {
+        let ship = phi.sprite("assets/spaceship.png")?
+            .region(Rectangle { x: 0.0, y: SHIP_H, w: SHIP_W, h: SHIP_H })
+            .ok_or("The spaceship's image is too small".to_string())?;
+
+        Ok(SpritesView {
+            ship: ship,
+            angle: 0.0,
+            elapsed: 0.0,
+        })
+    }
+
+    /// Where the ship which follows the mouse is drawn.
+    fn ship_rect(&self, phi: &Phi) -> Rectangle {
+        let (w, h) = phi.output_size();
+
+        Rectangle {
+            x: (w - SHIP_W * SCALE) / 2.0,
+            y: (h - SHIP_H * SCALE) / 2.0,
+            w: SHIP_W * SCALE,
+            h: SHIP_H * SCALE,
+        }
+    }
+}
+
+impl View for SpritesView {
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
+        if phi.events.now.quit {
+            return ViewAction::Quit(0);
+        }
+
+        if phi.events.now.key_escape == Some(true) {
+            return ViewAction::ChangeView(Box::new(::views::main_menu::MainMenuView::new(phi)));
+        }
+
+        let (mx, my) = phi.events.now.mouse_pos;
+        let towards_mouse = Vec2f::new(mx as f64, my as f64) - self.ship_rect(phi).center();
+
+        // Keep the last angle while the cursor is right over the ship.
+        if towards_mouse.len_squared() > 1.0 {
+            self.angle = towards_mouse.angle().to_degrees();
+        }
+
+        self.elapsed += dt;
+
+        ViewAction::None
+    }
+
+    fn render(&mut self, phi: &mut Phi, _: f64) {
+        phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
+        phi.renderer.clear();
+
+        // The ship in the middle turns around its center, which is what
+        // `copy_sprite_ex` does when it is not given one.
+        let dest = self.ship_rect(phi);
+        phi.renderer.copy_sprite_ex(&self.ship, dest, self.angle, None, false, false);
+
+        // Aim from the nose of the ship, wherever it is once turned.
+        let nose = (Transform2f::translation(dest.center()) * Transform2f::rotation(self.angle.to_radians()))
+            .apply(Vec2f::new(dest.w / 2.0, 0.0));
+        let (mx, my) = phi.events.now.mouse_pos;
+        phi.renderer.set_draw_color(Color::RGB(230, 40, 40));
+        phi.renderer.draw_line(Point::from(nose), Point::new(mx, my));
+
+        // The ships along the sides are mirrored rather than drawn again, so
+        // that the one on the right faces left, and those at the bottom are
+        // upside down. They rock around the middle of their tail.
+        let (w, h) = phi.output_size();
+        let rock = ROCK_ANGLE * (self.elapsed * ROCK_SPEED * 2.0 * ::std::f64::consts::PI).sin();
+        let margin = 32.0;
+
+        for &(flip_h, flip_v) in &[(false, false), (true, false), (false, true), (true, true)] {
+            let dest = Rectangle {
+                x: if flip_h { w - margin - SHIP_W } else { margin },
+                y: if flip_v { h - margin - SHIP_H } else { margin },
+                w: SHIP_W,
+                h: SHIP_H,
+            };
+
+            // The tail is on the right of a ship which faces left.
+            let tail = (if flip_h { SHIP_W } else { 0.0 }, SHIP_H / 2.0);
+            phi.renderer.copy_sprite_ex(&self.ship, dest, rock, Some(tail), flip_h, flip_v);
+        }
+
+        phi.render_text_at("Move the mouse to turn the ship", Color::RGB(255, 255, 255),
+                           margin, h / 2.0 + SHIP_H * SCALE, 20);
+    }
+}
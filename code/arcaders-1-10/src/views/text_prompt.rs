@@ -0,0 +1,135 @@
+use ::phi::{DEFAULT_FONT, Phi, View, ViewAction};
+use ::phi::data::Rectangle;
+use ::phi::gfx::CopySprite;
+use ::sdl2::keyboard::Keycode;
+use ::sdl2::pixels::Color;
+
+
+const PROMPT_SIZE: u16 = 32;
+const TEXT_SIZE: u16 = 28;
+const TEXT_COLOR: Color = Color::RGB(255, 255, 255);
+
+/// The number of times per second that the caret appears and disappears.
+const CARET_BLINKS: f64 = 2.0;
+const CARET_W: f64 = 2.0;
+
+
+/// Asks the player to type some text, for instance their name for the high
+/// scores. Pressing enter submits the text, and escape pops the view without
+/// submitting anything.
+pub struct TextPromptView {
+    prompt: String,
+    text: String,
+    /// The largest number of characters which may be typed, rather than of
+    /// bytes, since some letters take several bytes.
+    max_len: usize,
+    on_submit: Box<dyn Fn(&mut Phi, String) -> ViewAction>,
+
+    /// The time since the caret was last shown, in seconds, so that it does
+    /// not disappear while the player is typing.
+    caret_time: f64,
+}
+
+impl TextPromptView {
+    /// Creates a prompt showing `prompt`, which calls `on_submit` with the
+    /// text typed, at most `max_len` characters, when enter is pressed.
+    pub fn new(prompt: &str, max_len: usize, on_submit: Box<dyn Fn(&mut Phi, String) -> ViewAction>) -> TextPromptView {
+        TextPromptView {
+            prompt: prompt.to_string(),
+            text: String::new(),
+            max_len: max_len,
+            on_submit: on_submit,
+            caret_time: 0.0,
+        }
+    }
+}
+
+impl View for TextPromptView {
+    fn on_enter(&mut self, phi: &mut Phi) {
+        phi.start_text_input();
+    }
+
+    fn on_exit(&mut self, phi: &mut Phi) {
+        phi.stop_text_input();
+    }
+
+    fn pause(&mut self, phi: &mut Phi) {
+        phi.stop_text_input();
+    }
+
+    fn resume(&mut self, phi: &mut Phi) {
+        phi.start_text_input();
+    }
+
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
+        if phi.events.now.quit {
+            return ViewAction::Quit(0);
+        }
+
+        if phi.events.now.key_escape == Some(true) {
+            return ViewAction::Pop;
+        }
+
+        if phi.events.now.key_enter == Some(true) {
+            return (self.on_submit)(phi, self.text.clone());
+        }
+
+        self.caret_time += dt;
+
+        // `pop` removes a whole character, however many bytes it takes.
+        if phi.events.now.key(Keycode::Backspace) == Some(true) {
+            self.text.pop();
+            self.caret_time = 0.0;
+        }
+
+        let room = self.max_len.saturating_sub(self.text.chars().count());
+        if let Some(typed) = phi.events.now.text_input() {
+            self.text.extend(typed.chars().take(room));
+            self.caret_time = 0.0;
+        }
+
+        ViewAction::None
+    }
+
+    fn render(&mut self, phi: &mut Phi, _: f64) {
+        let (win_w, win_h) = phi.output_size();
+
+        phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
+        phi.renderer.clear();
+
+        if let Some(prompt) = phi.ttf_str_sprite(&self.prompt, DEFAULT_FONT, PROMPT_SIZE, TEXT_COLOR) {
+            let (w, h) = prompt.size();
+            phi.renderer.copy_sprite(&prompt, Rectangle {
+                w: w,
+                h: h,
+                x: (win_w - w) / 2.0,
+                y: win_h / 2.0 - h - 8.0,
+            });
+        }
+
+        // Nothing can be rendered for an empty string, but the caret must
+        // still be shown.
+        let text = if self.text.is_empty() { None }
+                   else { phi.ttf_str_sprite(&self.text, DEFAULT_FONT, TEXT_SIZE, TEXT_COLOR) };
+
+        let line_h = TEXT_SIZE as f64 + 4.0;
+        let text_w = text.as_ref().map(|text| text.size().0).unwrap_or(0.0);
+        let left = (win_w - text_w - CARET_W) / 2.0;
+        let top = win_h / 2.0 + 8.0;
+
+        if let Some(ref text) = text {
+            let (w, h) = text.size();
+            phi.renderer.copy_sprite(text, Rectangle { x: left, y: top, w: w, h: h });
+        }
+
+        if (self.caret_time * CARET_BLINKS) as u64 % 2 == 0 {
+            phi.renderer.set_draw_color(TEXT_COLOR);
+            phi.renderer.fill_rect(Rectangle {
+                x: left + text_w,
+                y: top,
+                w: CARET_W,
+                h: line_h,
+            }.to_sdl().unwrap());
+        }
+    }
+}
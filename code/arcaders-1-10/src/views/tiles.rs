@@ -0,0 +1,110 @@
+use ::phi::{Phi, View, ViewAction};
+use ::phi::camera::Camera;
+use ::phi::data::Rectangle;
+use ::phi::input::Action;
+use ::phi::tilemap::Tilemap;
+use ::sdl2::pixels::Color;
+
+
+const LEVEL_PATH: &'static str = "assets/level.csv";
+const TILES_PATH: &'static str = "assets/tiles.png";
+const TILE_SIDE: u32 = 32;
+
+/// The rocks and the brick walls.
+const SOLID_TILES: [u32; 2] = [1, 4];
+
+/// Pixels scrolled every second while an arrow key is held.
+const SCROLL_SPEED: f64 = 240.0;
+
+/// The side of the marker at the center of the screen, which turns red over a
+/// solid tile.
+const MARKER_SIDE: f64 = 12.0;
+
+
+/// Shows a level made of tiles, which the player scrolls through with the
+/// arrow keys. Escape returns to the main menu.
+pub struct TilemapView {
+    map: Tilemap,
+    /// The view has a camera of its own, so that the one of `Phi`, which the
+    /// game uses, is left where it was.
+    camera: Camera,
+}
+
+impl TilemapView {
+    pub fn new(phi: &mut Phi) -> Result<TilemapView, String> {
+        let mut map = Tilemap::from_csv(phi, LEVEL_PATH, TILES_PATH, TILE_SIDE, TILE_SIDE)?;
+        map.set_solid(SOLID_TILES.iter().cloned());
+
+        let (w, h) = phi.output_size();
+        let mut camera = Camera::new(w, h);
+        camera.bounds = Some(map.bounds());
+        camera.clamp();
+
+        Ok(TilemapView {
+            map: map,
+            camera: camera,
+        })
+    }
+}
+
+impl View for TilemapView {
+    fn update(&mut self, phi: &mut Phi, dt: f64) -> ViewAction {
+        if phi.events.now.quit {
+            return ViewAction::Quit(0);
+        }
+
+        if phi.events.now.key_escape == Some(true) {
+            return ViewAction::ChangeView(Box::new(::views::main_menu::MainMenuView::new(phi)));
+        }
+
+        let axis = |negative: Action, positive: Action| {
+            let (negative, positive) = (phi.events.action_down(&phi.bindings, negative),
+                                        phi.events.action_down(&phi.bindings, positive));
+            match (negative, positive) {
+                (true, false) => -1.0,
+                (false, true) => 1.0,
+                _ => 0.0,
+            }
+        };
+
+        let dx = axis(Action::MoveLeft, Action::MoveRight);
+        let dy = axis(Action::MoveUp, Action::MoveDown);
+
+        // Follow the size of the window, which may change at any time.
+        let (w, h) = phi.output_size();
+        self.camera.w = w;
+        self.camera.h = h;
+        self.camera.x += dx * SCROLL_SPEED * dt;
+        self.camera.y += dy * SCROLL_SPEED * dt;
+        self.camera.clamp();
+
+        ViewAction::None
+    }
+
+    fn render(&mut self, phi: &mut Phi, _: f64) {
+        phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
+        phi.renderer.clear();
+
+        self.map.render(phi, &self.camera);
+
+        let (cx, cy) = self.camera.center();
+        let marker = Rectangle {
+            x: cx - MARKER_SIDE / 2.0,
+            y: cy - MARKER_SIDE / 2.0,
+            w: MARKER_SIDE,
+            h: MARKER_SIDE,
+        };
+
+        phi.renderer.set_draw_color(
+            if self.map.rect_collides(marker) { Color::RGB(230, 40, 40) }
+            else { Color::RGB(255, 255, 255) });
+        phi.renderer.draw_rect(self.camera.to_screen(marker).to_sdl().unwrap());
+
+        let tile = match self.map.tile_at(cx, cy) {
+            Some(0) | None => "empty".to_string(),
+            Some(index) => index.to_string(),
+        };
+
+        phi.render_text_at(&format!("Tile: {}", tile), Color::RGB(255, 255, 255), 8.0, 8.0, 20);
+    }
+}